@@ -0,0 +1,452 @@
+use crate::logging::LogFormat;
+use crate::notify::{EventKind, SmtpTls};
+use crate::password::{PasswordEncoding, Secret};
+use crate::{
+    Args, LoginBodyFormat, LoginMethod, OnFailure, RebootBodyMode, RouterCommand, TimestampFormat,
+    TlsVersion,
+};
+use anyhow::{Context, Result};
+use chrono_tz::Tz;
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `--config` 指向的 TOML 文件；字段与命令行参数一一对应，均为可选。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub password: Option<Secret>,
+    pub username: Option<String>,
+    pub host: Option<String>,
+    pub login_path: Option<String>,
+    pub reboot_path: Option<String>,
+    pub reboot_referer: Option<String>,
+    pub status_path: Option<String>,
+    pub clients_path: Option<String>,
+    pub password_encoding: Option<PasswordEncoding>,
+    pub login_token: Option<String>,
+    pub auto_token: Option<bool>,
+    pub login_success_marker: Option<String>,
+    pub login_failure_marker: Option<Vec<String>>,
+    pub frashnum: Option<String>,
+    pub cookie_file: Option<PathBuf>,
+    pub insecure_tls: Option<bool>,
+    pub ca_cert: Option<PathBuf>,
+    pub proxy: Option<String>,
+    pub no_proxy: Option<bool>,
+    pub reboot_timestamp: Option<bool>,
+    pub timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub retry_base_delay_ms: Option<u64>,
+    pub verify_reboot: Option<bool>,
+    pub poll_interval_secs: Option<u64>,
+    pub reboot_wait_secs: Option<u64>,
+    pub cron: Option<Vec<String>>,
+    pub run_now: Option<bool>,
+    pub parallel: Option<bool>,
+    pub webhook_url: Option<String>,
+    pub telegram_bot_token: Option<String>,
+    pub telegram_chat_id: Option<String>,
+    pub notify_on: Option<Vec<EventKind>>,
+    pub dry_run: Option<bool>,
+    pub min_uptime_hours: Option<u64>,
+    pub max_log_body: Option<usize>,
+    pub log_format: Option<LogFormat>,
+    pub logout_path: Option<String>,
+    pub jitter_secs: Option<u64>,
+    pub metrics_addr: Option<String>,
+    pub health_addr: Option<String>,
+    pub skip_if_clients_above: Option<usize>,
+    pub strict_client_check: Option<bool>,
+    pub timestamp_param: Option<String>,
+    pub timestamp_format: Option<TimestampFormat>,
+    pub reboot_payload: Option<String>,
+    pub reboot_cmdtype: Option<String>,
+    pub password_file: Option<PathBuf>,
+    pub max_runs: Option<u64>,
+    pub count_run_now: Option<bool>,
+    pub state_file: Option<PathBuf>,
+    pub catch_up: Option<bool>,
+    pub headers: Option<Vec<String>>,
+    pub user_agent: Option<String>,
+    pub interval: Option<String>,
+    pub lock_file: Option<PathBuf>,
+    pub discord_webhook_url: Option<String>,
+    pub slack_webhook_url: Option<String>,
+    pub ntfy_url: Option<String>,
+    pub ntfy_priority: Option<u8>,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_tls: Option<SmtpTls>,
+    pub smtp_user: Option<String>,
+    pub smtp_pass: Option<String>,
+    pub email_from: Option<String>,
+    pub email_to: Option<Vec<String>>,
+    pub once: Option<bool>,
+    pub timezone: Option<Tz>,
+    pub post_login_delay_ms: Option<u64>,
+    pub reboot_verify_retries: Option<u32>,
+    pub reboot_success_field: Option<String>,
+    pub reboot_success_value: Option<String>,
+    pub max_redirects: Option<usize>,
+    pub verify_host: Option<bool>,
+    pub mqtt_broker: Option<String>,
+    pub mqtt_topic: Option<String>,
+    pub mqtt_user: Option<String>,
+    pub mqtt_pass: Option<String>,
+    pub min_reboot_interval: Option<String>,
+    pub preflight: Option<bool>,
+    pub influx_url: Option<String>,
+    pub influx_token: Option<String>,
+    pub influx_org: Option<String>,
+    pub influx_bucket: Option<String>,
+    pub tcp_keepalive_secs: Option<u64>,
+    pub pool_idle_timeout_secs: Option<u64>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub field_username: Option<String>,
+    pub field_password: Option<String>,
+    pub field_token: Option<String>,
+    pub field_action: Option<String>,
+    pub field_frashnum: Option<String>,
+    pub login_method: Option<LoginMethod>,
+    pub retry_deadline: Option<String>,
+    pub no_reboot: Option<bool>,
+    pub reboot_confirm_path: Option<String>,
+    pub reboot_confirm_field: Option<String>,
+    pub gotify_url: Option<String>,
+    pub gotify_token: Option<String>,
+    pub pushover_token: Option<String>,
+    pub pushover_user: Option<String>,
+    pub control_addr: Option<String>,
+    pub control_token: Option<String>,
+    pub accept_language: Option<String>,
+    pub startup_grace: Option<String>,
+    pub http_auth_user: Option<String>,
+    pub http_auth_pass: Option<Secret>,
+    pub user_agent_rotate: Option<bool>,
+    pub keyring_service: Option<String>,
+    pub on_failure: Option<OnFailure>,
+    pub otlp_endpoint: Option<String>,
+    #[serde(rename = "command")]
+    pub maintenance_command: Option<RouterCommand>,
+    pub validate_path: Option<String>,
+    pub print_request: Option<bool>,
+    pub pre_run_cmd: Option<String>,
+    pub post_run_cmd: Option<String>,
+    pub auto_detect: Option<bool>,
+    pub retry_on_failure_after: Option<String>,
+    pub max_failure_retries: Option<u32>,
+    pub matrix_homeserver: Option<String>,
+    pub matrix_token: Option<String>,
+    pub matrix_room_id: Option<String>,
+    pub tls_min_version: Option<TlsVersion>,
+    pub tls_sni: Option<String>,
+    pub reboot_settle_secs: Option<u64>,
+    pub trace_http: Option<bool>,
+    pub login_body_format: Option<LoginBodyFormat>,
+    pub connect_timeout_secs: Option<u64>,
+    pub summary: Option<bool>,
+    pub auto_frashnum: Option<bool>,
+    pub csrf_header_name: Option<String>,
+    pub require_csrf: Option<bool>,
+    pub reboot_body_mode: Option<RebootBodyMode>,
+    pub reboot_field_name: Option<String>,
+    pub verbose: Option<bool>,
+    /// 多台路由器：每个 `[[routers]]` 条目覆盖上面的公共设置。
+    pub routers: Vec<RouterEntry>,
+}
+
+/// `[[routers]]` 中的一台路由器；未填写的字段沿用顶层/命令行的值。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RouterEntry {
+    pub name: Option<String>,
+    pub host: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub login_path: Option<String>,
+    pub reboot_path: Option<String>,
+    pub reboot_referer: Option<String>,
+    pub status_path: Option<String>,
+    pub clients_path: Option<String>,
+    pub login_token: Option<String>,
+    pub frashnum: Option<String>,
+}
+
+impl RouterEntry {
+    /// 在公共参数基础上套用本条目的设置，得到这台路由器的完整参数。
+    pub fn apply(self, base: &Args) -> (String, Args) {
+        let mut args = base.clone();
+        macro_rules! set {
+            ($($field:ident),*) => {
+                $(if let Some(v) = self.$field { args.$field = v; })*
+            };
+        }
+        set!(
+            host,
+            username,
+            login_path,
+            reboot_path,
+            reboot_referer,
+            status_path,
+            clients_path,
+            login_token,
+            frashnum
+        );
+        if self.password.is_some() {
+            args.password = self.password;
+        }
+        let name = self.name.unwrap_or_else(|| args.host.clone());
+        (name, args)
+    }
+}
+
+pub fn load(path: &Path) -> Result<FileConfig> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("reading config file {}", path.display()))?;
+    parse(&text).with_context(|| format!("parsing config file {}", path.display()))
+}
+
+fn parse(text: &str) -> Result<FileConfig> {
+    // serde_path_to_error 会在类型不匹配时给出出错的键路径（例如 `timeout_secs`）。
+    let de = toml::Deserializer::new(text);
+    serde_path_to_error::deserialize(de).map_err(|e| {
+        let path = e.path().to_string();
+        anyhow::anyhow!("invalid value for `{}`: {}", path, e.into_inner())
+    })
+}
+
+/// 仅当命令行（或环境变量）没有显式给出时，才使用配置文件中的值。
+fn explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+fn fill<T>(matches: &ArgMatches, id: &str, target: &mut T, value: Option<T>) {
+    if let Some(v) = value
+        && !explicit(matches, id)
+    {
+        *target = v;
+    }
+}
+
+macro_rules! merge_fields {
+    ($matches:expr, $args:expr, $file:expr; $($field:ident),* $(,)?) => {
+        $(fill($matches, stringify!($field), &mut $args.$field, $file.$field);)*
+    };
+}
+
+/// 命令行里本身就是 `Option<T>` 的参数。
+macro_rules! merge_optional_fields {
+    ($matches:expr, $args:expr, $file:expr; $($field:ident),* $(,)?) => {
+        $(fill($matches, stringify!($field), &mut $args.$field, $file.$field.map(Some));)*
+    };
+}
+
+/// 合并配置文件与命令行：命令行 > 环境变量（如 ROUTER_PASSWORD）> 配置文件 > 默认值。
+pub fn merge(args: &mut Args, file: FileConfig, matches: &ArgMatches) {
+    merge_optional_fields!(matches, args, file;
+        password,
+        login_success_marker,
+        cookie_file,
+        ca_cert,
+        proxy,
+        webhook_url,
+        telegram_bot_token,
+        telegram_chat_id,
+        min_uptime_hours,
+        logout_path,
+        metrics_addr,
+        health_addr,
+        skip_if_clients_above,
+        reboot_payload,
+        password_file,
+        max_runs,
+        state_file,
+        interval,
+        lock_file,
+        discord_webhook_url,
+        slack_webhook_url,
+        ntfy_url,
+        smtp_host,
+        smtp_port,
+        smtp_tls,
+        smtp_user,
+        smtp_pass,
+        email_from,
+        timezone,
+        mqtt_broker,
+        mqtt_user,
+        mqtt_pass,
+        min_reboot_interval,
+        influx_url,
+        influx_token,
+        influx_org,
+        influx_bucket,
+        tcp_keepalive_secs,
+        pool_max_idle_per_host,
+        retry_deadline,
+        reboot_confirm_path,
+        gotify_url,
+        gotify_token,
+        pushover_token,
+        pushover_user,
+        control_addr,
+        control_token,
+        startup_grace,
+        http_auth_user,
+        http_auth_pass,
+        keyring_service,
+        otlp_endpoint,
+        validate_path,
+        pre_run_cmd,
+        post_run_cmd,
+        retry_on_failure_after,
+        matrix_homeserver,
+        matrix_token,
+        matrix_room_id,
+        tls_min_version,
+        tls_sni,
+        connect_timeout_secs,
+        csrf_header_name,
+        reboot_field_name,
+    );
+    merge_fields!(matches, args, file;
+        username,
+        host,
+        login_path,
+        reboot_path,
+        reboot_referer,
+        status_path,
+        clients_path,
+        password_encoding,
+        login_token,
+        auto_token,
+        login_failure_marker,
+        frashnum,
+        insecure_tls,
+        no_proxy,
+        reboot_timestamp,
+        timeout_secs,
+        max_retries,
+        retry_base_delay_ms,
+        verify_reboot,
+        poll_interval_secs,
+        reboot_wait_secs,
+        cron,
+        run_now,
+        parallel,
+        notify_on,
+        dry_run,
+        max_log_body,
+        log_format,
+        jitter_secs,
+        strict_client_check,
+        timestamp_param,
+        timestamp_format,
+        reboot_cmdtype,
+        count_run_now,
+        catch_up,
+        headers,
+        user_agent,
+        ntfy_priority,
+        email_to,
+        once,
+        post_login_delay_ms,
+        reboot_verify_retries,
+        reboot_success_field,
+        reboot_success_value,
+        max_redirects,
+        verify_host,
+        mqtt_topic,
+        preflight,
+        pool_idle_timeout_secs,
+        field_username,
+        field_password,
+        field_token,
+        field_action,
+        field_frashnum,
+        login_method,
+        no_reboot,
+        reboot_confirm_field,
+        accept_language,
+        user_agent_rotate,
+        on_failure,
+        maintenance_command,
+        print_request,
+        auto_detect,
+        max_failure_retries,
+        reboot_settle_secs,
+        trace_http,
+        login_body_format,
+        summary,
+        auto_frashnum,
+        require_csrf,
+        reboot_body_mode,
+        verbose,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn merged(cli: &[&str], toml: &str) -> Args {
+        let argv = std::iter::once("tianyi-auto").chain(cli.iter().copied());
+        let matches = Args::command().try_get_matches_from(argv).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        merge(&mut args, parse(toml).unwrap(), &matches);
+        args
+    }
+
+    #[test]
+    fn config_file_fills_unset_flags() {
+        let args = merged(&[], "host = \"http://10.0.0.1\"\ntimeout_secs = 30\n");
+        assert_eq!(args.host, "http://10.0.0.1");
+        assert_eq!(args.timeout_secs, 30);
+        assert_eq!(args.username, "useradmin");
+    }
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        let args = merged(
+            &["--host", "http://10.0.0.2", "--timeout-secs", "5"],
+            "host = \"http://10.0.0.1\"\ntimeout_secs = 30\n",
+        );
+        assert_eq!(args.host, "http://10.0.0.2");
+        assert_eq!(args.timeout_secs, 5);
+    }
+
+    #[test]
+    fn invalid_value_names_key_path() {
+        let err = parse("timeout_secs = \"ten\"\n").unwrap_err();
+        assert!(err.to_string().contains("`timeout_secs`"), "{err}");
+
+        let err = parse("[[routers]]\nhost = 5\n").unwrap_err();
+        assert!(err.to_string().contains("`routers[0].host`"), "{err}");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = parse("hots = \"http://10.0.0.1\"\n").unwrap_err();
+        assert!(err.to_string().contains("hots"), "{err}");
+    }
+
+    #[test]
+    fn router_entry_overrides_shared_settings() {
+        let base = merged(&["--username", "admin"], "");
+        let entry = RouterEntry {
+            host: Some("http://10.0.0.3".into()),
+            ..RouterEntry::default()
+        };
+        let (name, args) = entry.apply(&base);
+        assert_eq!(name, "http://10.0.0.3");
+        assert_eq!(args.host, "http://10.0.0.3");
+        assert_eq!(args.username, "admin");
+    }
+}
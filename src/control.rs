@@ -0,0 +1,182 @@
+use crate::Args;
+use crate::http_server::Reply;
+use crate::signals::Trigger;
+use crate::state::RunState;
+use anyhow::{Result, bail};
+use std::sync::{Arc, Mutex};
+use tiny_http::{Method, Request};
+use tracing::{info, warn};
+
+/// `--control-addr`：供脚本触发重启（`POST /reboot`）和查看最近一次结果（`GET /status`）。
+/// 重启请求只是置位与 SIGHUP 相同的触发标志，真正的运行仍在调度线程上执行，不会与计划运行重叠。
+pub struct Control {
+    token: String,
+    trigger: Trigger,
+    last_state: Arc<Mutex<Option<RunState>>>,
+}
+
+/// 开启控制接口时必须同时设置 `--control-token`。
+pub fn check_args(args: &Args) -> Result<()> {
+    if args.control_addr.is_some() && args.control_token.as_deref().is_none_or(str::is_empty) {
+        bail!("--control-addr requires --control-token");
+    }
+    Ok(())
+}
+
+impl Control {
+    pub fn new(token: String, trigger: Trigger, last_state: Arc<Mutex<Option<RunState>>>) -> Self {
+        Control {
+            token,
+            trigger,
+            last_state,
+        }
+    }
+
+    pub fn handle(&self, request: &Request) -> Reply {
+        let authorization = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .map(|h| h.value.as_str());
+        if !authorized(authorization, &self.token) {
+            warn!(url = request.url(), "Rejected unauthorized control request");
+            return Reply::text(401, "unauthorized\n");
+        }
+        match (request.method(), request.url()) {
+            (Method::Post, "/reboot") => {
+                if self.trigger.fire() {
+                    info!("Run requested via control API");
+                    Reply::text(202, "run scheduled\n")
+                } else {
+                    info!("Run requested via control API, manual run already pending");
+                    Reply::text(202, "run already pending\n")
+                }
+            }
+            (Method::Get, "/status") => {
+                let state = self.last_state.lock().expect("state lock poisoned");
+                match serde_json::to_string(&*state) {
+                    Ok(body) => Reply::json(200, body),
+                    Err(e) => Reply::text(500, format!("serializing state: {e}\n")),
+                }
+            }
+            _ => Reply::not_found(),
+        }
+    }
+}
+
+/// 比较 `Authorization: Bearer <token>`；逐字节比较全部内容，耗时不随匹配前缀长度变化。
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::http_server;
+    use crate::signals::{SignalWaiter, Wakeup};
+    use crate::test_support::{args, closed_port_url};
+    use chrono::Local;
+    use reqwest::blocking::{Client, RequestBuilder, Response};
+    use std::thread;
+    use std::time::Duration;
+
+    /// 在空闲端口上启动控制接口，等它开始监听后返回其地址。
+    fn serve(control: Control) -> String {
+        let base = closed_port_url();
+        let addr = base.trim_start_matches("http://").to_string();
+        http_server::spawn("control", &addr, move |req| control.handle(req));
+        for _ in 0..100 {
+            if Client::new().get(&base).send().is_ok() {
+                return base;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("control server did not start on {addr}");
+    }
+
+    fn send(req: RequestBuilder) -> (u16, String) {
+        let resp: Response = req.send().unwrap();
+        (resp.status().as_u16(), resp.text().unwrap())
+    }
+
+    #[test]
+    fn bearer_token_is_compared_exactly() {
+        assert!(authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cretX"), "s3cret"));
+        assert!(!authorized(Some("bearer s3cret"), "s3cret"));
+        assert!(!authorized(Some("s3cret"), "s3cret"));
+        assert!(!authorized(None, "s3cret"));
+    }
+
+    #[test]
+    fn control_addr_requires_a_token() {
+        check_args(&args(&[])).unwrap();
+        let err = check_args(&args(&["--control-addr", "127.0.0.1:9000"])).unwrap_err();
+        assert_eq!(err.to_string(), "--control-addr requires --control-token");
+        check_args(&args(&[
+            "--control-addr",
+            "127.0.0.1:9000",
+            "--control-token",
+            "t",
+        ]))
+        .unwrap();
+    }
+
+    #[test]
+    fn reboot_requires_auth_and_fires_the_trigger_once() {
+        let waiter = SignalWaiter::new();
+        let state = Arc::new(Mutex::new(None));
+        let base = serve(Control::new("t0k".into(), waiter.trigger(), state));
+        let client = Client::new();
+        let url = format!("{base}/reboot");
+
+        assert_eq!(send(client.post(&url)).0, 401);
+        assert_eq!(send(client.post(&url).bearer_auth("nope")).0, 401);
+        assert_eq!(waiter.wait(Duration::from_millis(50)), Wakeup::Elapsed);
+
+        assert_eq!(
+            send(client.post(&url).bearer_auth("t0k")),
+            (202, "run scheduled\n".into())
+        );
+        assert_eq!(
+            send(client.post(&url).bearer_auth("t0k")),
+            (202, "run already pending\n".into())
+        );
+        assert_eq!(waiter.wait(Duration::from_secs(5)), Wakeup::Trigger);
+        assert_eq!(send(client.get(&url).bearer_auth("t0k")).0, 404);
+    }
+
+    #[test]
+    fn status_returns_the_last_run_state() {
+        let state = Arc::new(Mutex::new(None));
+        let base = serve(Control::new(
+            "t0k".into(),
+            SignalWaiter::new().trigger(),
+            Arc::clone(&state),
+        ));
+        let client = Client::new();
+        let url = format!("{base}/status");
+        assert_eq!(send(client.get(&url)).0, 401);
+        assert_eq!(
+            send(client.get(&url).bearer_auth("t0k")),
+            (200, "null".into())
+        );
+
+        let run = RunState::new(Local::now(), &Ok(Outcome::Done), None, Default::default());
+        *state.lock().unwrap() = Some(run);
+        let (status, body) = send(client.get(&url).bearer_auth("t0k"));
+        assert_eq!(status, 200);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["last_result"], "success");
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use cookie_store::CookieStore;
+use reqwest_cookie_store::CookieStoreMutex;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// 落盘的 cookie 存储（`--cookie-file`），进程重启后可复用未过期的会话。
+/// `path` 为空时只保存在内存里（`dump-cookies` 需要能遍历的存储）。
+pub struct CookieJar {
+    path: Option<PathBuf>,
+    store: Arc<CookieStoreMutex>,
+}
+
+impl fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieJar")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CookieJar {
+    /// 文件不存在时从空存储开始。
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let store = if path.exists() {
+            let file = File::open(&path)
+                .with_context(|| format!("opening cookie file {}", path.display()))?;
+            let store = cookie_store::serde::json::load(BufReader::new(file))
+                .map_err(|e| anyhow::anyhow!("parsing cookie file {}: {e}", path.display()))?;
+            info!(path = %path.display(), "Loaded stored cookies");
+            store
+        } else {
+            CookieStore::default()
+        };
+        Ok(CookieJar {
+            path: Some(path),
+            store: Arc::new(CookieStoreMutex::new(store)),
+        })
+    }
+
+    pub fn in_memory() -> Self {
+        CookieJar {
+            path: None,
+            store: Arc::new(CookieStoreMutex::new(CookieStore::default())),
+        }
+    }
+
+    /// 交给 reqwest 作为 cookie provider。
+    pub fn store(&self) -> Arc<CookieStoreMutex> {
+        Arc::clone(&self.store)
+    }
+
+    pub fn has_cookies(&self) -> bool {
+        self.store
+            .lock()
+            .map(|s| s.iter_unexpired().next().is_some())
+            .unwrap_or(false)
+    }
+
+    /// 先写临时文件再 rename，避免写到一半崩溃导致文件损坏。
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = tmp_path(path);
+        {
+            let file = File::create(&tmp)
+                .with_context(|| format!("creating temp cookie file {}", tmp.display()))?;
+            let mut writer = BufWriter::new(file);
+            let store = self
+                .store
+                .lock()
+                .map_err(|_| anyhow::anyhow!("cookie store lock poisoned"))?;
+            // 路由器的会话 cookie 通常没有过期时间，需要连同非持久 cookie 一起保存。
+            cookie_store::serde::json::save_incl_expired_and_nonpersistent(&store, &mut writer)
+                .map_err(|e| anyhow::anyhow!("serializing cookies: {e}"))?;
+            writer.flush().context("flushing cookie file")?;
+        }
+        fs::rename(&tmp, path)
+            .with_context(|| format!("replacing cookie file {}", path.display()))?;
+        debug!(path = %path.display(), "Cookies saved");
+        Ok(())
+    }
+
+    /// `dump-cookies`：每个 cookie 一行，形如 `name=value; Domain=..; Path=..; Secure; HttpOnly`。
+    pub fn dump(&self, show_values: bool) -> Vec<String> {
+        let Ok(store) = self.store.lock() else {
+            return Vec::new();
+        };
+        store.iter_any().map(|c| render(c, show_values)).collect()
+    }
+}
+
+fn render(cookie: &cookie_store::Cookie<'_>, show_values: bool) -> String {
+    let value = if show_values { cookie.value() } else { "***" };
+    let mut line = format!(
+        "{}={value}; Domain={}; Path={}",
+        cookie.name(),
+        cookie.domain().unwrap_or("(host-only)"),
+        cookie.path().unwrap_or("/"),
+    );
+    if cookie.secure() == Some(true) {
+        line.push_str("; Secure");
+    }
+    if cookie.http_only() == Some(true) {
+        line.push_str("; HttpOnly");
+    }
+    if let Some(same_site) = cookie.same_site() {
+        line.push_str(&format!("; SameSite={same_site}"));
+    }
+    if cookie.is_expired() {
+        line.push_str("; (expired)");
+    }
+    line
+}
+
+/// 多台路由器共用 `--cookie-file` 时，每台使用 `<file>.<name>`，避免相互覆盖。
+pub fn per_router_path(path: &Path, router: &str) -> PathBuf {
+    let suffix: String = router
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    path.with_file_name(name)
+}
+
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+    use url::Url;
+
+    #[test]
+    fn saved_session_cookies_are_loaded_again() {
+        let path = temp_path("cookies-roundtrip.json");
+        let jar = CookieJar::load(path.clone()).unwrap();
+        assert!(!jar.has_cookies());
+        let url = Url::parse("http://192.168.1.1/").unwrap();
+        jar.store
+            .lock()
+            .unwrap()
+            .parse("SID=abc; Path=/; HttpOnly", &url)
+            .unwrap();
+        jar.save().unwrap();
+        assert!(!tmp_path(&path).exists());
+
+        let reloaded = CookieJar::load(path.clone()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(reloaded.has_cookies());
+        assert_eq!(
+            reloaded.dump(true),
+            ["SID=abc; Domain=(host-only); Path=/; HttpOnly"]
+        );
+    }
+
+    #[test]
+    fn dump_renders_flags_and_masks_values() {
+        let jar = CookieJar::in_memory();
+        let url = Url::parse("https://router.lan/cgi/login").unwrap();
+        {
+            let mut store = jar.store.lock().unwrap();
+            store
+                .parse(
+                    "token=xyz; Domain=router.lan; Path=/cgi; Secure; SameSite=Strict",
+                    &url,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            jar.dump(false),
+            ["token=***; Domain=router.lan; Path=/cgi; Secure; SameSite=Strict"]
+        );
+        assert_eq!(
+            jar.dump(true),
+            ["token=xyz; Domain=router.lan; Path=/cgi; Secure; SameSite=Strict"]
+        );
+        assert!(jar.has_cookies());
+        // 内存存储没有文件可写。
+        jar.save().unwrap();
+    }
+
+    #[test]
+    fn corrupt_cookie_file_is_an_error() {
+        let path = temp_path("cookies-corrupt.json");
+        fs::write(&path, "not json").unwrap();
+        let result = CookieJar::load(path.clone());
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn per_router_path_appends_sanitized_name() {
+        assert_eq!(
+            per_router_path(Path::new("/data/cookies.json"), "home router"),
+            Path::new("/data/cookies.json.home_router")
+        );
+    }
+}
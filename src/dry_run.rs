@@ -0,0 +1,206 @@
+use crate::http_log;
+use anyhow::{Context, Result};
+use reqwest::blocking::{Request, RequestBuilder};
+use tracing::info;
+use url::{Url, form_urlencoded};
+
+const REDACTED: &str = "***";
+
+/// `--dry-run`：构造完整请求并打印，但不发送。`secret_fields` 为额外需要隐藏的表单字段。
+pub fn log_request(what: &str, builder: RequestBuilder, secret_fields: &[&str]) -> Result<()> {
+    let req = builder
+        .build()
+        .with_context(|| format!("building {what} request"))?;
+    let url = redacted_url(&req, secret_fields);
+    let headers = http_log::dump_headers(req.headers());
+    let body = redacted_body(&req, secret_fields).unwrap_or_default();
+    info!(
+        method = %req.method(),
+        url = %url,
+        headers = %headers,
+        body = %body,
+        "[dry-run] {what} request not sent"
+    );
+    Ok(())
+}
+
+/// `--print-request`：打印一条等价的 curl 命令，方便脱离本工具复现请求。
+/// 只包含请求本身的头（不含客户端默认头）；cookie 用占位符代替，密码类字段打码。
+pub fn print_curl(what: &str, builder: &RequestBuilder, secret_fields: &[&str]) -> Result<()> {
+    let req = builder
+        .try_clone()
+        .with_context(|| format!("{what} request body cannot be copied"))?
+        .build()
+        .with_context(|| format!("building {what} request"))?;
+    println!("# {what}\n{}", curl_command(&req, secret_fields));
+    Ok(())
+}
+
+pub fn curl_command(req: &Request, secret_fields: &[&str]) -> String {
+    let url = redacted_url(req, secret_fields);
+    let mut parts = vec![
+        "curl".to_string(),
+        "-X".to_string(),
+        req.method().to_string(),
+        shell_quote(url.as_str()),
+    ];
+    for (name, value) in req.headers() {
+        let value = http_log::header_value(name, value);
+        parts.push("-H".to_string());
+        parts.push(shell_quote(&format!("{name}: {value}")));
+    }
+    parts.push("-b".to_string());
+    parts.push(shell_quote("<cookies from the login response>"));
+    if let Some(body) = redacted_body(req, secret_fields) {
+        parts.push("--data-raw".to_string());
+        parts.push(shell_quote(&body));
+    }
+    parts.join(" ")
+}
+
+/// 打码后的 URL：GET 登录时密码在查询参数里。
+fn redacted_url(req: &Request, secret_fields: &[&str]) -> Url {
+    let mut url = req.url().clone();
+    if let Some(query) = url.query() {
+        let redacted = redact_form(query, secret_fields);
+        url.set_query(Some(&redacted));
+    }
+    url
+}
+
+/// 打码后的请求体；没有请求体（或是流式请求体）时为 `None`。
+fn redacted_body(req: &Request, secret_fields: &[&str]) -> Option<String> {
+    req.body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| redact_body(&String::from_utf8_lossy(b), secret_fields))
+}
+
+/// POSIX shell 单引号转义。
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// 把表单里密码类字段（以及 `secret_fields`）的值替换为 `***`。
+pub fn redact_form(body: &str, secret_fields: &[&str]) -> String {
+    let mut out = form_urlencoded::Serializer::new(String::new());
+    for (k, v) in form_urlencoded::parse(body.as_bytes()) {
+        if is_secret_field(&k) || secret_fields.contains(&k.as_ref()) {
+            out.append_pair(&k, REDACTED);
+        } else {
+            out.append_pair(&k, &v);
+        }
+    }
+    out.finish()
+}
+
+/// 请求体是 JSON 对象时按键名打码，否则按表单处理。
+fn redact_body(body: &str, secret_fields: &[&str]) -> String {
+    let Ok(serde_json::Value::Object(mut map)) = serde_json::from_str(body) else {
+        return redact_form(body, secret_fields);
+    };
+    for (k, v) in map.iter_mut() {
+        if is_secret_field(k) || secret_fields.contains(&k.as_str()) {
+            *v = serde_json::Value::String(REDACTED.to_string());
+        }
+    }
+    serde_json::Value::Object(map).to_string()
+}
+
+fn is_secret_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.contains("pass") || name.contains("pwd")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::blocking::Client;
+
+    const URL: &str = "http://192.168.1.1/";
+
+    #[test]
+    fn form_body_masks_password_fields() {
+        let req = Client::new()
+            .post(URL)
+            .form(&[
+                ("user_name", "useradmin"),
+                ("Password", "secret"),
+                ("Frm_Logintoken", "7"),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            redacted_body(&req, &[]).as_deref(),
+            Some("user_name=useradmin&Password=***&Frm_Logintoken=7")
+        );
+    }
+
+    #[test]
+    fn json_body_masks_configured_secret_field() {
+        let req = Client::new()
+            .post(URL)
+            .json(&serde_json::json!({ "user": "useradmin", "loginKey": "secret" }))
+            .build()
+            .unwrap();
+        let body: serde_json::Value =
+            serde_json::from_str(&redacted_body(&req, &["loginKey"]).unwrap()).unwrap();
+        assert_eq!(body["user"], "useradmin");
+        assert_eq!(body["loginKey"], "***");
+    }
+
+    #[test]
+    fn query_string_password_is_masked() {
+        let req = Client::new()
+            .get(format!("{URL}?user_name=useradmin&Password=secret"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            redacted_url(&req, &[]).as_str(),
+            "http://192.168.1.1/?user_name=useradmin&Password=***"
+        );
+        assert_eq!(redacted_body(&req, &[]), None);
+    }
+
+    #[test]
+    fn curl_command_hides_cookies_and_secrets() {
+        let req = Client::new()
+            .post(URL)
+            .header("Cookie", "SID=abc")
+            .header("X-CSRF-Token", "t0k3n")
+            .form(&[("pwd", "it's secret")])
+            .build()
+            .unwrap();
+        let curl = curl_command(&req, &[]);
+        assert!(
+            curl.starts_with("curl -X POST 'http://192.168.1.1/'"),
+            "{curl}"
+        );
+        assert!(curl.contains("-H 'cookie: ***'"), "{curl}");
+        assert!(curl.contains("-H 'x-csrf-token: ***'"), "{curl}");
+        assert!(curl.ends_with("--data-raw 'pwd=***'"), "{curl}");
+        assert!(!curl.contains("secret") && !curl.contains("t0k3n"));
+    }
+
+    #[test]
+    fn curl_command_keeps_method_url_and_request_headers() {
+        let req = Client::new()
+            .get(format!("{URL}login.lua?user_name=o'neil&Password=secret"))
+            .header("User-Agent", "Probe/1.0")
+            .header("Referer", URL)
+            .build()
+            .unwrap();
+        let curl = curl_command(&req, &[]);
+        assert_eq!(
+            curl,
+            "curl -X GET 'http://192.168.1.1/login.lua?user_name=o%27neil&Password=***' \
+             -H 'user-agent: Probe/1.0' -H 'referer: http://192.168.1.1/' \
+             -b '<cookies from the login response>'"
+        );
+    }
+
+    #[test]
+    fn shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote("a b"), "'a b'");
+    }
+}
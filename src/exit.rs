@@ -0,0 +1,104 @@
+use std::fmt;
+
+/// `--help` 中列出的退出码。
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  unexpected error
+  2  invalid configuration or arguments
+  3  login failed
+  4  reboot request failed
+  5  router did not go down / come back within --reboot-wait-secs
+  6  network error (connection refused, unreachable, timeout)";
+
+/// 失败类别：作为 anyhow context 挂在错误链上，`main` 据此选择退出码。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Config,
+    Login,
+    Reboot,
+    VerifyTimeout,
+}
+
+impl Failure {
+    fn code(self) -> i32 {
+        match self {
+            Failure::Config => 2,
+            Failure::Login => 3,
+            Failure::Reboot => 4,
+            Failure::VerifyTimeout => 5,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Failure::Config => "invalid configuration",
+            Failure::Login => "logging in",
+            Failure::Reboot => "sending reboot",
+            Failure::VerifyTimeout => "verifying reboot",
+        })
+    }
+}
+
+const UNEXPECTED: i32 = 1;
+const NETWORK: i32 = 6;
+
+/// 网络错误优先（无论发生在哪一步），其次是错误链上最外层的失败类别。
+pub fn code(err: &anyhow::Error) -> i32 {
+    let network = err.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+    });
+    if network {
+        return NETWORK;
+    }
+    err.downcast_ref::<Failure>()
+        .map_or(UNEXPECTED, |failure| failure.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::closed_port_url;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn failure_classes_map_to_documented_codes() {
+        for (failure, expected) in [
+            (Failure::Config, 2),
+            (Failure::Login, 3),
+            (Failure::Reboot, 4),
+            (Failure::VerifyTimeout, 5),
+        ] {
+            let err = Err::<(), _>(anyhow!("boom")).context(failure).unwrap_err();
+            assert_eq!(code(&err), expected, "{failure:?}");
+            assert!(EXIT_CODES_HELP.contains(&format!("  {expected}  ")));
+        }
+    }
+
+    #[test]
+    fn outermost_failure_wins() {
+        let err = Err::<(), _>(anyhow!("boom"))
+            .context(Failure::Login)
+            .context(Failure::Reboot)
+            .unwrap_err();
+        assert_eq!(code(&err), 4);
+    }
+
+    #[test]
+    fn unclassified_errors_are_unexpected() {
+        assert_eq!(code(&anyhow!("boom")), 1);
+    }
+
+    #[test]
+    fn network_errors_take_precedence() {
+        let err = reqwest::blocking::get(closed_port_url()).unwrap_err();
+        let err = Err::<(), _>(err)
+            .context("fetching login page")
+            .context(Failure::Login)
+            .unwrap_err();
+        assert_eq!(code(&err), 6);
+    }
+}
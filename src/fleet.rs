@@ -0,0 +1,282 @@
+use crate::health::Health;
+use crate::hooks;
+use crate::influx::Influx;
+use crate::metrics::Metrics;
+use crate::notify::{Event, Notifier};
+use crate::summary::Summary;
+use crate::{Config, Outcome, run_with_fallback};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Local};
+use reqwest::blocking::Client;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use tracing::{error, field, info, info_span};
+
+/// `--parallel` 时同时处理的路由器上限。
+const MAX_PARALLEL: usize = 4;
+
+/// 一台路由器：独立的配置与 HTTP 客户端（各自的 cookie 存储，互不干扰）。
+pub struct Router {
+    pub cfg: Config,
+    /// `--host` 里列出的备用地址，主地址登录失败时依次尝试
+    pub fallbacks: Vec<Config>,
+    pub client: Client,
+}
+
+/// 调度循环每次触发时要做的事：对所有路由器跑一轮并发送通知。
+pub struct Runner {
+    pub routers: Vec<Router>,
+    pub parallel: bool,
+    pub notifier: Notifier,
+    pub metrics: Arc<Metrics>,
+    pub influx: Option<Influx>,
+    pub health: Arc<Health>,
+    pub summary: Summary,
+}
+
+impl Runner {
+    fn run_router(&self, router: &Router) -> Result<Outcome> {
+        let span = info_span!(
+            "router",
+            name = %router.cfg.name,
+            host = router.cfg.login_url.host_str().unwrap_or_default(),
+            result = field::Empty,
+        );
+        let _entered = span.enter();
+        let started = Instant::now();
+        let result = hooks::pre_run(&router.cfg).and_then(|()| {
+            catch_panic(|| run_with_fallback(&router.client, &router.cfg, &router.fallbacks))
+        });
+        hooks::post_run(&router.cfg, &result);
+        for cfg in std::iter::once(&router.cfg).chain(&router.fallbacks) {
+            if let Some(downtime) = cfg
+                .last_downtime
+                .lock()
+                .expect("downtime lock poisoned")
+                .take()
+            {
+                self.summary.add_downtime(downtime);
+            }
+        }
+        let elapsed = started.elapsed();
+        let result_name = match &result {
+            Ok(Outcome::Done) => "success",
+            Ok(Outcome::Skipped(_)) => "skipped",
+            Err(_) => "failure",
+        };
+        span.record("result", result_name);
+        self.metrics.record(&router.cfg.name, &result, elapsed);
+        // 跳过的运行不写 InfluxDB：result 字段只有成功/失败两种取值。
+        if let Some(influx) = &self.influx
+            && !matches!(result, Ok(Outcome::Skipped(_)))
+        {
+            influx.write(&router.cfg.name, result.is_ok(), elapsed);
+        }
+        if !router.cfg.dry_run {
+            self.notifier
+                .notify(&Event::from_result(&router.cfg, &result));
+        }
+        result
+    }
+
+    /// 各路由器最近一次实际下发重启的时间，写入 --state-file。
+    pub fn last_reboots(&self) -> BTreeMap<String, DateTime<Local>> {
+        self.routers
+            .iter()
+            .filter_map(|router| {
+                let last = *router
+                    .cfg
+                    .last_reboot
+                    .lock()
+                    .expect("last reboot lock poisoned");
+                last.map(|at| (router.cfg.name.clone(), at))
+            })
+            .collect()
+    }
+
+    /// 执行一轮；真正完成后才标记为就绪（`/ready`），整轮都被跳过不算。
+    pub fn run_all(&self) -> Result<Outcome> {
+        let result = self.run_routers();
+        self.summary.record_run(&result);
+        if let Ok(Outcome::Done) = result {
+            self.health.mark_ready();
+        }
+        result
+    }
+
+    /// 依次（或并发）对所有路由器执行一轮；单台失败不影响其他路由器，最后汇总结果。
+    /// 所有路由器都被跳过时整轮算作跳过。
+    fn run_routers(&self) -> Result<Outcome> {
+        let routers = &self.routers;
+        if let [router] = routers.as_slice() {
+            return self.run_router(router);
+        }
+
+        let results: Vec<Result<Outcome>> = if self.parallel {
+            let next = AtomicUsize::new(0);
+            let slots: Mutex<Vec<Option<Result<Outcome>>>> =
+                Mutex::new(routers.iter().map(|_| None).collect());
+            thread::scope(|s| {
+                for _ in 0..MAX_PARALLEL.min(routers.len()) {
+                    s.spawn(|| {
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(router) = routers.get(i) else { break };
+                            let result = self.run_router(router);
+                            slots.lock().expect("results lock poisoned")[i] = Some(result);
+                        }
+                    });
+                }
+            });
+            slots
+                .into_inner()
+                .expect("results lock poisoned")
+                .into_iter()
+                .map(|r| r.unwrap_or_else(|| Err(anyhow!("router run did not complete"))))
+                .collect()
+        } else {
+            routers.iter().map(|r| self.run_router(r)).collect()
+        };
+
+        let mut failed = 0;
+        let mut skipped = 0;
+        for (router, result) in routers.iter().zip(&results) {
+            match result {
+                Ok(Outcome::Done) => info!(router = %router.cfg.name, "Router run succeeded"),
+                Ok(Outcome::Skipped(reason)) => {
+                    skipped += 1;
+                    info!(router = %router.cfg.name, reason, "Router run skipped");
+                }
+                Err(e) => {
+                    failed += 1;
+                    error!(router = %router.cfg.name, "Router run failed: {e:#}");
+                }
+            }
+        }
+        info!(
+            total = routers.len(),
+            succeeded = routers.len() - failed - skipped,
+            skipped,
+            failed,
+            "Run summary"
+        );
+
+        if failed > 0 {
+            bail!("{failed} of {} routers failed", routers.len());
+        }
+        if skipped == routers.len() {
+            return Ok(Outcome::Skipped("all routers skipped".into()));
+        }
+        Ok(Outcome::Done)
+    }
+}
+
+/// 把一次运行中的 panic（如解析异常响应时的越界）转成普通错误，
+/// 让它像其他失败一样被记录、通知并交给 --on-failure 处理，而不是带走整个守护进程。
+fn catch_panic<T>(run: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|payload| {
+        let msg = panic_message(payload.as_ref());
+        error!("Router run panicked: {msg}");
+        Err(anyhow!("router run panicked: {msg}"))
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{closed_port_url, router, runner};
+
+    #[test]
+    fn one_failing_router_does_not_stop_the_others() {
+        let down = closed_port_url();
+        let runner = runner(
+            vec![
+                router(&down, &["--max-retries", "0"]),
+                router("http://192.168.1.1", &["--dry-run"]),
+            ],
+            false,
+        );
+        let err = runner.run_all().unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 routers failed");
+        let metrics = runner.metrics.render();
+        assert!(metrics.contains("tianyi_reboot_success_total{router=\"http://192.168.1.1\"} 1"));
+        assert!(metrics.contains(&format!(
+            "tianyi_reboot_failure_total{{router=\"{down}\"}} 1"
+        )));
+    }
+
+    #[test]
+    fn ready_only_after_a_successful_run() {
+        let down = closed_port_url();
+        let failing = runner(vec![router(&down, &["--max-retries", "0"])], false);
+        assert!(failing.run_all().is_err());
+        assert_eq!(
+            failing
+                .health
+                .handle(&tiny_http::Method::Get, "/ready")
+                .status,
+            503
+        );
+
+        let ok = runner(vec![router("http://192.168.1.1", &["--dry-run"])], false);
+        ok.run_all().unwrap();
+        assert_eq!(
+            ok.health.handle(&tiny_http::Method::Get, "/ready").status,
+            200
+        );
+    }
+
+    #[test]
+    fn parallel_run_covers_every_router() {
+        let routers = (1..=5)
+            .map(|i| router(&format!("http://192.168.1.{i}"), &["--dry-run"]))
+            .collect();
+        let runner = runner(routers, true);
+        assert_eq!(runner.run_all().unwrap(), Outcome::Done);
+    }
+
+    #[test]
+    fn panics_become_run_errors() {
+        let err = catch_panic::<()>(|| panic!("index out of bounds")).unwrap_err();
+        assert_eq!(err.to_string(), "router run panicked: index out of bounds");
+        let code = 7;
+        let err = catch_panic::<()>(|| panic!("bad status {code}")).unwrap_err();
+        assert_eq!(err.to_string(), "router run panicked: bad status 7");
+        let err = catch_panic::<()>(|| panic::panic_any(42)).unwrap_err();
+        assert_eq!(err.to_string(), "router run panicked: unknown panic");
+        assert_eq!(catch_panic(|| Ok(Outcome::Done)).unwrap(), Outcome::Done);
+    }
+
+    #[test]
+    fn a_panicking_run_does_not_stop_later_runs() {
+        let runs = AtomicUsize::new(0);
+        let results: Vec<_> = (0..3)
+            .map(|_| {
+                catch_panic(|| {
+                    if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                        panic!("unexpected response");
+                    }
+                    Ok(Outcome::Done)
+                })
+            })
+            .collect();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert!(results[0].is_err());
+        assert!(results[1..].iter().all(|r| matches!(r, Ok(Outcome::Done))));
+    }
+}
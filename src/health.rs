@@ -0,0 +1,72 @@
+use crate::http_server::Reply;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tiny_http::Method;
+
+/// 与调度循环共享的健康状态：`/healthz` 存活探针，`/ready` 就绪探针。
+#[derive(Debug, Default)]
+pub struct Health {
+    alive: AtomicBool,
+    ready: AtomicBool,
+}
+
+impl Health {
+    pub fn set_alive(&self, alive: bool) {
+        self.alive.store(alive, Ordering::SeqCst);
+    }
+
+    /// 至少成功运行过一次后才算就绪。
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::SeqCst);
+    }
+
+    pub fn handle(&self, method: &Method, url: &str) -> Reply {
+        if *method != Method::Get {
+            return Reply::not_found();
+        }
+        respond(
+            url,
+            self.alive.load(Ordering::SeqCst),
+            self.ready.load(Ordering::SeqCst),
+        )
+    }
+}
+
+pub fn respond(url: &str, alive: bool, ready: bool) -> Reply {
+    match url {
+        "/healthz" if alive => Reply::text(200, "ok\n"),
+        "/healthz" => Reply::text(503, "scheduler not running\n"),
+        "/ready" if ready => Reply::text(200, "ready\n"),
+        "/ready" => Reply::text(503, "no successful run yet\n"),
+        _ => Reply::not_found(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_follow_scheduler_state() {
+        let health = Health::default();
+        assert_eq!(health.handle(&Method::Get, "/healthz").status, 503);
+        assert_eq!(health.handle(&Method::Get, "/ready").status, 503);
+
+        health.set_alive(true);
+        assert_eq!(health.handle(&Method::Get, "/healthz").status, 200);
+        assert_eq!(health.handle(&Method::Get, "/ready").status, 503);
+
+        health.mark_ready();
+        assert_eq!(health.handle(&Method::Get, "/ready").status, 200);
+
+        health.set_alive(false);
+        assert_eq!(health.handle(&Method::Get, "/healthz").status, 503);
+    }
+
+    #[test]
+    fn unknown_paths_and_methods_are_not_found() {
+        assert_eq!(respond("/", true, true).status, 404);
+        let health = Health::default();
+        health.set_alive(true);
+        assert_eq!(health.handle(&Method::Post, "/healthz").status, 404);
+    }
+}
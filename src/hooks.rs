@@ -0,0 +1,151 @@
+use crate::{Config, Outcome};
+use anyhow::{Context, Result, bail};
+use std::process::Command;
+use tracing::{info, warn};
+
+/// `--pre-run-cmd`：每次运行前执行；非零退出码中止本次运行。
+pub fn pre_run(cfg: &Config) -> Result<()> {
+    let Some(cmd) = &cfg.pre_run_cmd else {
+        return Ok(());
+    };
+    if cfg.dry_run {
+        info!(cmd, "[dry-run] skipping pre-run hook");
+        return Ok(());
+    }
+    run("pre-run", cmd, cfg, None)
+}
+
+/// `--post-run-cmd`：每次运行后执行，结果（success/skipped/failure）通过 `TIANYI_RESULT`/`TIANYI_ERROR`
+/// 传入；失败只记录日志。
+pub fn post_run(cfg: &Config, result: &Result<Outcome>) {
+    let Some(cmd) = &cfg.post_run_cmd else {
+        return;
+    };
+    if cfg.dry_run {
+        info!(cmd, "[dry-run] skipping post-run hook");
+        return;
+    }
+    if let Err(e) = run("post-run", cmd, cfg, Some(result)) {
+        warn!("{e:#}");
+    }
+}
+
+fn run(what: &str, cmd: &str, cfg: &Config, result: Option<&Result<Outcome>>) -> Result<()> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(cmd)
+        .env("TIANYI_ROUTER", &cfg.name)
+        .env("TIANYI_HOST", cfg.login_url.host_str().unwrap_or_default());
+    if let Some(result) = result {
+        command.env("TIANYI_RESULT", result_name(result));
+        if let Err(e) = result {
+            command.env("TIANYI_ERROR", format!("{e:#}"));
+        }
+    }
+    info!(cmd, "Running {what} hook");
+    let status = command
+        .status()
+        .with_context(|| format!("{what} hook could not be started"))?;
+    if !status.success() {
+        bail!("{what} hook `{cmd}` failed with {status}");
+    }
+    Ok(())
+}
+
+fn result_name(result: &Result<Outcome>) -> &'static str {
+    match result {
+        Ok(Outcome::Done) => "success",
+        Ok(Outcome::Skipped(_)) => "skipped",
+        Err(_) => "failure",
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Answer, Stub, config, temp_path};
+    use std::fs;
+
+    /// 把钩子看到的环境变量写入文件的命令。
+    fn recorder(name: &str) -> (std::path::PathBuf, String) {
+        let path = temp_path(name);
+        let cmd = format!(
+            r#"printf '%s|%s|%s|%s' "$TIANYI_ROUTER" "$TIANYI_HOST" "$TIANYI_RESULT" "$TIANYI_ERROR" > '{}'"#,
+            path.display()
+        );
+        (path, cmd)
+    }
+
+    fn recorded(path: &std::path::Path) -> String {
+        let text = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        text
+    }
+
+    #[test]
+    fn post_hook_receives_the_result() {
+        let (path, cmd) = recorder("hook-post-ok");
+        let cfg = config("http://192.168.1.1", &["--post-run-cmd", &cmd]);
+        post_run(&cfg, &Ok(Outcome::Done));
+        assert_eq!(recorded(&path), "test|192.168.1.1|success|");
+
+        post_run(&cfg, &Ok(Outcome::Skipped("busy".into())));
+        assert_eq!(recorded(&path), "test|192.168.1.1|skipped|");
+
+        post_run(&cfg, &Err(anyhow::anyhow!("boom")));
+        assert_eq!(recorded(&path), "test|192.168.1.1|failure|boom");
+    }
+
+    #[test]
+    fn failing_pre_hook_is_an_error() {
+        let cfg = config("http://192.168.1.1", &["--pre-run-cmd", "exit 3"]);
+        let err = pre_run(&cfg).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("pre-run hook `exit 3` failed with "),
+            "{err}"
+        );
+        pre_run(&config("http://192.168.1.1", &["--pre-run-cmd", "true"])).unwrap();
+    }
+
+    #[test]
+    fn failing_post_hook_is_only_logged() {
+        let cfg = config("http://192.168.1.1", &["--post-run-cmd", "exit 1"]);
+        post_run(&cfg, &Ok(Outcome::Done));
+    }
+
+    #[test]
+    fn hooks_are_skipped_in_dry_run() {
+        let (path, cmd) = recorder("hook-dry-run");
+        let cfg = config(
+            "http://192.168.1.1",
+            &[
+                "--dry-run",
+                "--pre-run-cmd",
+                "exit 1",
+                "--post-run-cmd",
+                &cmd,
+            ],
+        );
+        pre_run(&cfg).unwrap();
+        post_run(&cfg, &Ok(Outcome::Done));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn failing_pre_hook_aborts_the_run_and_still_runs_the_post_hook() {
+        let stub = Stub::start(|_| Answer::status(500, "unexpected"));
+        let (path, cmd) = recorder("hook-abort");
+        let router = test_support::router(
+            &stub.base,
+            &["--pre-run-cmd", "exit 1", "--post-run-cmd", &cmd],
+        );
+        let runner = test_support::runner(vec![router], false);
+        let err = runner.run_all().unwrap_err();
+        assert!(format!("{err:#}").contains("pre-run hook"), "{err:#}");
+        assert!(stub.hits().is_empty());
+        let seen = recorded(&path);
+        assert!(seen.contains("|failure|"), "{seen}");
+    }
+}
@@ -0,0 +1,147 @@
+use crate::dry_run;
+use regex::Regex;
+use reqwest::StatusCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{
+    AUTHORIZATION, COOKIE, HeaderMap, HeaderName, HeaderValue, PROXY_AUTHORIZATION, SET_COOKIE,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tracing::{debug, info};
+
+const REDACTED: &str = "***";
+
+static COOKIE_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?im)^(\s*set-cookie\s*:).*$|(document\.cookie\s*=\s*)[^;\n]*")
+        .expect("valid regex")
+});
+
+/// debug 级别记录响应状态、耗时与（截断、脱敏后的）响应体。
+pub fn log_response(
+    what: &str,
+    status: StatusCode,
+    elapsed: Duration,
+    body: &str,
+    max_bytes: usize,
+    secrets: &[&str],
+) {
+    debug!(
+        status = %status,
+        elapsed_ms = elapsed.as_millis() as u64,
+        body_len = body.len(),
+        body = %truncate_bytes(&redact(body, secrets), max_bytes),
+        "{what} response"
+    );
+}
+
+/// 发送请求；开启 `--trace-http` 时先记录方法、URL 与请求头，收到响应后记录状态与响应头。
+/// cookie、认证头以及 URL 查询串里的 `secret_fields` 都会脱敏。
+/// 只能看到请求构造时设置的头；客户端在发送时追加的 Cookie 等不在其中。
+pub fn send(
+    what: &str,
+    req: RequestBuilder,
+    trace: bool,
+    secret_fields: &[&str],
+) -> reqwest::Result<Response> {
+    if !trace {
+        return req.send();
+    }
+    let (client, req) = req.build_split();
+    let req = req?;
+    let mut url = req.url().clone();
+    if let Some(query) = url.query() {
+        let redacted = dry_run::redact_form(query, secret_fields);
+        url.set_query(Some(&redacted));
+    }
+    info!(
+        method = %req.method(),
+        url = %url,
+        headers = %dump_headers(req.headers()),
+        "{what} request"
+    );
+    let resp = client.execute(req)?;
+    info!(
+        status = %resp.status(),
+        headers = %dump_headers(resp.headers()),
+        "{what} response"
+    );
+    Ok(resp)
+}
+
+/// 把头部拼成 `name: value; ...`，cookie、认证与 token 类头只保留名字。
+pub fn dump_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {}", header_value(name, value)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 日志里展示的头部值：敏感头替换为 `***`。
+pub fn header_value<'a>(name: &HeaderName, value: &'a HeaderValue) -> &'a str {
+    if is_secret_header(name, value) {
+        REDACTED
+    } else {
+        value.to_str().unwrap_or("<binary>")
+    }
+}
+
+/// 标记为 sensitive 的值、cookie 与认证头，以及名字里带 token/csrf/xsrf/secret 的头
+/// （--header 传入的 API token、--csrf-header-name 注入的 CSRF token）。
+fn is_secret_header(name: &HeaderName, value: &HeaderValue) -> bool {
+    let lower = name.as_str();
+    value.is_sensitive()
+        || [COOKIE, SET_COOKIE, AUTHORIZATION, PROXY_AUTHORIZATION].contains(name)
+        || ["token", "csrf", "xsrf", "secret", "api-key"]
+            .iter()
+            .any(|word| lower.contains(word))
+}
+
+/// 去掉 Set-Cookie / document.cookie 的值，以及回显出来的密码等敏感串。
+pub fn redact(body: &str, secrets: &[&str]) -> String {
+    let mut out = COOKIE_LINE
+        .replace_all(body, |c: &regex::Captures| {
+            let prefix = c.get(1).or_else(|| c.get(2)).map_or("", |m| m.as_str());
+            format!("{prefix}{REDACTED}")
+        })
+        .into_owned();
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        out = out.replace(secret, REDACTED);
+    }
+    out
+}
+
+/// 截断到不超过 `max_bytes` 字节（不会切断 UTF-8 字符）。
+pub fn truncate_bytes(s: &str, max_bytes: usize) -> String {
+    if s.len() <= max_bytes {
+        return s.to_string();
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...[truncated {} bytes]", &s[..end], s.len() - end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation_respects_char_boundaries() {
+        assert_eq!(truncate_bytes("short", 10), "short");
+        assert_eq!(truncate_bytes("abcdef", 4), "abcd...[truncated 2 bytes]");
+        // "天" 占 3 个字节，不能从中间切开。
+        assert_eq!(truncate_bytes("天翼", 4), "天...[truncated 3 bytes]");
+    }
+
+    #[test]
+    fn cookies_and_secrets_are_removed_from_bodies() {
+        let body = "Set-Cookie: SID=abc\n<script>document.cookie = \"SID=abc; path=/\";</script>\npwd=hunter2";
+        let redacted = redact(body, &["hunter2", ""]);
+        assert_eq!(
+            redacted,
+            "Set-Cookie:***\n<script>document.cookie = ***; path=/\";</script>\npwd=***"
+        );
+    }
+}
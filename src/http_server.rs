@@ -0,0 +1,133 @@
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Request, Response, Server};
+use tracing::{debug, error, info, warn};
+
+/// 内置小型 HTTP 服务（metrics/health 等）的一次响应。
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Reply {
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Reply {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, body: impl Into<String>) -> Self {
+        Reply {
+            status,
+            content_type: "application/json",
+            body: body.into(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Reply::text(404, "not found\n")
+    }
+}
+
+/// 绑定失败后的首次重试间隔；之后每次翻倍，最长 `MAX_BIND_RETRY`。
+const INITIAL_BIND_RETRY: Duration = Duration::from_secs(1);
+const MAX_BIND_RETRY: Duration = Duration::from_secs(60);
+
+/// 在后台线程上启动服务；绑定失败（如端口被占用）时在后台按退避间隔不断重试，不影响调度循环。
+pub fn spawn<F>(name: &'static str, addr: &str, handler: F)
+where
+    F: Fn(&Request) -> Reply + Send + 'static,
+{
+    let addr = addr.to_string();
+    thread::spawn(move || {
+        let server = bind(name, &addr);
+        for request in server.incoming_requests() {
+            let reply = handler(&request);
+            debug!(
+                method = %request.method(),
+                url = request.url(),
+                status = reply.status,
+                "{name} request"
+            );
+            let content_type =
+                Header::from_bytes("Content-Type", reply.content_type).expect("valid header");
+            let response = Response::from_string(reply.body)
+                .with_status_code(reply.status)
+                .with_header(content_type);
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to send {name} response: {e}");
+            }
+        }
+    });
+}
+
+/// 绑定成功为止；只有第一次失败和最终成功记为 error/info，中间的重试记为 debug。
+fn bind(name: &str, addr: &str) -> Server {
+    let mut delay = INITIAL_BIND_RETRY;
+    let mut attempts = 0u32;
+    loop {
+        attempts += 1;
+        match Server::http(addr) {
+            Ok(server) => {
+                if attempts > 1 {
+                    info!(addr, attempts, "{name} server bound after retrying");
+                }
+                info!(addr, "{name} server listening");
+                return server;
+            }
+            Err(e) if attempts == 1 => error!(
+                addr,
+                retry_secs = delay.as_secs(),
+                "Failed to bind {name} server, retrying in the background: {e}"
+            ),
+            Err(e) => debug!(addr, attempts, "Retrying {name} server bind failed: {e}"),
+        }
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_BIND_RETRY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    #[test]
+    fn bind_retries_until_the_port_is_released() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let server = bind("test", &addr.to_string());
+            tx.send(server.server_addr().to_ip().unwrap()).unwrap();
+        });
+        thread::sleep(Duration::from_millis(200));
+        assert!(rx.try_recv().is_err(), "bound while the port was taken");
+        drop(taken);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), addr);
+    }
+
+    #[test]
+    fn spawned_server_answers_once_bound_after_a_conflict() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap();
+        spawn("test", &addr.to_string(), |_| Reply::text(200, "pong"));
+        thread::sleep(Duration::from_millis(200));
+        drop(taken);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let body = loop {
+            if let Ok(resp) = reqwest::blocking::get(format!("http://{addr}/ping")) {
+                break resp.text().unwrap();
+            }
+            assert!(Instant::now() < deadline, "server never came up");
+            thread::sleep(Duration::from_millis(100));
+        };
+        assert_eq!(body, "pong");
+    }
+}
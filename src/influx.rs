@@ -0,0 +1,160 @@
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use reqwest::blocking::Client;
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
+
+const MEASUREMENT: &str = "tianyi_reboot";
+
+/// `--influx-url`：每次运行后向 InfluxDB v2 写入一条 line protocol 记录。
+pub struct Influx {
+    client: Client,
+    write_url: Url,
+    token: Option<String>,
+}
+
+impl Influx {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(base) = &args.influx_url else {
+            return Ok(None);
+        };
+        let (Some(org), Some(bucket)) = (&args.influx_org, &args.influx_bucket) else {
+            bail!("--influx-org and --influx-bucket are required with --influx-url");
+        };
+        let mut write_url = Url::parse(base)
+            .context("invalid --influx-url")?
+            .join("api/v2/write")
+            .context("invalid --influx-url")?;
+        write_url
+            .query_pairs_mut()
+            .append_pair("org", org)
+            .append_pair("bucket", bucket)
+            .append_pair("precision", "ms");
+        let client = Client::builder()
+            .timeout(Duration::from_secs(args.timeout_secs))
+            .build()
+            .context("building InfluxDB HTTP client")?;
+        Ok(Some(Influx {
+            client,
+            write_url,
+            token: args.influx_token.clone(),
+        }))
+    }
+
+    /// 写入失败只记录日志，不影响调度循环。
+    pub fn write(&self, router: &str, success: bool, duration: Duration) {
+        let line = line_protocol(router, success, duration, Local::now().timestamp_millis());
+        match self.send(&line) {
+            Ok(()) => debug!(%line, "InfluxDB point written"),
+            Err(e) => warn!("InfluxDB write failed: {e:#}"),
+        }
+    }
+
+    fn send(&self, line: &str) -> Result<()> {
+        let mut req = self
+            .client
+            .post(self.write_url.clone())
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(line.to_string());
+        if let Some(token) = &self.token {
+            req = req.header("Authorization", format!("Token {token}"));
+        }
+        req.send()
+            .context("InfluxDB request failed")?
+            .error_for_status()
+            .context("InfluxDB returned error status")?;
+        Ok(())
+    }
+}
+
+/// 例如 `tianyi_reboot,router=192.168.1.1 result=1i,duration_ms=1234i 1700000000000`。
+pub fn line_protocol(router: &str, success: bool, duration: Duration, timestamp_ms: i64) -> String {
+    format!(
+        "{MEASUREMENT},router={} result={}i,duration_ms={}i {timestamp_ms}",
+        escape_tag(router),
+        u8::from(success),
+        duration.as_millis(),
+    )
+}
+
+/// tag 值里的逗号、等号和空格需要反斜杠转义。
+fn escape_tag(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Answer, Stub, args};
+
+    #[test]
+    fn line_protocol_for_success_and_failure() {
+        let ms = Duration::from_millis(1234);
+        assert_eq!(
+            line_protocol("192.168.1.1", true, ms, 1_700_000_000_000),
+            "tianyi_reboot,router=192.168.1.1 result=1i,duration_ms=1234i 1700000000000"
+        );
+        assert_eq!(
+            line_protocol("192.168.1.1", false, ms, 1_700_000_000_000),
+            "tianyi_reboot,router=192.168.1.1 result=0i,duration_ms=1234i 1700000000000"
+        );
+    }
+
+    #[test]
+    fn tag_values_are_escaped() {
+        assert_eq!(
+            line_protocol("living room,a=b", true, Duration::ZERO, 1),
+            r"tianyi_reboot,router=living\ room\,a\=b result=1i,duration_ms=0i 1"
+        );
+    }
+
+    #[test]
+    fn writes_to_the_v2_endpoint_with_token() {
+        let stub = Stub::start(|_| Answer::status(204, ""));
+        let influx = Influx::from_args(&args(&[
+            "--influx-url",
+            &stub.base,
+            "--influx-org",
+            "home",
+            "--influx-bucket",
+            "ops",
+            "--influx-token",
+            "t0k",
+        ]))
+        .unwrap()
+        .unwrap();
+        influx.write("test", true, Duration::from_millis(5));
+
+        let hits = stub.hits_to("/api/v2/write");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field("org").as_deref(), Some("home"));
+        assert_eq!(hits[0].field("bucket").as_deref(), Some("ops"));
+        assert_eq!(hits[0].field("precision").as_deref(), Some("ms"));
+        assert_eq!(hits[0].header("Authorization"), Some("Token t0k"));
+        assert!(
+            hits[0]
+                .body
+                .starts_with("tianyi_reboot,router=test result=1i,duration_ms=5i ")
+        );
+    }
+
+    #[test]
+    fn org_and_bucket_are_required() {
+        let err = Influx::from_args(&args(&["--influx-url", "http://influx:8086"]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--influx-org and --influx-bucket are required with --influx-url"
+        );
+    }
+}
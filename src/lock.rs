@@ -0,0 +1,117 @@
+use anyhow::{Context, Result, bail};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// `--lock-file`：以 O_CREAT|O_EXCL 创建、写入本进程 pid 的锁文件，防止两个实例重复重启路由器。
+/// 正常退出时（drop）删除。
+#[derive(Debug)]
+pub struct LockFile {
+    path: PathBuf,
+}
+
+impl LockFile {
+    /// 锁已被存活的进程持有时报错；持有者已退出（陈旧锁）则接管。
+    pub fn acquire(path: &Path) -> Result<Self> {
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id())
+                        .with_context(|| format!("writing lock file {}", path.display()))?;
+                    debug!(path = %path.display(), "Lock acquired");
+                    return Ok(LockFile {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let holder = fs::read_to_string(path)
+                        .ok()
+                        .and_then(|s| s.trim().parse::<u32>().ok());
+                    match holder {
+                        Some(pid) if process_alive(pid) => bail!(
+                            "another instance (pid {pid}) holds the lock file {}",
+                            path.display()
+                        ),
+                        _ => {
+                            warn!(path = %path.display(), pid = ?holder, "Removing stale lock file");
+                            fs::remove_file(path).with_context(|| {
+                                format!("removing stale lock file {}", path.display())
+                            })?;
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("creating lock file {}", path.display()));
+                }
+            }
+        }
+        bail!("could not acquire lock file {}", path.display())
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(path = %self.path.display(), "Failed to remove lock file: {e}");
+        }
+    }
+}
+
+/// Linux 上通过 /proc 判断；其他平台无法判断时保守地视为存活，需要手动删除锁文件。
+fn process_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    if cfg!(target_os = "linux") {
+        Path::new(&format!("/proc/{pid}")).exists()
+    } else {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+
+    #[test]
+    fn second_acquisition_fails_while_held() {
+        let path = temp_path("lock-held");
+        let lock = LockFile::acquire(&path).unwrap();
+        let err = LockFile::acquire(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "another instance (pid {}) holds the lock file {}",
+                std::process::id(),
+                path.display()
+            )
+        );
+        drop(lock);
+        assert!(!path.exists());
+        drop(LockFile::acquire(&path).unwrap());
+    }
+
+    #[test]
+    fn unreadable_holder_is_treated_as_stale() {
+        let path = temp_path("lock-garbage");
+        fs::write(&path, "not a pid\n").unwrap();
+        let lock = LockFile::acquire(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        drop(lock);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn lock_of_dead_process_is_taken_over() {
+        let path = temp_path("lock-stale");
+        fs::write(&path, format!("{}\n", u32::MAX)).unwrap();
+        drop(LockFile::acquire(&path).unwrap());
+        assert!(!path.exists());
+    }
+}
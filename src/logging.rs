@@ -0,0 +1,119 @@
+use chrono::Local;
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines (default)
+    #[default]
+    Text,
+    /// One JSON object per line with ts, level, target and message
+    Json,
+}
+
+/// 单行 JSON 日志，便于 Loki 等系统采集：`ts`/`level`/`target`/`message` 加上事件字段。
+pub struct JsonLines;
+
+impl<S, N> FormatEvent<S, N> for JsonLines
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut fields = JsonVisitor::default();
+        event.record(&mut fields);
+        let mut fields = fields.0;
+        let meta = event.metadata();
+
+        let mut record = Map::new();
+        record.insert("ts".into(), Value::from(Local::now().to_rfc3339()));
+        record.insert("level".into(), Value::from(meta.level().to_string()));
+        record.insert("target".into(), Value::from(meta.target()));
+        record.insert(
+            "message".into(),
+            fields.remove("message").unwrap_or(Value::from("")),
+        );
+        if let Some(scope) = ctx.event_scope() {
+            let spans: Vec<Value> = scope
+                .from_root()
+                .map(|span| {
+                    let ext = span.extensions();
+                    match ext.get::<FormattedFields<N>>() {
+                        Some(f) if !f.is_empty() => {
+                            Value::from(format!("{}{{{}}}", span.name(), f))
+                        }
+                        _ => Value::from(span.name()),
+                    }
+                })
+                .collect();
+            if !spans.is_empty() {
+                record.insert("spans".into(), Value::Array(spans));
+            }
+        }
+        record.extend(fields);
+
+        writeln!(writer, "{}", Value::Object(record))
+    }
+}
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), Value::from(format!("{value:?}")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::capture_logs;
+
+    #[test]
+    fn events_are_single_json_lines_with_fields_and_spans() {
+        let ((), lines) = capture_logs(|| {
+            let span = tracing::info_span!("router", name = "home");
+            let _entered = span.enter();
+            tracing::info!(status = 200u64, cookie = true, "login response");
+        });
+
+        assert_eq!(lines.len(), 1, "{lines:?}");
+        let line = &lines[0];
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "login response");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["cookie"], true);
+        assert_eq!(line["spans"][0], r#"router{name="home"}"#);
+        assert!(line["ts"].as_str().unwrap().contains('T'));
+    }
+}
@@ -1,35 +1,135 @@
 use anyhow::{Context, Result, bail};
-use chrono::{Local, TimeDelta};
-use clap::Parser;
-use cron::Schedule;
-use reqwest::blocking::Client;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Local, TimeDelta};
+use chrono_tz::Tz;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use config::RouterEntry;
+use control::Control;
+use cookies::CookieJar;
+use exit::{EXIT_CODES_HELP, Failure};
+use fleet::{Router, Runner};
+use health::Health;
+use influx::Influx;
+use lock::LockFile;
+use logging::{JsonLines, LogFormat};
+use metrics::Metrics;
+use notify::{EventKind, Notifier, SmtpTls};
+use password::{PasswordEncoding, Secret};
+use rand::Rng;
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use reqwest::header::{
-    ACCEPT, ACCEPT_LANGUAGE, CACHE_CONTROL, CONNECTION, HeaderMap, HeaderValue, PRAGMA, REFERER,
-    USER_AGENT,
+    ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, CACHE_CONTROL, CONNECTION, HeaderMap, HeaderName,
+    HeaderValue, PRAGMA, REFERER, USER_AGENT, WWW_AUTHENTICATE,
 };
 use reqwest::redirect::Policy;
-use serde_json::json;
+use reqwest::{Certificate, Proxy, StatusCode};
+use retry::{RetryPolicy, retry_with_backoff};
+use schedule::{CronSet, Timetable};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use signals::{SignalWaiter, Wakeup};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::str::FromStr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tracing::{debug, error, info, warn};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use summary::Summary;
+use tracing::{Span, debug, error, field, info, info_span, warn};
 use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
 use url::Url;
 
+mod config;
+mod control;
+mod cookies;
+mod dry_run;
+mod exit;
+mod fleet;
+mod health;
+mod hooks;
+mod http_log;
+mod http_server;
+mod influx;
+mod lock;
+mod logging;
+mod metrics;
+mod notify;
+#[cfg(feature = "otel")]
+mod otel;
+mod password;
+mod profile;
+mod retry;
+mod schedule;
+mod scrape;
+mod signals;
+mod state;
+mod status;
+mod summary;
+#[cfg(test)]
+mod test_support;
+mod verify;
+
 const DEFAULT_CRON: &str = "0 0 4 * * Mon";
+const DEFAULT_LOGIN_TOKEN: &str = "5";
+const DEFAULT_ACCEPT_LANGUAGE: &str = "zh-CN,zh;q=0.9,en;q=0.8";
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36";
+/// `--user-agent-rotate` 的候选：常见桌面浏览器的真实 UA。
+const USER_AGENTS: &[&str] = &[
+    DEFAULT_USER_AGENT,
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:146.0) Gecko/20100101 Firefox/146.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36 Edg/143.0.0.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/26.1 Safari/605.1.15",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36",
+];
+/// 调度意外没有下一次触发时间时，隔多久重新计算一次。
+const NO_FUTURE_RUN_RETRY: Duration = Duration::from_secs(3600);
+/// 登录失败时固件常见的错误提示。
+const LOGIN_FAILURE_MARKERS: &[&str] = &["用户名或密码错误", "密码错误", "登录失败", "loginerrmsg"];
 
-#[derive(Parser, Debug)]
-#[command(name = "tianyi-auto", about = "Login then reboot Tianyi/ZTE router")]
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "tianyi-auto",
+    about = "Login then reboot Tianyi/ZTE router",
+    after_help = EXIT_CODES_HELP
+)]
 struct Args {
-    /// Router password (env: ROUTER_PASSWORD)
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// TOML config file; explicit flags and env vars take precedence over its values
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Router password (env: ROUTER_PASSWORD); overridden by --password-file/--password-stdin
     #[arg(long, env = "ROUTER_PASSWORD")]
-    password: String,
+    password: Option<Secret>,
+    /// Read the password from this file (one trailing newline is trimmed); takes precedence over --password and ROUTER_PASSWORD
+    #[arg(long, conflicts_with = "password_stdin")]
+    password_file: Option<PathBuf>,
+    /// Read the password from standard input; takes precedence over --password and ROUTER_PASSWORD
+    #[arg(long, default_value_t = false)]
+    password_stdin: bool,
+    /// Look up the password in the OS keyring under this service name and --username;
+    /// falls back to --password/ROUTER_PASSWORD/config file when there is no entry
+    /// (requires building with `--features keyring`)
+    #[arg(long)]
+    keyring_service: Option<String>,
+    /// HTTP Basic auth user for admin UIs behind a browser login prompt (separate from --username);
+    /// HTTP Digest is not supported
+    #[arg(long)]
+    http_auth_user: Option<String>,
+    /// HTTP Basic auth password
+    #[arg(long, env = "HTTP_AUTH_PASS")]
+    http_auth_pass: Option<Secret>,
     /// Router username
     #[arg(long, default_value = "useradmin")]
     username: String,
-    /// Router base URL (with scheme)
+    /// Router base URL (with scheme); IPv6 literals go in brackets, e.g. http://[2001:db8::1]:8080.
+    /// A comma-separated list is tried in order until one logs in
     #[arg(long, default_value = "http://192.168.1.1")]
     host: String,
     /// Login path
@@ -38,69 +138,1167 @@ struct Args {
     /// Reboot path
     #[arg(long, default_value = "/common_page/gatewayManage.lua")]
     reboot_path: String,
+    /// Logout path; when set, the admin session is released at the end of each run
+    #[arg(long)]
+    logout_path: Option<String>,
+    /// Protected page fetched right after login; landing on the login form means the login failed
+    #[arg(long)]
+    validate_path: Option<String>,
+    /// Fetch the login page on startup, detect the router model and apply a built-in profile
+    /// (login/reboot paths, reboot CmdType, login token field) to settings left at their defaults
+    #[arg(long, default_value_t = false)]
+    auto_detect: bool,
+    /// Status endpoint that reports DevUpTime (used by --min-uptime-hours)
+    #[arg(long, default_value = "/common_page/deviceinfo.lua")]
+    status_path: String,
+    /// Skip the reboot when the router has been up for less than this many hours
+    #[arg(long)]
+    min_uptime_hours: Option<u64>,
+    /// DHCP/associated-devices endpoint used by --skip-if-clients-above
+    #[arg(long, default_value = "/common_page/lanStatus.lua")]
+    clients_path: String,
+    /// Defer the reboot to the next scheduled run when more than N clients are connected
+    #[arg(long)]
+    skip_if_clients_above: Option<usize>,
+    /// Fail the run instead of rebooting when the client list cannot be fetched
+    #[arg(long, default_value_t = false)]
+    strict_client_check: bool,
     /// Referer for reboot
     #[arg(long, default_value = "/common_page/main.lp")]
     reboot_referer: String,
-    /// Login token value
-    #[arg(long, default_value = "5")]
+    /// How to encode the password in the login form
+    #[arg(long, value_enum, default_value_t = PasswordEncoding::None)]
+    password_encoding: PasswordEncoding,
+    /// Login token value (scraped from the login page when left at the default)
+    #[arg(long, default_value = DEFAULT_LOGIN_TOKEN)]
     login_token: String,
+    /// Always scrape the login token (--field-token) from the login page, falling back to --login-token
+    #[arg(long, default_value_t = false)]
+    auto_token: bool,
+    /// GET the login page first so the session cookie it sets is sent with the login POST
+    #[arg(long, default_value_t = false)]
+    preflight: bool,
+    /// HTTP method of the login request; get sends the form fields as query parameters
+    #[arg(long, value_enum, default_value_t = LoginMethod::Post)]
+    login_method: LoginMethod,
+    /// Encoding of the login POST body; json sends the login fields (named by the --field-* options) as a JSON object
+    #[arg(long, value_enum, default_value_t = LoginBodyFormat::Form)]
+    login_body_format: LoginBodyFormat,
+    /// Text that must appear in the login response body for login to count as successful
+    #[arg(long)]
+    login_success_marker: Option<String>,
+    /// Extra text that marks a failed login when found in the response body (repeatable)
+    #[arg(long)]
+    login_failure_marker: Vec<String>,
+    /// Name of the username field in the login form
+    #[arg(long, default_value = "user_name")]
+    field_username: String,
+    /// Name of the password field in the login form
+    #[arg(long, default_value = "Password")]
+    field_password: String,
+    /// Name of the login token field (also scraped from the login page)
+    #[arg(long, default_value = scrape::TOKEN_FIELD)]
+    field_token: String,
+    /// Name of the action field in the login form
+    #[arg(long, default_value = "action")]
+    field_action: String,
+    /// Name of the frashnum field in the login form
+    #[arg(long, default_value = "frashnum")]
+    field_frashnum: String,
     /// frashnum value
     #[arg(long, default_value = "")]
     frashnum: String,
+    /// Scrape the frashnum value (--field-frashnum) from the login page, falling back to --frashnum
+    #[arg(long, default_value_t = false)]
+    auto_frashnum: bool,
+    /// User-Agent sent with every router request
+    #[arg(long, default_value = DEFAULT_USER_AGENT)]
+    user_agent: String,
+    /// Accept-Language sent with every router request; some firmwares localize their pages by it
+    #[arg(long, default_value = DEFAULT_ACCEPT_LANGUAGE)]
+    accept_language: String,
+    /// Pick a random browser User-Agent from a built-in list for each run (ignored when --user-agent is set)
+    #[arg(long, default_value_t = false)]
+    user_agent_rotate: bool,
+    /// Extra header for the login/reboot requests as "Name: Value" (repeatable; overrides built-in headers)
+    #[arg(long = "header", value_name = "NAME: VALUE")]
+    headers: Vec<String>,
+    /// Never reboot again within this long of the last successful reboot (e.g. 6h; units s/m/h/d)
+    #[arg(long)]
+    min_reboot_interval: Option<String>,
+    /// Persist session cookies to this file so a restart can reuse an unexpired session
+    #[arg(long)]
+    cookie_file: Option<PathBuf>,
+    /// Accept invalid/self-signed TLS certificates from the router (insecure)
+    #[arg(long, default_value_t = false)]
+    insecure_tls: bool,
+    /// Extra PEM root certificate to trust for HTTPS
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+    /// Minimum TLS version accepted from the router
+    #[arg(long, value_enum)]
+    tls_min_version: Option<TlsVersion>,
+    /// Hostname to use for TLS SNI, certificate checks and the Host header while still
+    /// connecting to the IP in --host (for certificates issued to a hostname)
+    #[arg(long)]
+    tls_sni: Option<String>,
+    /// Proxy for router requests (http://, https:// or socks5://, credentials may be embedded)
+    #[arg(long, conflicts_with = "no_proxy")]
+    proxy: Option<String>,
+    /// Connect directly, ignoring --proxy and HTTP_PROXY/HTTPS_PROXY/ALL_PROXY
+    #[arg(long, default_value_t = false)]
+    no_proxy: bool,
+    /// Log in (and out) without rebooting; with --reboot-payload, send that command instead
+    #[arg(long, default_value_t = false)]
+    no_reboot: bool,
+    /// Second request for two-step reboots; carries a token taken from the first response
+    #[arg(long)]
+    reboot_confirm_path: Option<String>,
+    /// Name of the confirmation token in the first reboot response (JSON key or form field)
+    #[arg(long, default_value = "_sessionTOKEN")]
+    reboot_confirm_field: String,
+    /// Scrape a CSRF token from the --reboot-referer page after login and send it in this header
+    /// with the reboot request (e.g. X-CSRF-Token)
+    #[arg(long)]
+    csrf_header_name: Option<String>,
+    /// Fail the reboot when --csrf-header-name is set but no CSRF token is found
+    #[arg(long, default_value_t = false)]
+    require_csrf: bool,
+    /// Command to send after logging in; other firmware commands (WAN reconnect, DNS flush, ...)
+    /// can be sent with `--command custom --reboot-payload`
+    #[arg(long = "command", value_enum, default_value_t = RouterCommand::Reboot)]
+    maintenance_command: RouterCommand,
+    /// Raw JSON sent verbatim as the payload of the reboot request (see --reboot-body-mode)
+    #[arg(long, conflicts_with = "reboot_cmdtype")]
+    reboot_payload: Option<String>,
+    /// CmdType used in the default reboot payload
+    #[arg(long, default_value = "HG_COMMAND_REBOOT")]
+    reboot_cmdtype: String,
+    /// How the command payload is encoded in the request body
+    #[arg(long, value_enum, default_value_t = RebootBodyMode::FormJsonCfg)]
+    reboot_body_mode: RebootBodyMode,
+    /// Form field holding the payload with --reboot-body-mode form-custom
+    #[arg(long)]
+    reboot_field_name: Option<String>,
+    /// JSON field in the reboot response holding the router's result code (empty to disable the check)
+    #[arg(long, default_value = "IF_ERRORID")]
+    reboot_success_field: String,
+    /// Result code value that means the reboot was accepted
+    #[arg(long, default_value = "0")]
+    reboot_success_value: String,
     /// Add timestamp query param on reboot
     #[arg(long, default_value_t = true)]
     reboot_timestamp: bool,
+    /// Query parameter name for the reboot timestamp (e.g. timeStamp, _, t, random)
+    #[arg(long, default_value = "timeStamp")]
+    timestamp_param: String,
+    /// Unit of the reboot timestamp value
+    #[arg(long, value_enum, default_value_t = TimestampFormat::Millis)]
+    timestamp_format: TimestampFormat,
+    /// Send TCP keepalive probes on router connections every N seconds
+    #[arg(long)]
+    tcp_keepalive_secs: Option<u64>,
+    /// Drop pooled connections idle for longer than this (0 = keep them forever)
+    #[arg(long, default_value_t = 90)]
+    pool_idle_timeout_secs: u64,
+    /// Maximum idle pooled connections kept per host
+    #[arg(long)]
+    pool_max_idle_per_host: Option<usize>,
+    /// Redirects to follow per request (0 = do not follow)
+    #[arg(long, default_value_t = 4)]
+    max_redirects: usize,
     /// Request timeout seconds
     #[arg(long, default_value_t = 10)]
     timeout_secs: u64,
-    /// Cron expression for scheduled runs (local time). Default: Mon 04:00 (sec min hour dom mon dow)
+    /// Timeout seconds for establishing the TCP/TLS connection (default: bounded only by --timeout-secs); keeps reboot verification polls snappy while the router is down
+    #[arg(long)]
+    connect_timeout_secs: Option<u64>,
+    /// Pause between a successful login and the reboot request
+    #[arg(long, default_value_t = 0)]
+    post_login_delay_ms: u64,
+    /// Retries for login/reboot on connection errors or timeouts
+    #[arg(long, default_value_t = 3)]
+    max_retries: u32,
+    /// Stop retrying login/reboot once this much time has passed in a run (e.g. 10m; units s/m/h/d)
+    #[arg(long)]
+    retry_deadline: Option<String>,
+    /// Base delay before the first retry; doubles on every further attempt (plus jitter)
+    #[arg(long, default_value_t = 500)]
+    retry_base_delay_ms: u64,
+    /// After rebooting, wait for the router to go down and come back online
+    #[arg(long, default_value_t = false)]
+    verify_reboot: bool,
+    /// With --verify-reboot, redo login+reboot up to N times when the router never went offline
+    #[arg(long, default_value_t = 0)]
+    reboot_verify_retries: u32,
+    /// Interval between reachability polls while verifying a reboot
+    #[arg(long, default_value_t = 5)]
+    poll_interval_secs: u64,
+    /// Give up verifying if the router is not back online within this many seconds
+    #[arg(long, default_value_t = 180)]
+    reboot_wait_secs: u64,
+    /// With --verify-reboot, wait this many seconds after the reboot response before polling, so the still-shutting-down router is not mistaken for "still up"
+    #[arg(long, default_value_t = 5)]
+    reboot_settle_secs: u64,
+    /// Cron expression for scheduled runs (local time). Accepts 5 fields (min hour dom mon dow), 6/7 fields with leading seconds, or a macro: @hourly = `0 * * * *`, @daily = `0 0 * * *`, @weekly = `0 0 * * Sun`, @monthly = `0 0 1 * *`, @yearly = `0 0 1 1 *`. Repeat to combine schedules. Default: Mon 04:00
     #[arg(long, default_value = DEFAULT_CRON)]
-    cron: String,
+    cron: Vec<String>,
+    /// Run every fixed interval from process start instead of --cron (e.g. 12h, 90m, 1h30m; units s/m/h/d)
+    #[arg(long)]
+    interval: Option<String>,
+    /// IANA timezone for evaluating --cron (e.g. Asia/Shanghai); defaults to the host's local timezone
+    #[arg(long)]
+    timezone: Option<Tz>,
+    /// Delay each scheduled run by a random 0..=N seconds (never past the following occurrence)
+    #[arg(long, default_value_t = 0)]
+    jitter_secs: u64,
+    /// Check that each router's login page is reachable on startup and abort if not
+    #[arg(long, default_value_t = false)]
+    verify_host: bool,
+    /// On startup, wait up to this long for each router's web UI to answer before the first run (e.g. 3m)
+    #[arg(long)]
+    startup_grace: Option<String>,
     /// Run once immediately on start
     #[arg(long, default_value_t = false)]
     run_now: bool,
+    /// Run a single cycle and exit without scheduling (exit code 0 on success, non-zero on failure)
+    #[arg(long, default_value_t = false)]
+    once: bool,
+    /// What the scheduler does after a failed run: keep going, or exit non-zero
+    #[arg(long, value_enum, default_value_t = OnFailure::Continue)]
+    on_failure: OnFailure,
+    /// After a failed scheduled run, try again after this cooldown instead of waiting for the next
+    /// scheduled time (e.g. 30m)
+    #[arg(long)]
+    retry_on_failure_after: Option<String>,
+    /// Maximum consecutive cooldown retries before waiting for the next scheduled run
+    #[arg(long, default_value_t = 3)]
+    max_failure_retries: u32,
+    /// Exit after this many scheduled runs instead of looping forever
+    #[arg(long)]
+    max_runs: Option<u64>,
+    /// Print totals (runs, successes, failures, observed downtime, last error) before exiting
+    #[arg(long, default_value_t = false)]
+    summary: bool,
+    /// Count the --run-now immediate run towards --max-runs
+    #[arg(long, default_value_t = false)]
+    count_run_now: bool,
+    /// Refuse to start while another instance holds this lock file (contains the holder's pid)
+    #[arg(long)]
+    lock_file: Option<PathBuf>,
+    /// Write the last run time, result and next scheduled run to this JSON file after each run
+    #[arg(long)]
+    state_file: Option<PathBuf>,
+    /// On startup, run once immediately if a scheduled run was missed since the last run in --state-file
+    #[arg(long, default_value_t = false)]
+    catch_up: bool,
+    /// Process the routers from the config file concurrently (bounded worker pool)
+    #[arg(long, default_value_t = false)]
+    parallel: bool,
+    /// POST a JSON event to this URL after each run
+    #[arg(long)]
+    webhook_url: Option<String>,
+    /// Telegram bot token for sendMessage notifications
+    #[arg(long)]
+    telegram_bot_token: Option<String>,
+    /// Telegram chat id to notify
+    #[arg(long)]
+    telegram_chat_id: Option<String>,
+    /// Discord webhook URL to post run results to
+    #[arg(long)]
+    discord_webhook_url: Option<String>,
+    /// Slack incoming webhook URL to post run results to
+    #[arg(long)]
+    slack_webhook_url: Option<String>,
+    /// ntfy topic URL (e.g. https://ntfy.sh/my-router) to push run results to
+    #[arg(long)]
+    ntfy_url: Option<String>,
+    /// ntfy priority 1-5 for notifications; failures are sent at least at 4 (high)
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=5))]
+    ntfy_priority: u8,
+    /// SMTP server for email notifications
+    #[arg(long)]
+    smtp_host: Option<String>,
+    /// SMTP port (default: 465 with implicit TLS, otherwise 587)
+    #[arg(long)]
+    smtp_port: Option<u16>,
+    /// SMTP encryption (default: implicit on port 465, otherwise starttls)
+    #[arg(long, value_enum)]
+    smtp_tls: Option<SmtpTls>,
+    /// SMTP username
+    #[arg(long)]
+    smtp_user: Option<String>,
+    /// SMTP password
+    #[arg(long, env = "SMTP_PASS")]
+    smtp_pass: Option<String>,
+    /// Sender address for email notifications
+    #[arg(long)]
+    email_from: Option<String>,
+    /// Recipient address for email notifications (repeatable)
+    #[arg(long)]
+    email_to: Vec<String>,
+    /// MQTT broker for publishing run results (mqtt://host:1883 or host[:port])
+    #[arg(long)]
+    mqtt_broker: Option<String>,
+    /// MQTT topic for the retained status message
+    #[arg(long, default_value = "tianyi-auto/status")]
+    mqtt_topic: String,
+    /// MQTT username
+    #[arg(long)]
+    mqtt_user: Option<String>,
+    /// MQTT password
+    #[arg(long, env = "MQTT_PASS")]
+    mqtt_pass: Option<String>,
+    /// Gotify server URL (e.g. https://gotify.example.com) to push run results to
+    #[arg(long)]
+    gotify_url: Option<String>,
+    /// Gotify application token
+    #[arg(long, env = "GOTIFY_TOKEN")]
+    gotify_token: Option<String>,
+    /// PushOver application API token
+    #[arg(long, env = "PUSHOVER_TOKEN")]
+    pushover_token: Option<String>,
+    /// PushOver user (or group) key to deliver to; failures are sent at high priority
+    #[arg(long)]
+    pushover_user: Option<String>,
+    /// Matrix homeserver URL (e.g. https://matrix.example.org) to post run results to
+    #[arg(long)]
+    matrix_homeserver: Option<String>,
+    /// Matrix access token of the posting account
+    #[arg(long, env = "MATRIX_TOKEN")]
+    matrix_token: Option<String>,
+    /// Matrix room id to post to (e.g. !abc123:example.org)
+    #[arg(long)]
+    matrix_room_id: Option<String>,
+    /// Which run results trigger notifications (comma-separated)
+    /// (skipped = reboot skipped by --min-uptime-hours, --skip-if-clients-above or --min-reboot-interval)
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [EventKind::Success, EventKind::Failure])]
+    notify_on: Vec<EventKind>,
+    /// Max bytes of each response body to include in debug logs
+    #[arg(long, default_value_t = 2048)]
+    max_log_body: usize,
+    /// Log the login/reboot requests (secrets redacted) instead of sending them
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    /// Print an equivalent curl command for the login and reboot requests (password redacted)
+    #[arg(long, default_value_t = false)]
+    print_request: bool,
+    /// Log method, URL and headers of every login/reboot request and status and headers of its response (cookies and credentials redacted)
+    #[arg(long, default_value_t = false)]
+    trace_http: bool,
+    /// Shell command run before each run; a non-zero exit aborts the run
+    #[arg(long)]
+    pre_run_cmd: Option<String>,
+    /// Shell command run after each run, with TIANYI_RESULT (success/failure) and TIANYI_ERROR set
+    #[arg(long)]
+    post_run_cmd: Option<String>,
+    /// Serve Prometheus metrics at http://ADDR/metrics (e.g. 0.0.0.0:9898)
+    #[arg(long)]
+    metrics_addr: Option<String>,
+    /// InfluxDB v2 base URL; a line-protocol point is written after each run
+    #[arg(long)]
+    influx_url: Option<String>,
+    /// InfluxDB API token
+    #[arg(long, env = "INFLUX_TOKEN")]
+    influx_token: Option<String>,
+    /// InfluxDB organization
+    #[arg(long)]
+    influx_org: Option<String>,
+    /// InfluxDB bucket
+    #[arg(long)]
+    influx_bucket: Option<String>,
+    /// Serve /healthz and /ready probes at http://ADDR (e.g. 0.0.0.0:8080)
+    #[arg(long)]
+    health_addr: Option<String>,
+    /// OTLP/HTTP endpoint to export a trace per run to, e.g. http://collector:4318/v1/traces
+    /// (requires building with `--features otel`)
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+    /// Address for the control API (POST /reboot, GET /status), e.g. 127.0.0.1:9102
+    #[arg(long)]
+    control_addr: Option<String>,
+    /// Bearer token required by every control API request
+    #[arg(long, env = "CONTROL_TOKEN")]
+    control_token: Option<String>,
+    /// Print the fully resolved configuration as JSON (password redacted) and exit
+    #[arg(long, default_value_t = false)]
+    show_config: bool,
+    /// Log output format
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
     /// Verbose logging
     #[arg(long, short, default_value_t = false)]
     verbose: bool,
 }
 
+/// `--tls-min-version`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+enum TlsVersion {
+    #[value(name = "1.2")]
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[value(name = "1.3")]
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    fn to_reqwest(self) -> reqwest::tls::Version {
+        match self {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+}
+
+/// 调度循环里一次运行失败后的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum OnFailure {
+    /// Log the failure and keep the scheduler running (default)
+    #[default]
+    Continue,
+    /// Exit with the run's error so a supervisor can restart or alert
+    Exit,
+}
+
+/// 重启 URL 上时间戳参数的单位。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TimestampFormat {
+    /// Milliseconds since the Unix epoch (default)
+    Millis,
+    /// Seconds since the Unix epoch
+    Secs,
+}
+
+impl TimestampFormat {
+    fn now(self) -> String {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        match self {
+            TimestampFormat::Millis => since_epoch.as_millis().to_string(),
+            TimestampFormat::Secs => since_epoch.as_secs().to_string(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Print the next N scheduled run times for --cron/--interval and exit (no network access)
+    Next {
+        /// Number of occurrences to print
+        #[arg(default_value_t = 5)]
+        count: usize,
+    },
+    /// Log in to each router once without rebooting; exits non-zero if any login fails
+    TestLogin,
+    /// Log in to each router and print the cookies it set (values redacted unless --show-values)
+    DumpCookies {
+        /// Print cookie values instead of ***
+        #[arg(long, default_value_t = false)]
+        show_values: bool,
+    },
+    /// Check the configuration (URLs, cron, notification settings) without any network access
+    Validate,
+    /// Print every --command and the payload it sends (no network access)
+    ListCommands,
+    /// Print every run the scheduler would perform between two times, honoring --run-now,
+    /// --catch-up and --max-runs (no network access; jitter is not applied)
+    Simulate {
+        /// Virtual start time (RFC 3339, or local "YYYY-MM-DD HH:MM[:SS]")
+        #[arg(long, value_parser = schedule::parse_timestamp)]
+        from: DateTime<Local>,
+        /// Virtual end time (inclusive)
+        #[arg(long, value_parser = schedule::parse_timestamp)]
+        until: DateTime<Local>,
+    },
+    /// Read a password from standard input and store it in the OS keyring under
+    /// --keyring-service and --username (requires building with `--features keyring`)
+    SetPassword,
+}
+
+impl Config {
+    /// 每个路由器请求都显式带上本次运行的 UA（覆盖客户端默认请求头，--header 仍可覆盖它）。
+    fn user_agent(&self) -> HeaderValue {
+        self.user_agent
+            .lock()
+            .expect("user agent lock poisoned")
+            .clone()
+    }
+
+    /// `--user-agent-rotate`：每次运行开始时随机换一个 UA。
+    fn rotate_user_agent(&self) {
+        if !self.user_agent_rotate {
+            return;
+        }
+        let ua = USER_AGENTS[rand::thread_rng().gen_range(0..USER_AGENTS.len())];
+        debug!(user_agent = ua, "Rotated User-Agent");
+        *self.user_agent.lock().expect("user agent lock poisoned") = HeaderValue::from_static(ua);
+    }
+}
+
+/// `--command`：登录后下发的命令；custom 只发送命令，不做掉线验证。
+/// 只内置确认过 CmdType 的重启，其他维护命令按各自固件的抓包写进 --reboot-payload。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum RouterCommand {
+    /// Reboot the router (CmdType from --reboot-cmdtype)
+    #[default]
+    Reboot,
+    /// Send --reboot-payload verbatim, e.g. a WAN reconnect or DNS flush captured from your firmware
+    Custom,
+}
+
+impl RouterCommand {
+    fn name(self) -> &'static str {
+        match self {
+            RouterCommand::Reboot => "reboot",
+            RouterCommand::Custom => "custom command",
+        }
+    }
+}
+
+/// 命令请求体的编码方式：多数固件用 `jsonCfg` 表单字段，也有直接收 JSON 或换了字段名的。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Serialize)]
+enum RebootBodyMode {
+    /// Form field jsonCfg holding the JSON payload (default)
+    #[default]
+    #[value(name = "form-jsonCfg")]
+    #[serde(rename = "form-jsonCfg")]
+    FormJsonCfg,
+    /// The JSON payload as the request body (Content-Type: application/json)
+    #[value(name = "raw-json")]
+    #[serde(rename = "raw-json")]
+    RawJson,
+    /// Form field named by --reboot-field-name holding the JSON payload
+    #[value(name = "form-custom")]
+    #[serde(rename = "form-custom")]
+    FormCustom,
+}
+
+/// 登录请求的 HTTP 方法：少数固件用 GET + 查询参数登录。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum LoginMethod {
+    /// Form POST (default)
+    Post,
+    /// GET with the fields as query parameters
+    Get,
+}
+
+/// 登录 POST 的请求体格式：较新的 ZTE 网页用 JSON 登录。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum LoginBodyFormat {
+    /// application/x-www-form-urlencoded (default)
+    Form,
+    /// application/json
+    Json,
+}
+
+/// 登录表单的字段名；不同 OEM 固件不尽相同（如 `Username`/`Passwd`）。
+#[derive(Debug, Clone)]
+struct LoginFields {
+    username: String,
+    password: String,
+    token: String,
+    action: String,
+    frashnum: String,
+}
+
 #[derive(Debug)]
 struct Config {
+    /// 日志/汇总里使用的路由器名称（默认为 host）
+    name: String,
     login_url: Url,
     reboot_url: Url,
     reboot_referer: Url,
+    reboot_confirm_url: Option<Url>,
+    reboot_confirm_field: String,
+    /// `--csrf-header-name`：重启请求携带 CSRF token 的请求头
+    csrf_header: Option<HeaderName>,
+    require_csrf: bool,
+    logout_url: Option<Url>,
+    validate_url: Option<Url>,
+    status_url: Url,
+    min_uptime: Option<Duration>,
+    clients_url: Url,
+    skip_if_clients_above: Option<usize>,
+    strict_client_check: bool,
     username: String,
-    password: String,
+    password: Secret,
+    password_encoding: PasswordEncoding,
     login_token: String,
+    auto_token: bool,
+    preflight: bool,
+    login_method: LoginMethod,
+    login_body_format: LoginBodyFormat,
+    login_fields: LoginFields,
+    login_success_marker: Option<String>,
+    login_failure_markers: Vec<String>,
     frashnum: String,
+    auto_frashnum: bool,
+    /// --command 选择的命令
+    command: RouterCommand,
+    reboot_cmdtype: String,
+    /// 承载命令 JSON 的表单字段；`None` 表示 --reboot-body-mode raw-json，直接以 JSON 作为请求体
+    reboot_field: Option<String>,
+    /// --reboot-payload 原文（已校验为 JSON），替代内置模板
+    custom_payload: Option<String>,
+    no_reboot: bool,
+    reboot_success_field: String,
+    reboot_success_value: String,
+    /// --header 指定的额外请求头，覆盖同名的默认请求头
+    extra_headers: HeaderMap,
+    /// 本次运行使用的 User-Agent；--user-agent-rotate 时每次运行重新挑选
+    user_agent: Mutex<HeaderValue>,
+    user_agent_rotate: bool,
     add_timestamp: bool,
+    timestamp_param: String,
+    timestamp_format: TimestampFormat,
+    post_login_delay: Duration,
+    retry: RetryPolicy,
+    cookie_jar: Option<CookieJar>,
+    dry_run: bool,
+    print_request: bool,
+    trace_http: bool,
+    pre_run_cmd: Option<String>,
+    post_run_cmd: Option<String>,
+    max_log_body: usize,
+    verify_reboot: bool,
+    poll_interval: Duration,
+    reboot_wait: Duration,
+    /// 重启响应后、开始校验前的等待时间，避免路由器尚未关机就被判定为“未掉线”。
+    reboot_settle: Duration,
+    reboot_verify_retries: u32,
+    min_reboot_interval: Option<Duration>,
+    /// 最近一次成功下发重启的时间（启动时从 --state-file 恢复）；同一台路由器的各个 --host 地址共用
+    last_reboot: Arc<Mutex<Option<DateTime<Local>>>>,
+    /// 最近一次校验重启观察到的掉线时长，由 `Runner` 取走计入 --summary
+    last_downtime: Mutex<Option<Duration>>,
+}
+
+fn main() {
+    let result = run();
+    if let Err(e) = &result {
+        eprintln!("Error: {e:?}");
+    }
+    std::process::exit(result.map_or_else(|e| exit::code(&e), |()| 0));
+}
+
+/// 解析命令行并合并 --config 之后的结果。
+struct Cli {
+    args: Args,
+    /// 配置文件里的 `[[routers]]`
+    routers: Vec<RouterEntry>,
+    /// 命令行、环境变量或配置文件里显式给出了 --cron（而不是默认值），用于与 --interval 的互斥检查
+    cron_explicit: bool,
+}
+
+fn parse_cli(matches: &ArgMatches) -> Result<Cli> {
+    let mut args = Args::from_arg_matches(matches).unwrap_or_else(|e| e.exit());
+    let mut routers = Vec::new();
+    let mut cron_explicit = matches
+        .value_source("cron")
+        .is_some_and(|source| source != ValueSource::DefaultValue);
+    if let Some(path) = args.config.clone() {
+        let mut file = config::load(&path).context(Failure::Config)?;
+        routers = std::mem::take(&mut file.routers);
+        cron_explicit |= file.cron.is_some();
+        config::merge(&mut args, file, matches);
+    }
+    Ok(Cli {
+        args,
+        routers,
+        cron_explicit,
+    })
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    init_logger(args.verbose);
+fn run() -> Result<()> {
+    let Cli {
+        mut args,
+        routers,
+        cron_explicit,
+    } = parse_cli(&Args::command().get_matches())?;
+    if cron_explicit && args.interval.is_some() {
+        return Err(anyhow::anyhow!(
+            "--interval and --cron are mutually exclusive; use only one of them"
+        ))
+        .context(Failure::Config);
+    }
+    init_logger(args.verbose, args.log_format, args.otlp_endpoint.as_deref());
+
+    if let Some(Command::Next { count }) = args.command {
+        return print_next_runs(&args, count);
+    }
+    if let Some(Command::Validate) = args.command {
+        return validate(args, routers);
+    }
+    if let Some(Command::ListCommands) = args.command {
+        list_commands(&args);
+        return Ok(());
+    }
+    if let Some(Command::Simulate { from, until }) = args.command {
+        return simulate(&args, from, until);
+    }
+    if let Some(Command::SetPassword) = args.command {
+        return set_password(&args);
+    }
+
+    // 只读取一次，所有路由器共用（[[routers]] 里单独配置的 password 仍然优先）。
+    resolve_password(&mut args, password::read_keyring)?;
 
     // 定时任务使用 chrono::Local，容器里若未配置时区（常见为 UTC），cron 会按 UTC 解释而发生整体偏移。
     log_time_diagnostics();
 
-    let base = Url::parse(&args.host).context("invalid host URL")?;
-    let cfg = Config {
+    let mut router_args = router_args(&args, routers);
+    if args.auto_detect {
+        auto_detect(&mut router_args);
+    }
+
+    if args.show_config {
+        return show_config(&args, router_args).context(Failure::Config);
+    }
+
+    if let Some(Command::DumpCookies { show_values }) = args.command {
+        return dump_cookies(router_args, show_values);
+    }
+
+    let routers = router_args
+        .into_iter()
+        .map(|(name, a)| build_router(name, &a))
+        .collect::<Result<Vec<_>>>()
+        .context(Failure::Config)?;
+
+    if let Some(Command::TestLogin) = args.command {
+        return test_login(&routers).context(Failure::Login);
+    }
+
+    if let Some(grace) = &args.startup_grace {
+        let grace = schedule::parse_duration(grace)
+            .context("invalid --startup-grace")
+            .context(Failure::Config)?;
+        warm_up(&routers, grace);
+    }
+    if args.verify_host {
+        verify_hosts(&routers).context(Failure::Config)?;
+    }
+
+    // 持有到 run 返回（包括收到 SIGINT/SIGTERM 后的正常退出）时释放。
+    let _lock = args
+        .lock_file
+        .as_deref()
+        .map(LockFile::acquire)
+        .transpose()?;
+
+    control::check_args(&args).context(Failure::Config)?;
+    let notifier = Notifier::from_args(&args).context(Failure::Config)?;
+    let metrics = Arc::new(Metrics::default());
+    if let Some(addr) = &args.metrics_addr {
+        let metrics = Arc::clone(&metrics);
+        http_server::spawn("metrics", addr, move |req| {
+            metrics.handle(req.method(), req.url())
+        });
+    }
+    let health = Arc::new(Health::default());
+    if let Some(addr) = &args.health_addr {
+        let health = Arc::clone(&health);
+        http_server::spawn("health", addr, move |req| {
+            health.handle(req.method(), req.url())
+        });
+    }
+    let runner = Runner {
+        routers,
+        parallel: args.parallel,
+        notifier,
+        metrics,
+        influx: Influx::from_args(&args).context(Failure::Config)?,
+        health,
+        summary: Summary::default(),
+    };
+
+    // 交给系统 cron / Kubernetes CronJob 调度时使用：不进入调度循环。
+    let result = if args.once {
+        if args.control_addr.is_some() {
+            warn!("--control-addr is ignored with --once");
+        }
+        info!("Running a single cycle due to --once");
+        runner.run_all().map(drop)
+    } else {
+        run_scheduler(&runner, &args)
+    };
+    if args.summary {
+        runner.summary.print();
+    }
+    result
+}
+
+/// `--host a,b`：第一个地址作为主配置，其余各自生成一份备用配置，共用同一个 HTTP 客户端。
+/// 备用地址不读写 --cookie-file，避免与主配置争用同一个文件。
+fn build_router(name: String, args: &Args) -> Result<Router> {
+    let hosts: Vec<&str> = args
+        .host
+        .split(',')
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+        .collect();
+    let Some((primary, others)) = hosts.split_first() else {
+        bail!("--host is empty for router {name}");
+    };
+    if !others.is_empty() && args.tls_sni.is_some() {
+        bail!("--tls-sni cannot be combined with multiple hosts");
+    }
+    let with_host = |host: &str| {
+        let mut a = args.clone();
+        a.host = host.to_string();
+        a
+    };
+    let primary_args = with_host(primary);
+    let cfg = build_config(name.clone(), &primary_args)?;
+    let fallbacks = others
+        .iter()
+        .map(|host| {
+            let mut a = with_host(host);
+            a.cookie_file = None;
+            let mut fallback = build_config(name.clone(), &a)?;
+            // 无论经由哪个地址重启，都是同一台设备：--min-reboot-interval 必须看到同一个时间。
+            fallback.last_reboot = Arc::clone(&cfg.last_reboot);
+            Ok(fallback)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let client = build_client(&primary_args, cfg.cookie_jar.as_ref())?;
+    Ok(Router {
+        cfg,
+        fallbacks,
+        client,
+    })
+}
+
+fn build_config(name: String, args: &Args) -> Result<Config> {
+    if args.login_method == LoginMethod::Get && args.login_body_format == LoginBodyFormat::Json {
+        bail!("--login-body-format json requires --login-method post");
+    }
+    let mut base =
+        parse_host(&args.host).with_context(|| format!("invalid host URL for router {name}"))?;
+    if let Some(sni) = &args.tls_sni {
+        base.set_host(Some(sni))
+            .with_context(|| format!("invalid --tls-sni {sni:?}"))?;
+    }
+    Ok(Config {
         login_url: build_url(&base, &args.login_path)?,
         reboot_url: build_url(&base, &args.reboot_path)?,
         reboot_referer: build_url(&base, &args.reboot_referer)?,
-        username: args.username,
-        password: args.password,
-        login_token: args.login_token,
-        frashnum: args.frashnum,
+        reboot_confirm_url: args
+            .reboot_confirm_path
+            .as_deref()
+            .map(|p| build_url(&base, p))
+            .transpose()?,
+        reboot_confirm_field: args.reboot_confirm_field.clone(),
+        csrf_header: args
+            .csrf_header_name
+            .as_deref()
+            .map(|name| {
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("invalid --csrf-header-name {name:?}"))
+            })
+            .transpose()?,
+        require_csrf: args.require_csrf,
+        logout_url: args
+            .logout_path
+            .as_deref()
+            .map(|p| build_url(&base, p))
+            .transpose()?,
+        validate_url: args
+            .validate_path
+            .as_deref()
+            .map(|p| build_url(&base, p))
+            .transpose()?,
+        status_url: build_url(&base, &args.status_path)?,
+        clients_url: build_url(&base, &args.clients_path)?,
+        skip_if_clients_above: args.skip_if_clients_above,
+        strict_client_check: args.strict_client_check,
+        min_uptime: args
+            .min_uptime_hours
+            .map(|h| Duration::from_secs(h * 3600)),
+        username: args.username.clone(),
+        password: args.password.clone().with_context(|| {
+            format!(
+                "router password is required for {name} (--password, --password-file, --password-stdin, ROUTER_PASSWORD or config file)"
+            )
+        })?,
+        password_encoding: args.password_encoding,
+        auto_token: args.auto_token || args.login_token == DEFAULT_LOGIN_TOKEN,
+        login_token: args.login_token.clone(),
+        preflight: args.preflight,
+        login_method: args.login_method,
+        login_body_format: args.login_body_format,
+        login_fields: LoginFields {
+            username: args.field_username.clone(),
+            password: args.field_password.clone(),
+            token: args.field_token.clone(),
+            action: args.field_action.clone(),
+            frashnum: args.field_frashnum.clone(),
+        },
+        login_success_marker: args.login_success_marker.clone(),
+        login_failure_markers: args.login_failure_marker.clone(),
+        frashnum: args.frashnum.clone(),
+        auto_frashnum: args.auto_frashnum,
+        command: args.maintenance_command,
+        reboot_cmdtype: args.reboot_cmdtype.clone(),
+        reboot_field: reboot_field(args)?,
+        custom_payload: custom_payload(args)?,
+        no_reboot: args.no_reboot,
+        reboot_success_field: args.reboot_success_field.clone(),
+        reboot_success_value: args.reboot_success_value.clone(),
+        extra_headers: parse_headers(&args.headers)?,
+        user_agent: Mutex::new(
+            HeaderValue::from_str(&args.user_agent).context("invalid --user-agent")?,
+        ),
+        // 显式指定的 --user-agent 优先于轮换。
+        user_agent_rotate: args.user_agent_rotate && args.user_agent == DEFAULT_USER_AGENT,
         add_timestamp: args.reboot_timestamp,
-    };
+        timestamp_param: args.timestamp_param.clone(),
+        timestamp_format: args.timestamp_format,
+        post_login_delay: Duration::from_millis(args.post_login_delay_ms),
+        retry: RetryPolicy::new(
+            args.max_retries,
+            Duration::from_millis(args.retry_base_delay_ms),
+            args.retry_deadline
+                .as_deref()
+                .map(schedule::parse_duration)
+                .transpose()
+                .context("invalid --retry-deadline")?,
+        ),
+        cookie_jar: args.cookie_file.clone().map(CookieJar::load).transpose()?,
+        dry_run: args.dry_run,
+        print_request: args.print_request,
+        trace_http: args.trace_http,
+        pre_run_cmd: args.pre_run_cmd.clone(),
+        post_run_cmd: args.post_run_cmd.clone(),
+        max_log_body: args.max_log_body,
+        verify_reboot: args.verify_reboot,
+        poll_interval: Duration::from_secs(args.poll_interval_secs),
+        reboot_wait: Duration::from_secs(args.reboot_wait_secs),
+        reboot_settle: Duration::from_secs(args.reboot_settle_secs),
+        reboot_verify_retries: args.reboot_verify_retries,
+        min_reboot_interval: args
+            .min_reboot_interval
+            .as_deref()
+            .map(schedule::parse_duration)
+            .transpose()
+            .context("invalid --min-reboot-interval")?,
+        last_reboot: Arc::new(Mutex::new(previous_reboot(args, &name))),
+        last_downtime: Mutex::new(None),
+        name,
+    })
+}
+
+/// 每台路由器的完整参数；多台共用 --cookie-file 时各自使用独立的文件。
+fn router_args(args: &Args, entries: Vec<RouterEntry>) -> Vec<(String, Args)> {
+    if entries.is_empty() {
+        return vec![(args.host.clone(), args.clone())];
+    }
+    let multi = entries.len() > 1;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let (name, mut a) = entry.apply(args);
+            if multi {
+                a.cookie_file = a.cookie_file.map(|p| cookies::per_router_path(&p, &name));
+            }
+            (name, a)
+        })
+        .collect()
+}
+
+/// 顺序：--password-stdin > --password-file > 密钥环 > --password / ROUTER_PASSWORD / 配置文件。
+/// 密钥环读取由调用方传入，测试里不访问真实的系统密钥环。
+fn resolve_password(
+    args: &mut Args,
+    read_keyring: impl Fn(&str, &str) -> Result<Option<Secret>>,
+) -> Result<()> {
+    if args.password_stdin {
+        args.password = Some(password::read_stdin().context(Failure::Config)?);
+    } else if let Some(path) = &args.password_file {
+        args.password = Some(password::read_file(path).context(Failure::Config)?);
+    } else if let Some(service) = &args.keyring_service {
+        match read_keyring(service, &args.username) {
+            Ok(Some(password)) => {
+                debug!(service, username = %args.username, "Using password from keyring");
+                args.password = Some(password);
+            }
+            Ok(None) => info!(
+                service,
+                username = %args.username,
+                "No keyring entry, falling back to other password sources"
+            ),
+            Err(e) => warn!("Keyring unavailable, falling back to other password sources: {e:#}"),
+        }
+    }
+    Ok(())
+}
 
-    let client = build_client(args.timeout_secs)?;
+/// `set-password` 子命令：从标准输入读取密码并写入 --keyring-service 指定的密钥环条目。
+fn set_password(args: &Args) -> Result<()> {
+    let service = args
+        .keyring_service
+        .as_deref()
+        .context("set-password requires --keyring-service")
+        .context(Failure::Config)?;
+    let password = password::read_stdin().context(Failure::Config)?;
+    password::store_keyring(service, &args.username, &password)?;
+    println!(
+        "password stored in keyring (service {service}, user {})",
+        args.username
+    );
+    Ok(())
+}
+
+/// `validate` 子命令：逐项检查配置并列出所有问题，不访问网络。
+fn validate(mut args: Args, entries: Vec<RouterEntry>) -> Result<()> {
+    let mut problems = Vec::new();
+    if args.password_stdin {
+        // 不读取标准输入，只检查其余设置。
+        let Ok(placeholder) = "<stdin>".parse::<Secret>();
+        args.password = Some(placeholder);
+    } else if let Some(path) = &args.password_file {
+        match password::read_file(path) {
+            Ok(password) => args.password = Some(password),
+            Err(e) => problems.push(format!("{e:#}")),
+        }
+    } else if args.keyring_service.is_some() && args.password.is_none() {
+        // 访问密钥环可能弹出系统授权提示，这里不读取。
+        let Ok(placeholder) = "<keyring>".parse::<Secret>();
+        args.password = Some(placeholder);
+    }
+    for (name, a) in router_args(&args, entries) {
+        if let Err(e) = build_router(name.clone(), &a) {
+            problems.push(format!("router {name}: {e:#}"));
+        }
+    }
+    if let Err(e) = timetable(&args) {
+        problems.push(format!("schedule: {e:#}"));
+    }
+    if let Some(grace) = &args.startup_grace
+        && let Err(e) = schedule::parse_duration(grace)
+    {
+        problems.push(format!("invalid --startup-grace: {e:#}"));
+    }
+    if let Some(cooldown) = &args.retry_on_failure_after
+        && let Err(e) = schedule::parse_duration(cooldown)
+    {
+        problems.push(format!("invalid --retry-on-failure-after: {e:#}"));
+    }
+    if let Err(e) = Notifier::validate(&args) {
+        problems.push(format!("notifications: {e:#}"));
+    }
+    if let Err(e) = Influx::from_args(&args) {
+        problems.push(format!("influx: {e:#}"));
+    }
+    if let Err(e) = control::check_args(&args) {
+        problems.push(format!("control: {e:#}"));
+    }
+
+    if problems.is_empty() {
+        println!("configuration OK");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("- {problem}");
+    }
+    Err(anyhow::anyhow!(
+        "{} configuration problem(s) found",
+        problems.len()
+    ))
+    .context(Failure::Config)
+}
+
+/// `--show-config`：输出合并、规范化之后实际生效的配置。
+fn show_config(args: &Args, router_args: Vec<(String, Args)>) -> Result<()> {
+    let routers = router_args
+        .into_iter()
+        .map(|(name, a)| build_router(name, &a).map(|router| config_json(&router.cfg)))
+        .collect::<Result<Vec<_>>>()?;
+    let schedule = match &args.interval {
+        Some(interval) => json!({
+            "interval_secs": schedule::parse_duration(interval)
+                .context("invalid --interval")?
+                .as_secs(),
+        }),
+        None => json!({
+            "cron": args
+                .cron
+                .iter()
+                .filter(|c| !c.trim().is_empty())
+                .map(|c| schedule::normalize_cron(c))
+                .collect::<Vec<_>>(),
+            "timezone": args.timezone.map(|tz| tz.name()),
+        }),
+    };
+    let effective = json!({
+        "routers": routers,
+        "schedule": schedule,
+        "jitter_secs": args.jitter_secs,
+        "run_now": args.run_now,
+        "once": args.once,
+        "max_runs": args.max_runs,
+        "parallel": args.parallel,
+        "state_file": args.state_file,
+        "lock_file": args.lock_file,
+        "notify_on": args.notify_on.iter().map(|k| k.name()).collect::<Vec<_>>(),
+        "metrics_addr": args.metrics_addr,
+        "health_addr": args.health_addr,
+        "control_addr": args.control_addr,
+    });
+    println!("{}", serde_json::to_string_pretty(&effective)?);
+    Ok(())
+}
 
-    run_scheduler(client, cfg, &args.cron, args.run_now)
+fn config_json(cfg: &Config) -> Value {
+    json!({
+        "name": cfg.name,
+        "login_url": cfg.login_url.as_str(),
+        "reboot_url": cfg.reboot_url.as_str(),
+        "reboot_referer": cfg.reboot_referer.as_str(),
+        "logout_url": cfg.logout_url.as_ref().map(Url::as_str),
+        "status_url": cfg.status_url.as_str(),
+        "clients_url": cfg.clients_url.as_str(),
+        "username": cfg.username,
+        "password": format!("{:?}", cfg.password),
+        "password_encoding": cfg.password_encoding,
+        "login_token": cfg.login_token,
+        "auto_token": cfg.auto_token,
+        "preflight": cfg.preflight,
+        "login_method": cfg.login_method,
+        "login_body_format": cfg.login_body_format,
+        "frashnum": cfg.frashnum,
+        "csrf_header_name": cfg.csrf_header.as_ref().map(HeaderName::as_str),
+        "require_csrf": cfg.require_csrf,
+        "auto_frashnum": cfg.auto_frashnum,
+        "command": cfg.command,
+        "payload": command_payload(cfg.command, cfg).ok(),
+        "timestamp": cfg.add_timestamp.then(|| json!({
+            "param": cfg.timestamp_param,
+            "format": cfg.timestamp_format,
+        })),
+        "extra_headers": cfg
+            .extra_headers
+            .keys()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>(),
+        "min_uptime_secs": cfg.min_uptime.map(|d| d.as_secs()),
+        "skip_if_clients_above": cfg.skip_if_clients_above,
+        "max_retries": cfg.retry.max_retries,
+        "verify_reboot": cfg.verify_reboot,
+        "reboot_wait_secs": cfg.reboot_wait.as_secs(),
+        "reboot_settle_secs": cfg.reboot_settle.as_secs(),
+        "dry_run": cfg.dry_run,
+    })
 }
 
-fn init_logger(verbose: bool) {
+fn init_logger(verbose: bool, format: LogFormat, otlp_endpoint: Option<&str>) {
     // 使用 tracing：更现代、支持结构化字段与更强的订阅/过滤能力。
     // 时间戳使用本地时区（容器内通过 TZ/tzdata 生效），避免看到默认的 UTC `Z` 前缀。
     let filter = match EnvFilter::try_from_default_env() {
@@ -114,11 +1312,35 @@ fn init_logger(verbose: bool) {
         }
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_timer(tracing_subscriber::fmt::time::ChronoLocal::rfc_3339())
-        .with_target(false)
-        .init();
+    let fmt_layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_timer(tracing_subscriber::fmt::time::ChronoLocal::rfc_3339())
+            .with_target(false)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .event_format(JsonLines)
+            .boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(fmt_layer);
+
+    #[cfg(feature = "otel")]
+    {
+        let (otel_layer, otel_error) = match otlp_endpoint.map(otel::layer).transpose() {
+            Ok(layer) => (layer, None),
+            Err(e) => (None, Some(e)),
+        };
+        registry.with(otel_layer).init();
+        if let Some(e) = otel_error {
+            warn!("OpenTelemetry export disabled: {e:#}");
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.init();
+        if otlp_endpoint.is_some() {
+            warn!("--otlp-endpoint is ignored: built without the `otel` feature");
+        }
+    }
 }
 
 fn log_time_diagnostics() {
@@ -139,13 +1361,32 @@ fn log_time_diagnostics() {
     );
 }
 
-fn build_client(timeout_secs: u64) -> Result<Client> {
+/// `--http-auth-user`/`--http-auth-pass`：部分贴牌固件在表单登录之外还套了一层 HTTP Basic 认证，
+/// 作为默认请求头发给该路由器的所有请求（登录、重启、状态页等）。
+/// 不支持 HTTP Digest；遇到 Digest 质询时登录会直接报错。
+fn basic_auth(args: &Args) -> Result<Option<HeaderValue>> {
+    let user = match (&args.http_auth_user, &args.http_auth_pass) {
+        (None, None) => return Ok(None),
+        (Some(user), _) => user,
+        (None, Some(_)) => bail!("--http-auth-pass requires --http-auth-user"),
+    };
+    let pass = args
+        .http_auth_pass
+        .as_ref()
+        .map(Secret::expose)
+        .unwrap_or("");
+    let credentials = STANDARD.encode(format!("{user}:{pass}"));
+    let mut value = HeaderValue::from_str(&format!("Basic {credentials}"))
+        .context("invalid --http-auth-user")?;
+    value.set_sensitive(true);
+    Ok(Some(value))
+}
+
+fn build_client(args: &Args, cookie_jar: Option<&CookieJar>) -> Result<Client> {
     let mut default_headers = HeaderMap::new();
     default_headers.insert(
         USER_AGENT,
-        HeaderValue::from_static(
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36",
-        ),
+        HeaderValue::from_str(&args.user_agent).context("invalid --user-agent")?,
     );
     default_headers.insert(
         ACCEPT,
@@ -153,139 +1394,1333 @@ fn build_client(timeout_secs: u64) -> Result<Client> {
     );
     default_headers.insert(
         ACCEPT_LANGUAGE,
-        HeaderValue::from_static("zh-CN,zh;q=0.9,en;q=0.8"),
+        HeaderValue::from_str(&args.accept_language).context("invalid --accept-language")?,
     );
+    if let Some(auth) = basic_auth(args)? {
+        default_headers.insert(AUTHORIZATION, auth);
+    }
     default_headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
     default_headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
     default_headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
 
-    Client::builder()
-        .default_headers(default_headers)
-        .cookie_store(true)
-        .redirect(Policy::limited(4))
-        .timeout(Duration::from_secs(timeout_secs))
+    let builder = Client::builder().default_headers(default_headers);
+    // 配置了 --cookie-file 时使用可落盘的存储，否则仅保存在内存里。
+    let mut builder = match cookie_jar {
+        Some(jar) => builder.cookie_provider(jar.store()),
+        None => builder.cookie_store(true),
+    };
+    if args.insecure_tls {
+        warn!("--insecure-tls is set: router TLS certificates will NOT be verified");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(path) = &args.ca_cert {
+        let pem =
+            fs::read(path).with_context(|| format!("reading CA certificate {}", path.display()))?;
+        let cert = Certificate::from_pem(&pem)
+            .with_context(|| format!("parsing CA certificate {}", path.display()))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if let Some(version) = args.tls_min_version {
+        builder = builder.min_tls_version(version.to_reqwest());
+    }
+    if let Some((sni, addr)) = sni_override(args)? {
+        debug!(sni, %addr, "Connecting by IP with TLS SNI override");
+        builder = builder.resolve(sni, addr);
+    }
+    // 未指定 --proxy 时 reqwest 默认读取 HTTP_PROXY/HTTPS_PROXY/ALL_PROXY 环境变量。
+    if args.no_proxy {
+        builder = builder.no_proxy();
+    } else if let Some(proxy) = &args.proxy {
+        let proxy_url = Url::parse(proxy).context("invalid --proxy URL")?;
+        debug!(
+            scheme = proxy_url.scheme(),
+            host = proxy_url.host_str().unwrap_or_default(),
+            "Using proxy"
+        );
+        builder = builder.proxy(Proxy::all(proxy_url).context("invalid --proxy")?);
+    }
+    // 一周不用的空闲连接往往已被路由器断开，默认 90s 后从连接池丢弃，避免每周首次登录失败。
+    builder = builder.pool_idle_timeout(
+        (args.pool_idle_timeout_secs > 0).then(|| Duration::from_secs(args.pool_idle_timeout_secs)),
+    );
+    if let Some(max) = args.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(secs) = args.tcp_keepalive_secs {
+        builder = builder.tcp_keepalive(Duration::from_secs(secs));
+    }
+    // 路由器断电时 TCP 连接会一直挂到整体超时；单独限制建连时间，校验轮询能更快进入下一轮。
+    if let Some(secs) = args.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    builder
+        .redirect(redirect_policy(args.max_redirects))
+        .timeout(Duration::from_secs(args.timeout_secs))
         .build()
         .context("building HTTP client")
 }
 
-fn login(client: &Client, cfg: &Config) -> Result<()> {
-    let mut form: HashMap<String, String> = HashMap::new();
-    form.insert("frashnum".into(), cfg.frashnum.clone());
-    form.insert("action".into(), "login".into());
-    form.insert("Frm_Logintoken".into(), cfg.login_token.clone());
-    form.insert("user_name".into(), cfg.username.clone());
-    form.insert("Password".into(), cfg.password.clone());
+/// `--tls-sni`：URL 里改用该主机名（SNI、证书校验、Host 头都随之变化），再把它解析到 --host 的 IP。
+fn sni_override(args: &Args) -> Result<Option<(&str, SocketAddr)>> {
+    let Some(sni) = args.tls_sni.as_deref() else {
+        return Ok(None);
+    };
+    let base = parse_host(&args.host)?;
+    let ip: IpAddr = match base.host() {
+        Some(url::Host::Ipv4(ip)) => ip.into(),
+        Some(url::Host::Ipv6(ip)) => ip.into(),
+        _ => bail!(
+            "--tls-sni requires --host to be an IP address, got {}",
+            args.host
+        ),
+    };
+    let port = base.port_or_known_default().context("--host has no port")?;
+    Ok(Some((sni, SocketAddr::new(ip, port))))
+}
 
-    let origin = origin_of(&cfg.login_url)?;
+/// 0 表示不跟随重定向（可以据此发现登录失败后被 302 回登录页的情况）；跟随时逐跳记录。
+fn redirect_policy(max_redirects: usize) -> Policy {
+    if max_redirects == 0 {
+        return Policy::none();
+    }
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() > max_redirects {
+            attempt.error(format!("too many redirects (max {max_redirects})"))
+        } else {
+            debug!(
+                url = %attempt.url(),
+                status = %attempt.status(),
+                hop = attempt.previous().len(),
+                "Following redirect"
+            );
+            attempt.follow()
+        }
+    })
+}
+
+/// 只支持 HTTP Basic：401 响应要求 Digest 认证时直接报错，而不是带着 Basic 头反复重试。
+fn reject_digest_challenge(resp: &Response) -> Result<()> {
+    if resp.status() != StatusCode::UNAUTHORIZED {
+        return Ok(());
+    }
+    let digest = resp
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(is_digest_challenge);
+    if digest {
+        bail!(
+            "router requires HTTP Digest authentication, which is not supported (only Basic via --http-auth-user)"
+        );
+    }
+    Ok(())
+}
+
+fn is_digest_challenge(challenge: &str) -> bool {
+    challenge
+        .trim_start()
+        .get(..6)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("digest"))
+}
+
+/// GET 登录页；返回页面内容以及响应是否设置了 cookie（cookie 会留在共享的 cookie 存储里）。
+fn fetch_login_page(client: &Client, cfg: &Config) -> Result<(String, bool)> {
     let resp = client
-        .post(cfg.login_url.clone())
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .header("Origin", origin.as_str())
-        .header("Upgrade-Insecure-Requests", "1")
-        .header(REFERER, cfg.login_url.as_str())
-        .form(&form)
+        .get(cfg.login_url.clone())
+        .header(USER_AGENT, cfg.user_agent())
         .send()
+        .context("login page request failed")?;
+    reject_digest_challenge(&resp)?;
+    let resp = resp
+        .error_for_status()
+        .context("login page returned error status")?;
+    let had_cookie = resp.cookies().next().is_some();
+    let body = resp.text().context("reading login page body")?;
+    Ok((body, had_cookie))
+}
+
+/// 登录；返回路由器是否下发过会话 cookie（登录页或登录响应里的 Set-Cookie），没有时也就无从登出。
+fn login(client: &Client, cfg: &Config) -> Result<bool> {
+    let _span = info_span!("login", status_code = field::Empty).entered();
+    // --preflight 与抓取 token、frashnum 共用同一次登录页请求；dry-run 不访问网络。
+    let page = if (cfg.preflight || cfg.auto_token || cfg.auto_frashnum) && !cfg.dry_run {
+        match fetch_login_page(client, cfg) {
+            Ok(page) => Some(page),
+            Err(e) if cfg.preflight => {
+                warn!("Preflight GET of login page failed: {e:#}");
+                None
+            }
+            Err(e) => {
+                debug!("Loading login page for token failed: {e:#}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if cfg.preflight
+        && let Some((_, had_cookie)) = &page
+    {
+        info!(cookie = had_cookie, "Preflight GET of login page done");
+    }
+
+    let scraped = page
+        .as_ref()
+        .filter(|_| cfg.auto_token)
+        .and_then(|(body, _)| scrape::login_token(body, &cfg.login_fields.token));
+    let login_token = match scraped {
+        Some(token) => {
+            debug!(token = %token, "Scraped login token");
+            token
+        }
+        None => {
+            if cfg.auto_token && !cfg.dry_run {
+                debug!(
+                    field = %cfg.login_fields.token,
+                    "Login token not scraped, using supplied value"
+                );
+            }
+            cfg.login_token.clone()
+        }
+    };
+
+    let fields = &cfg.login_fields;
+    let scraped_frashnum = page
+        .as_ref()
+        .filter(|_| cfg.auto_frashnum)
+        .and_then(|(body, _)| scrape::field_value(body, &fields.frashnum));
+    let frashnum = match scraped_frashnum {
+        Some(value) => {
+            debug!(frashnum = %value, "Scraped frashnum");
+            value
+        }
+        None => {
+            if cfg.auto_frashnum && !cfg.dry_run {
+                debug!(
+                    field = %fields.frashnum,
+                    "frashnum not scraped, using supplied value"
+                );
+            }
+            cfg.frashnum.clone()
+        }
+    };
+
+    let password = cfg
+        .password_encoding
+        .encode(cfg.password.expose(), &login_token);
+    let mut form: HashMap<String, String> = HashMap::new();
+    form.insert(fields.frashnum.clone(), frashnum);
+    form.insert(fields.action.clone(), "login".into());
+    form.insert(fields.token.clone(), login_token);
+    form.insert(fields.username.clone(), cfg.username.clone());
+    form.insert(fields.password.clone(), password);
+
+    let origin = origin_of(&cfg.login_url)?;
+    let content_type = match cfg.login_body_format {
+        LoginBodyFormat::Form => "application/x-www-form-urlencoded",
+        LoginBodyFormat::Json => "application/json",
+    };
+    let req = match cfg.login_method {
+        LoginMethod::Post => client
+            .post(cfg.login_url.clone())
+            .header("Content-Type", content_type)
+            .header("Origin", origin.as_str()),
+        LoginMethod::Get => client.get(cfg.login_url.clone()),
+    }
+    .header(USER_AGENT, cfg.user_agent())
+    .header("Upgrade-Insecure-Requests", "1")
+    .header(REFERER, cfg.login_url.as_str())
+    .headers(cfg.extra_headers.clone());
+    let req = match (cfg.login_method, cfg.login_body_format) {
+        (LoginMethod::Post, LoginBodyFormat::Form) => req.form(&form),
+        (LoginMethod::Post, LoginBodyFormat::Json) => req.json(&form),
+        (LoginMethod::Get, _) => req.query(&form),
+    };
+    if cfg.print_request {
+        dry_run::print_curl("login", &req, &[cfg.login_fields.password.as_str()])?;
+    }
+    if cfg.dry_run {
+        dry_run::log_request("login", req, &[cfg.login_fields.password.as_str()])?;
+        return Ok(false);
+    }
+    let started = Instant::now();
+    // GET 登录时密码在 URL 里，错误信息中不能带 URL。
+    let secret_fields = [cfg.login_fields.password.as_str()];
+    let resp = http_log::send("login", req, cfg.trace_http, &secret_fields)
+        .map_err(reqwest::Error::without_url)
         .context("login request failed")?;
 
     let status = resp.status();
-    debug!("login status={}", status);
+    Span::current().record("status_code", status.as_u16());
+    reject_digest_challenge(&resp)?;
+    let had_cookie = resp.cookies().next().is_some();
+    // 响应体只读一次：既用于日志和判断登录结果，也让连接可以被复用。
+    let body = resp.text().context("reading login response body")?;
+    http_log::log_response(
+        "login",
+        status,
+        started.elapsed(),
+        &body,
+        cfg.max_log_body,
+        &[cfg.password.expose()],
+    );
 
     if !status.is_success() {
         bail!("login failed with status {}", status);
     }
 
-    let had_cookie = resp.cookies().next().is_some();
     if !had_cookie {
         warn!("No cookies received from login; device may still accept commands without cookie.");
     } else {
         debug!("Login cookies captured.");
     }
 
-    Ok(())
-}
-
-fn reboot(client: &Client, cfg: &Config) -> Result<()> {
-    let origin = origin_of(&cfg.reboot_url)?;
-    let mut url = cfg.reboot_url.clone();
-    if cfg.add_timestamp {
-        let ts = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        url.query_pairs_mut()
-            .append_pair("timeStamp", &ts.to_string());
+    check_login_body(&body, cfg)?;
+    if let Some(url) = &cfg.validate_url {
+        validate_session(client, cfg, url)?;
     }
 
-    let payload = json!({
-        "RPCMethod": "Post",
-        "Parameter": {
-            "CmdType": "HG_COMMAND_REBOOT",
-        }
-    })
-    .to_string();
+    if let Some(jar) = &cfg.cookie_jar
+        && let Err(e) = jar.save()
+    {
+        warn!("Failed to persist cookies: {e:#}");
+    }
+    Ok(had_cookie || page.is_some_and(|(_, had_cookie)| had_cookie))
+}
 
+/// `--validate-path`：登录后访问一个需要会话的页面；被重定向回登录页或拿到登录表单说明登录没有生效。
+fn validate_session(client: &Client, cfg: &Config, url: &Url) -> Result<()> {
     let resp = client
-        .post(url)
-        .header(
-            "Content-Type",
-            "application/x-www-form-urlencoded; charset=UTF-8",
-        )
-        .header("X-Requested-With", "XMLHttpRequest")
-        .header(ACCEPT, "application/json, text/javascript, */*; q=0.01")
-        .header("Origin", origin.as_str())
-        .header(REFERER, cfg.reboot_referer.as_str())
-        .form(&[("jsonCfg", payload)])
+        .get(url.clone())
+        .header(USER_AGENT, cfg.user_agent())
+        .header(REFERER, cfg.login_url.as_str())
         .send()
-        .context("reboot request failed")?;
-
+        .context("session validation request failed")?;
     let status = resp.status();
-    debug!("reboot status={}", status);
+    let landed_on_login =
+        resp.url().path() == cfg.login_url.path() && url.path() != cfg.login_url.path();
+    let body = resp
+        .text()
+        .context("reading session validation response body")?;
+    debug!(%status, url = %url, "Session validation response");
+    if status.is_redirection() || landed_on_login {
+        bail!("login rejected: {url} redirected to the login page");
+    }
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        bail!("login rejected: {url} returned {status}");
+    }
     if !status.is_success() {
-        bail!("reboot request returned {}", status);
+        bail!("session validation request to {url} returned {status}");
     }
-
+    if scrape::is_login_form(&body) {
+        bail!("login rejected: {url} still shows the login form");
+    }
+    debug!("Session validated");
     Ok(())
 }
 
-fn run_scheduler(client: Client, cfg: Config, cron_expr: &str, run_now: bool) -> Result<()> {
-    // 如果 cron 表达式为空或解析失败，则使用默认值
-    let schedule = Schedule::from_str(cron_expr)
-        .or_else(|_| Schedule::from_str(DEFAULT_CRON))
-        .context("invalid cron expression and failed to use default")?;
-
-    if run_now {
-        info!("Running immediately due to --run-now");
-        if let Err(e) = run_once(&client, &cfg) {
-            error!("Immediate run failed: {e:?}");
+/// 很多固件凭据错误时仍返回 200，只能通过页面内容判断是否登录成功。
+fn check_login_body(body: &str, cfg: &Config) -> Result<()> {
+    if let Some(marker) = &cfg.login_success_marker {
+        if body.contains(marker.as_str()) {
+            return Ok(());
         }
+        bail!("login rejected: success marker {marker:?} not found in response");
     }
 
-    loop {
-        let now = Local::now();
-        let next = schedule
-            .after(&now)
-            .next()
-            .context("cron produced no future times")?;
-        let wait_delta = next - now;
+    let failure = LOGIN_FAILURE_MARKERS
+        .iter()
+        .copied()
+        .chain(cfg.login_failure_markers.iter().map(String::as_str))
+        .find(|m| body.contains(*m));
+    if let Some(marker) = failure {
+        debug!(marker, "Login failure marker found in response");
+        bail!("login rejected: credentials invalid");
+    }
+    if scrape::is_login_form(body) {
+        debug!("Login response is still the login form");
+        bail!("login rejected: credentials invalid");
+    }
+
+    Ok(())
+}
+
+/// 向重启接口 POST 一条命令（默认放在 `jsonCfg` 表单字段里，见 --reboot-body-mode）；重启之外的命令走同一个接口，只是 CmdType 不同。
+fn send_command(client: &Client, cfg: &Config, command: RouterCommand) -> Result<()> {
+    let _span = info_span!(
+        "reboot",
+        command = command.name(),
+        status_code = field::Empty
+    )
+    .entered();
+    let origin = origin_of(&cfg.reboot_url)?;
+    let mut url = cfg.reboot_url.clone();
+    if cfg.add_timestamp {
+        url.query_pairs_mut()
+            .append_pair(&cfg.timestamp_param, &cfg.timestamp_format.now());
+    }
+
+    let payload = command_payload(command, cfg)?;
+    let csrf = match &cfg.csrf_header {
+        Some(name) if !cfg.dry_run => csrf_token(client, cfg)?.map(|token| (name.clone(), token)),
+        _ => None,
+    };
+
+    let content_type = match cfg.reboot_field {
+        Some(_) => "application/x-www-form-urlencoded; charset=UTF-8",
+        None => "application/json",
+    };
+    let req = client
+        .post(url)
+        .header(USER_AGENT, cfg.user_agent())
+        .header("Content-Type", content_type)
+        .header("X-Requested-With", "XMLHttpRequest")
+        .header(ACCEPT, "application/json, text/javascript, */*; q=0.01")
+        .header("Origin", origin.as_str())
+        .header(REFERER, cfg.reboot_referer.as_str())
+        .headers(cfg.extra_headers.clone());
+    let mut req = match &cfg.reboot_field {
+        Some(field) => req.form(&[(field.as_str(), payload)]),
+        None => req.body(payload),
+    };
+    if let Some((name, token)) = &csrf {
+        req = req.header(name, token);
+    }
+    if cfg.print_request {
+        dry_run::print_curl(command.name(), &req, &[])?;
+    }
+    let confirm_url = cfg
+        .reboot_confirm_url
+        .as_ref()
+        .filter(|_| command == RouterCommand::Reboot);
+    if cfg.dry_run {
+        if confirm_url.is_some() {
+            info!("[dry-run] confirmation request depends on the reboot response; not shown");
+        }
+        return dry_run::log_request(command.name(), req, &[]);
+    }
+    let body = send_reboot_request(command.name(), req, cfg)?;
+    check_reboot_body(&body, cfg)?;
+
+    if let Some(confirm_url) = confirm_url {
+        confirm_reboot(client, cfg, confirm_url, &body, csrf.as_ref())?;
+    }
+    Ok(())
+}
+
+/// 登录后打开 --reboot-referer 页面抓取 CSRF token；找不到时除非 --require-csrf，否则不带它继续。
+fn csrf_token(client: &Client, cfg: &Config) -> Result<Option<String>> {
+    let page = client
+        .get(cfg.reboot_referer.clone())
+        .header(USER_AGENT, cfg.user_agent())
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .and_then(|resp| resp.text());
+    let token = match page {
+        Ok(body) => scrape::csrf_token(&body),
+        Err(e) if cfg.require_csrf => {
+            return Err(e).context("loading page for CSRF token failed");
+        }
+        Err(e) => {
+            warn!("Loading page for CSRF token failed: {e}");
+            None
+        }
+    };
+    match token {
+        Some(token) => {
+            debug!("Scraped CSRF token");
+            Ok(Some(token))
+        }
+        None if cfg.require_csrf => bail!(
+            "CSRF token not found on {} (--require-csrf)",
+            cfg.reboot_referer
+        ),
+        None => {
+            warn!("CSRF token not found, sending the reboot request without it");
+            Ok(None)
+        }
+    }
+}
+
+/// 两步重启的第二步：从第一次的响应里取出确认 token，再 POST 到 --reboot-confirm-path。
+fn confirm_reboot(
+    client: &Client,
+    cfg: &Config,
+    url: &Url,
+    first_body: &str,
+    csrf: Option<&(HeaderName, String)>,
+) -> Result<()> {
+    let field = &cfg.reboot_confirm_field;
+    let from_json = serde_json::from_str::<serde_json::Value>(first_body)
+        .ok()
+        .and_then(|v| match status::find_key(&v, field) {
+            Some(serde_json::Value::String(s)) => Some(s.clone()),
+            Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        });
+    let token = from_json
+        .or_else(|| scrape::field_value(first_body, field))
+        .with_context(|| format!("confirmation token {field:?} not found in reboot response"))?;
+    debug!(field = %field, "Found reboot confirmation token");
+
+    let origin = origin_of(url)?;
+    let mut req = client
+        .post(url.clone())
+        .header(USER_AGENT, cfg.user_agent())
+        .header("X-Requested-With", "XMLHttpRequest")
+        .header(ACCEPT, "application/json, text/javascript, */*; q=0.01")
+        .header("Origin", origin.as_str())
+        .header(REFERER, cfg.reboot_referer.as_str())
+        .headers(cfg.extra_headers.clone())
+        .form(&[(field.as_str(), token.as_str())]);
+    if let Some((name, value)) = csrf {
+        req = req.header(name, value);
+    }
+    let body = send_reboot_request("reboot confirm", req, cfg)?;
+    check_reboot_body(&body, cfg)
+}
+
+/// 发送重启类请求并返回响应体；401/403 或又回到登录页视为会话失效。
+fn send_reboot_request(what: &str, req: RequestBuilder, cfg: &Config) -> Result<String> {
+    let started = Instant::now();
+    let resp = http_log::send(what, req, cfg.trace_http, &[])
+        .with_context(|| format!("{what} request failed"))?;
+
+    let status = resp.status();
+    Span::current().record("status_code", status.as_u16());
+    let body = resp
+        .text()
+        .with_context(|| format!("reading {what} response body"))?;
+    http_log::log_response(
+        what,
+        status,
+        started.elapsed(),
+        &body,
+        cfg.max_log_body,
+        &[cfg.password.expose()],
+    );
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(SessionExpired.into());
+    }
+    if !status.is_success() {
+        bail!("{what} request returned {status}");
+    }
+
+    if scrape::is_login_form(&body) {
+        return Err(SessionExpired.into());
+    }
+    Ok(body)
+}
+
+/// HTTP 200 不代表命令被接受：JSON 响应里的结果码（默认 `IF_ERRORID`）必须等于期望值。
+/// 响应不是 JSON 或没有该字段时无法判断，按成功处理。
+fn check_reboot_body(body: &str, cfg: &Config) -> Result<()> {
+    if cfg.reboot_success_field.is_empty() {
+        return Ok(());
+    }
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        debug!("Reboot response is not JSON; not checking result code");
+        return Ok(());
+    };
+    let Some(code) = status::find_key(&value, &cfg.reboot_success_field) else {
+        debug!(
+            field = %cfg.reboot_success_field,
+            "Result code field not found in reboot response"
+        );
+        return Ok(());
+    };
+    let code = match code {
+        serde_json::Value::String(s) => s.trim().to_string(),
+        other => other.to_string(),
+    };
+    if code != cfg.reboot_success_value {
+        let detail = status::find_key(&value, "IF_ERRORSTR")
+            .and_then(serde_json::Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(|s| format!(" ({s})"))
+            .unwrap_or_default();
+        bail!(
+            "router rejected reboot: {}={code}{detail}",
+            cfg.reboot_success_field
+        );
+    }
+    debug!(%code, "Reboot accepted by router");
+    Ok(())
+}
+
+/// 释放管理员会话：部分固件同一时间只允许一个管理员登录。
+fn logout(client: &Client, cfg: &Config) -> Result<()> {
+    let Some(logout_url) = &cfg.logout_url else {
+        return Ok(());
+    };
+    let origin = origin_of(logout_url)?;
+    client
+        .post(logout_url.clone())
+        .header(USER_AGENT, cfg.user_agent())
+        .header("Origin", origin.as_str())
+        .header(REFERER, cfg.reboot_referer.as_str())
+        .headers(cfg.extra_headers.clone())
+        .form(&[(cfg.login_fields.action.as_str(), "logout")])
+        .send()
+        .context("logout request failed")?
+        .error_for_status()
+        .context("logout returned error status")?;
+    Ok(())
+}
+
+/// 路由器拒绝了当前会话（401/403 或又返回了登录页）。
+#[derive(Debug)]
+struct SessionExpired;
+
+impl fmt::Display for SessionExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("session expired or rejected by router")
+    }
+}
+
+impl std::error::Error for SessionExpired {}
+
+fn is_session_expired(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<SessionExpired>().is_some()
+}
+
+fn run_scheduler(runner: &Runner, args: &Args) -> Result<()> {
+    let timetable = timetable(args).context(Failure::Config)?;
+    let waiter = SignalWaiter::install()?;
+    schedule_loop(runner, args, timetable, &waiter)
+}
+
+/// 调度循环本体；等待器由调用方提供（测试里用不接信号的等待器）。
+fn schedule_loop(
+    runner: &Runner,
+    args: &Args,
+    timetable: Timetable,
+    waiter: &SignalWaiter,
+) -> Result<()> {
+    runner.health.set_alive(true);
+
+    let previous = match &args.state_file {
+        Some(path) => match state::load(path) {
+            Ok(Some(prev)) => {
+                info!(
+                    last_run = %prev.last_run,
+                    last_result = ?prev.last_result,
+                    last_error = prev.last_error.as_deref().unwrap_or(""),
+                    next_run = ?prev.next_run,
+                    "Previous run state"
+                );
+                Some(prev)
+            }
+            Ok(None) => {
+                debug!(path = %path.display(), "No previous run state");
+                None
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable state file: {e:#}");
+                None
+            }
+        },
+        None => None,
+    };
+    let last_state = Arc::new(Mutex::new(previous.clone()));
+    if let (Some(addr), Some(token)) = (&args.control_addr, &args.control_token) {
+        let control = Control::new(token.clone(), waiter.trigger(), Arc::clone(&last_state));
+        http_server::spawn("control", addr, move |req| control.handle(req));
+    }
+    let cooldown = args
+        .retry_on_failure_after
+        .as_deref()
+        .map(schedule::parse_duration)
+        .transpose()
+        .context("invalid --retry-on-failure-after")
+        .context(Failure::Config)?;
+    // 返回本次运行是否成功；--on-failure exit 时把失败的运行作为错误返回，结束调度循环。
+    let run = |label: &str| -> Result<bool> {
+        let started = Local::now();
+        let result = runner.run_all();
+        let succeeded = result.is_ok();
+        if let Err(e) = &result {
+            error!("{label} run failed: {e:?}");
+        }
+        let next = timetable.next_after(&Local::now()).map(|(_, t)| t);
+        let state = state::RunState::new(started, &result, next, runner.last_reboots());
+        if let Some(path) = &args.state_file
+            && let Err(e) = state::save(path, &state)
+        {
+            warn!("Failed to write state file: {e:#}");
+        }
+        *last_state.lock().expect("state lock poisoned") = Some(state);
+        match result {
+            Err(e) if args.on_failure == OnFailure::Exit => {
+                runner.health.set_alive(false);
+                error!("exiting due to --on-failure exit");
+                Err(e)
+            }
+            _ => Ok(succeeded),
+        }
+    };
+
+    // SIGHUP 或控制接口触发的手动运行不计入 --max-runs。
+    let mut runs = 0;
+    if args.run_now {
+        info!("Running immediately due to --run-now");
+        run("Immediate")?;
+        if args.count_run_now {
+            runs += 1;
+        }
+    } else if args.catch_up {
+        // 停机期间错过了多个窗口也只补跑一次。
+        match &previous {
+            Some(prev) => {
+                if let Some((index, missed)) =
+                    timetable.missed_between(&prev.last_run, &Local::now())
+                {
+                    info!(
+                        schedule = timetable.describe(index),
+                        missed_at = %missed,
+                        last_run = %prev.last_run,
+                        "Missed scheduled run since last run, catching up"
+                    );
+                    run("Catch-up")?;
+                    runs += 1;
+                }
+            }
+            None => warn!("--catch-up has no previous run to compare against (needs --state-file)"),
+        }
+    }
+
+    // 冷却重试与手动运行一样不计入 --max-runs。
+    let mut cooldown_retries = 0;
+    let mut retry_at = None;
+    loop {
+        if args.max_runs.is_some_and(|max| runs >= max) {
+            runner.health.set_alive(false);
+            info!(runs, "max runs reached, exiting");
+            return Ok(());
+        }
+        let now = Local::now();
+        let Some((index, base)) = timetable.next_after(&now) else {
+            // 启动时已拒绝没有未来触发时间的表达式；万一仍然发生，保持进程存活并定期重算。
+            warn!(
+                retry_secs = NO_FUTURE_RUN_RETRY.as_secs(),
+                "Schedule produced no future run times, checking again later"
+            );
+            match waiter.wait(NO_FUTURE_RUN_RETRY) {
+                Wakeup::Shutdown => {
+                    runner.health.set_alive(false);
+                    info!("shutting down");
+                    return Ok(());
+                }
+                Wakeup::Trigger => {
+                    info!("Manual run triggered");
+                    run("Manual")?;
+                }
+                Wakeup::Elapsed => {}
+            }
+            continue;
+        };
+        let next = if args.jitter_secs > 0 {
+            let next = schedule::jittered(
+                base,
+                timetable.next_after(&base).map(|(_, t)| t),
+                args.jitter_secs,
+            );
+            info!(cron_time = %base, effective_time = %next, "Applied jitter");
+            next
+        } else {
+            base
+        };
+        let (next, is_retry) = match retry_at {
+            Some(at) if at < next => (at, true),
+            _ => (next, false),
+        };
+        let wait_delta = next - now;
         let wait = to_std(wait_delta);
         info!(
-            "Next run at {} (in {:.1} minutes)",
-            next,
+            "Next {} at {} (in {:.1} minutes)",
+            if is_retry { "cooldown retry" } else { "run" },
+            timetable.display_time(&next),
             wait.as_secs_f64() / 60.0
         );
-        thread::sleep(wait);
-        if let Err(e) = run_once(&client, &cfg) {
-            error!("Scheduled run failed: {e:?}");
+        match waiter.wait(wait) {
+            Wakeup::Shutdown => {
+                runner.health.set_alive(false);
+                info!("shutting down");
+                return Ok(());
+            }
+            Wakeup::Trigger => {
+                info!("Manual run triggered");
+                run("Manual")?;
+                continue;
+            }
+            Wakeup::Elapsed => {}
+        }
+        let succeeded = if is_retry {
+            info!(attempt = cooldown_retries, "Cooldown retry triggered");
+            run("Cooldown retry")?
+        } else {
+            info!(
+                schedule = timetable.describe(index),
+                "Scheduled run triggered"
+            );
+            runs += 1;
+            run("Scheduled")?
+        };
+        retry_at = cooldown.and_then(|cooldown| {
+            next_cooldown_retry(
+                succeeded,
+                cooldown,
+                &mut cooldown_retries,
+                args.max_failure_retries,
+            )
+        });
+    }
+}
+
+/// `--retry-on-failure-after`：失败后安排一次冷却重试；成功或连续重试达到上限后回到正常调度。
+fn next_cooldown_retry(
+    succeeded: bool,
+    cooldown: Duration,
+    retries: &mut u32,
+    max_retries: u32,
+) -> Option<DateTime<Local>> {
+    if succeeded {
+        *retries = 0;
+        return None;
+    }
+    if *retries >= max_retries {
+        warn!(
+            retries = *retries,
+            "Cooldown retries exhausted, waiting for the next scheduled run"
+        );
+        *retries = 0;
+        return None;
+    }
+    *retries += 1;
+    info!(
+        cooldown_secs = cooldown.as_secs(),
+        attempt = *retries,
+        max_retries,
+        "Run failed, scheduling a cooldown retry"
+    );
+    Local::now().checked_add_signed(TimeDelta::from_std(cooldown).ok()?)
+}
+
+/// `--auto-detect`：按登录页识别出的机型套用内置配置；识别失败或未知机型时沿用当前设置。
+fn auto_detect(router_args: &mut [(String, Args)]) {
+    for (name, a) in router_args.iter_mut() {
+        let detected = detection_url(a).and_then(|url| {
+            let client = build_client(a, None)?;
+            profile::detect_model(&client, &url)
+        });
+        match detected {
+            Ok(profile::Detection {
+                model,
+                profile: Some(profile),
+            }) => {
+                info!(
+                    router = %name,
+                    model = model.as_deref().unwrap_or("unknown"),
+                    profile = profile.name,
+                    "Detected router model"
+                );
+                profile.apply(a);
+            }
+            Ok(profile::Detection {
+                model,
+                profile: None,
+            }) => info!(
+                router = %name,
+                model = model.as_deref().unwrap_or("unknown"),
+                "Unrecognized router model, keeping current defaults"
+            ),
+            Err(e) => {
+                warn!(router = %name, "Model detection failed, keeping current defaults: {e:#}")
+            }
+        }
+    }
+}
+
+/// 机型识别要访问的登录页：`--host` 列表里的第一个地址（有 --tls-sni 时换成该主机名）。
+fn detection_url(args: &Args) -> Result<Url> {
+    let host = args
+        .host
+        .split(',')
+        .map(str::trim)
+        .next()
+        .unwrap_or_default();
+    let mut base = parse_host(host)?;
+    if let Some(sni) = &args.tls_sni {
+        base.set_host(Some(sni))
+            .with_context(|| format!("invalid --tls-sni {sni:?}"))?;
+    }
+    build_url(&base, &args.login_path)
+}
+
+/// `--startup-grace`：首次运行前等待每台路由器的 Web 服务就绪；超时只告警，照常继续。
+fn warm_up(routers: &[Router], grace: Duration) {
+    let deadline = Instant::now() + grace;
+    for router in routers {
+        let cfg = &router.cfg;
+        if cfg.dry_run {
+            info!(router = %cfg.name, "[dry-run] skipping startup warmup");
+            continue;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        info!(
+            router = %cfg.name,
+            grace_secs = remaining.as_secs(),
+            "Waiting for router to become reachable"
+        );
+        if verify::wait_until_reachable(&router.client, &cfg.login_url, remaining) {
+            info!(router = %cfg.name, "Router is reachable");
+        } else {
+            warn!(
+                router = %cfg.name,
+                "Router still unreachable after --startup-grace, continuing anyway"
+            );
+        }
+    }
+}
+
+/// `--verify-host`：启动时先确认每台路由器的登录页可以访问，尽早发现 --host 写错。
+fn verify_hosts(routers: &[Router]) -> Result<()> {
+    for router in routers {
+        let cfg = &router.cfg;
+        if cfg.dry_run {
+            info!(router = %cfg.name, "[dry-run] skipping host verification");
+            continue;
+        }
+        let status = router
+            .client
+            .get(cfg.login_url.clone())
+            .send()
+            .with_context(|| format!("router {} is unreachable at {}", cfg.name, cfg.login_url))?
+            .status();
+        if !status.is_success() {
+            bail!(
+                "router {} returned {status} for {}; check --host and --login-path",
+                cfg.name,
+                cfg.login_url
+            );
+        }
+        info!(router = %cfg.name, %status, "Router is reachable");
+    }
+    Ok(())
+}
+
+fn test_login(routers: &[Router]) -> Result<()> {
+    let mut failed = 0;
+    for router in routers {
+        let cfg = &router.cfg;
+        let session = match retry_with_backoff("login", &cfg.retry, || login(&router.client, cfg)) {
+            Ok(session) => {
+                println!("{}: login OK", cfg.name);
+                session
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{}: login FAILED: {e:#}", cfg.name);
+                false
+            }
+        };
+        if session
+            && cfg.logout_url.is_some()
+            && !cfg.dry_run
+            && let Err(e) = logout(&router.client, cfg)
+        {
+            warn!("Logout failed: {e:#}");
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "login test failed for {failed} of {} router(s)",
+            routers.len()
+        );
+    }
+    Ok(())
+}
+
+/// `dump-cookies`：登录后列出 cookie 存储里的全部 cookie；没有 --cookie-file 时使用内存存储。
+fn dump_cookies(router_args: Vec<(String, Args)>, show_values: bool) -> Result<()> {
+    for (name, a) in router_args {
+        let mut router = build_router(name, &a).context(Failure::Config)?;
+        if router.cfg.cookie_jar.is_none() {
+            router.cfg.cookie_jar = Some(CookieJar::in_memory());
+            router.client =
+                build_client(&a, router.cfg.cookie_jar.as_ref()).context(Failure::Config)?;
+        }
+        let cfg = &router.cfg;
+        let session = retry_with_backoff("login", &cfg.retry, || login(&router.client, cfg))
+            .with_context(|| format!("router {}", cfg.name))
+            .context(Failure::Login)?;
+        let lines = cfg
+            .cookie_jar
+            .as_ref()
+            .map(|jar| jar.dump(show_values))
+            .unwrap_or_default();
+        println!("{}: {} cookie(s)", cfg.name, lines.len());
+        for line in lines {
+            println!("  {line}");
+        }
+        if session
+            && cfg.logout_url.is_some()
+            && !cfg.dry_run
+            && let Err(e) = logout(&router.client, cfg)
+        {
+            warn!("Logout failed: {e:#}");
+        }
+    }
+    Ok(())
+}
+
+/// 给出 --interval 时按固定间隔运行，否则按 --cron（未指定时使用默认 cron）。
+fn timetable(args: &Args) -> Result<Timetable> {
+    timetable_from(args, Local::now())
+}
+
+/// `--interval` 从 `start` 起算；`simulate` 用虚拟起点代替进程启动时间。
+fn timetable_from(args: &Args, start: DateTime<Local>) -> Result<Timetable> {
+    let Some(interval) = &args.interval else {
+        // cron 表达式全为空时使用默认值；5 段表达式会先补齐秒字段
+        return Ok(Timetable::Cron(CronSet::parse(
+            &args.cron,
+            DEFAULT_CRON,
+            args.timezone,
+        )?));
+    };
+    let every = schedule::parse_duration(interval).context("invalid --interval")?;
+    Ok(Timetable::Interval {
+        start,
+        every: TimeDelta::from_std(every).context("--interval is too large")?,
+        label: format!("every {}", interval.trim()),
+    })
+}
+
+fn print_next_runs(args: &Args, count: usize) -> Result<()> {
+    let timetable = timetable(args).context(Failure::Config)?;
+    let now = Local::now();
+    for (i, next) in timetable.upcoming(&now, count) {
+        let shown = timetable.display_time(&next);
+        if timetable.len() > 1 {
+            println!(
+                "{shown}  (in {})  [cron #{} `{}`]",
+                schedule::format_delta(next - now),
+                i + 1,
+                timetable.describe(i)
+            );
+        } else {
+            println!("{shown}  (in {})", schedule::format_delta(next - now));
+        }
+    }
+    Ok(())
+}
+
+/// `simulate`：用虚拟时钟走一遍调度循环的决策（立即运行、补跑、--max-runs），只打印不执行。
+fn simulate(args: &Args, from: DateTime<Local>, until: DateTime<Local>) -> Result<()> {
+    if until < from {
+        return Err(anyhow::anyhow!("--until must not be earlier than --from"))
+            .context(Failure::Config);
+    }
+    let timetable = timetable_from(args, from).context(Failure::Config)?;
+    let print = |t: &DateTime<Local>, label: &str, index: Option<usize>| match index {
+        Some(i) if timetable.len() > 1 => println!(
+            "{}  {label}  [cron #{} `{}`]",
+            timetable.display_time(t),
+            i + 1,
+            timetable.describe(i)
+        ),
+        _ => println!("{}  {label}", timetable.display_time(t)),
+    };
+
+    let mut runs = 0;
+    if args.run_now {
+        print(&from, "immediate (--run-now)", None);
+        if args.count_run_now {
+            runs += 1;
+        }
+    } else if args.catch_up {
+        let previous = match &args.state_file {
+            Some(path) => state::load(path)?,
+            None => None,
+        };
+        match previous {
+            Some(prev) => {
+                if let Some((index, missed)) = timetable.missed_between(&prev.last_run, &from) {
+                    print(&from, &format!("catch-up (missed {missed})"), Some(index));
+                    runs += 1;
+                }
+            }
+            None => println!("(--catch-up has no previous run state to compare against)"),
+        }
+    }
+
+    let mut cursor = from;
+    loop {
+        if args.max_runs.is_some_and(|max| runs >= max) {
+            println!("(max runs reached after {runs} run(s), scheduler would exit)");
+            return Ok(());
+        }
+        let Some((index, next)) = timetable.next_after(&cursor) else {
+            println!("(schedule produced no further times)");
+            return Ok(());
+        };
+        if next > until {
+            println!("({runs} run(s) counted toward --max-runs in this window)");
+            return Ok(());
+        }
+        print(&next, "scheduled", Some(index));
+        runs += 1;
+        cursor = next;
+    }
+}
+
+/// 一次运行（单台路由器）的结果。按 --min-uptime-hours、--skip-if-clients-above 或
+/// --min-reboot-interval 跳过的运行既不算成功也不算失败：不计入成功指标、不标记就绪。
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    /// 完成了本次要做的事（重启、其他命令，或 --no-reboot 时仅登录）
+    Done,
+    /// 按条件跳过，附带原因
+    Skipped(String),
+}
+
+/// 一次运行中的会话状态，用于决定结束时是否需要登出。
+#[derive(Debug, Default)]
+struct Session {
+    /// 持有会话 cookie：复用了落盘的会话，或登录时路由器下发了 cookie
+    active: bool,
+    /// --verify-reboot 已确认设备掉线（会话自然失效，无需登出）
+    went_down: bool,
+    /// 本次运行的重试策略（--retry-deadline 从运行开始计时）
+    retry: RetryPolicy,
+}
+
+/// 仅用于 --min-reboot-interval：从 --state-file 里取出这台路由器上次实际下发重启的时间。
+fn previous_reboot(args: &Args, name: &str) -> Option<DateTime<Local>> {
+    args.min_reboot_interval.as_ref()?;
+    let prev = state::load(args.state_file.as_deref()?).ok()??;
+    prev.last_reboots.get(name).copied()
+}
+
+/// 有备用地址时依次尝试，直到某个地址登录成功；之后的重启和验证都在这个地址上完成，
+/// 重启或验证阶段的失败不会再换地址重试。
+fn run_with_fallback(client: &Client, primary: &Config, fallbacks: &[Config]) -> Result<Outcome> {
+    if fallbacks.is_empty() {
+        return run_once(client, primary);
+    }
+    let mut last_error = None;
+    for cfg in std::iter::once(primary).chain(fallbacks) {
+        let host = cfg.login_url.host_str().unwrap_or_default();
+        match run_once(client, cfg) {
+            Err(e) if !reached_reboot(&e) => {
+                warn!(
+                    host,
+                    "Host failed before logging in, trying the next one: {e:#}"
+                );
+                last_error = Some(e);
+            }
+            result => {
+                info!(host, "Used host");
+                return result;
+            }
+        }
+    }
+    Err(last_error
+        .expect("at least one host was tried")
+        .context("all hosts failed"))
+}
+
+/// 错误发生在登录之后（重启或验证阶段）。
+fn reached_reboot(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<Failure>(),
+        Some(Failure::Reboot | Failure::VerifyTimeout)
+    )
+}
+
+fn run_once(client: &Client, cfg: &Config) -> Result<Outcome> {
+    cfg.rotate_user_agent();
+    if let Some(min_interval) = cfg.min_reboot_interval
+        && let Some(last) = *cfg.last_reboot.lock().expect("last reboot lock poisoned")
+        && Local::now() - last < TimeDelta::from_std(min_interval).unwrap_or(TimeDelta::MAX)
+    {
+        warn!(
+            last_reboot = %last,
+            min_interval_secs = min_interval.as_secs(),
+            "skipping, last reboot was too recent (--min-reboot-interval)"
+        );
+        return Ok(Outcome::Skipped(
+            "last reboot was too recent (--min-reboot-interval)".into(),
+        ));
+    }
+
+    let mut session = Session {
+        retry: cfg.retry.starting_now(),
+        ..Session::default()
+    };
+    // 设备始终没有掉线说明重启没有生效（"came back" 才算成功），重新登录并再次下发。
+    let mut attempt = 0;
+    let result = loop {
+        let result = run_cycle(client, cfg, &mut session, attempt > 0);
+        match &result {
+            Err(e) if verify::is_never_went_down(e) && attempt < cfg.reboot_verify_retries => {
+                attempt += 1;
+                warn!(
+                    attempt,
+                    max = cfg.reboot_verify_retries,
+                    "Reboot was not applied; retrying login and reboot: {e:#}"
+                );
+            }
+            _ => break result,
+        }
+    };
+
+    if cfg.logout_url.is_some() && session.active && !cfg.dry_run {
+        if session.went_down {
+            debug!("Router went down during reboot; skipping logout");
+        } else if let Err(e) = logout(client, cfg) {
+            warn!("Logout failed: {e:#}");
+        } else {
+            info!("Logged out.");
         }
     }
+    result
 }
 
-fn run_once(client: &Client, cfg: &Config) -> Result<()> {
-    login(client, cfg)?;
+fn run_cycle(
+    client: &Client,
+    cfg: &Config,
+    session: &mut Session,
+    fresh_login: bool,
+) -> Result<Outcome> {
+    // 有落盘的会话时先直接尝试；会话被拒绝再走正常登录流程。
+    if !fresh_login && !cfg.dry_run && cfg.cookie_jar.as_ref().is_some_and(CookieJar::has_cookies) {
+        info!("Trying stored session cookies before logging in");
+        session.active = true;
+        match run_logged_in(client, cfg, session) {
+            Err(e) if is_session_expired(&e) => {
+                info!("Stored session rejected, logging in again");
+            }
+            other => return other,
+        }
+    }
+
+    // 只有拿到会话 cookie 才需要登出；被拒绝的落盘会话或没有 cookie 的登录都不算。
+    session.active = false;
+    session.active = retry_with_backoff("login", &session.retry, || login(client, cfg))
+        .context(Failure::Login)?;
     info!("Login request sent.");
-    reboot(client, cfg)?;
+    // 部分固件登录后会话要过一会儿才生效，立即重启会得到 403。
+    if !cfg.post_login_delay.is_zero() && !cfg.dry_run {
+        info!(
+            delay_ms = cfg.post_login_delay.as_millis() as u64,
+            "Waiting after login before continuing"
+        );
+        thread::sleep(cfg.post_login_delay);
+    }
+    run_logged_in(client, cfg, session)
+}
+
+/// 登录之后的步骤：检查运行时长、下发重启、（可选）验证恢复。
+fn run_logged_in(client: &Client, cfg: &Config, session: &mut Session) -> Result<Outcome> {
+    // --no-reboot：只保持会话；给了 --reboot-payload 时改为发送这条自定义命令（不做重启相关的检查和验证）。
+    if cfg.no_reboot {
+        if cfg.custom_payload.is_some() {
+            retry_with_backoff("command", &session.retry, || {
+                send_command(client, cfg, RouterCommand::Custom)
+            })
+            .context(Failure::Reboot)?;
+            info!("Custom command sent (--no-reboot).");
+        } else {
+            info!("Logged in; skipping reboot due to --no-reboot");
+        }
+        return Ok(Outcome::Done);
+    }
+
+    if let Some(min_uptime) = cfg.min_uptime
+        && !cfg.dry_run
+    {
+        match status::fetch_uptime(client, cfg) {
+            Ok(uptime) if status::should_skip_reboot(uptime, min_uptime) => {
+                info!(
+                    uptime_hours = uptime.as_secs() / 3600,
+                    min_uptime_hours = min_uptime.as_secs() / 3600,
+                    "skipping, uptime below threshold"
+                );
+                return Ok(Outcome::Skipped(format!(
+                    "uptime {}h below --min-uptime-hours {}",
+                    uptime.as_secs() / 3600,
+                    min_uptime.as_secs() / 3600
+                )));
+            }
+            Ok(uptime) => debug!(uptime_secs = uptime.as_secs(), "Router uptime"),
+            Err(e) if is_session_expired(&e) => return Err(e),
+            Err(e) => warn!("Could not determine uptime, rebooting anyway: {e:#}"),
+        }
+    }
+
+    if let Some(max_clients) = cfg.skip_if_clients_above
+        && !cfg.dry_run
+    {
+        match status::fetch_active_clients(client, cfg) {
+            Ok(count) if count > max_clients => {
+                info!(
+                    clients = count,
+                    max_clients, "skipping, active clients above threshold; deferring to next run"
+                );
+                return Ok(Outcome::Skipped(format!(
+                    "{count} active clients above --skip-if-clients-above {max_clients}"
+                )));
+            }
+            Ok(count) => info!(clients = count, max_clients, "Active clients within limit"),
+            Err(e) if is_session_expired(&e) => return Err(e),
+            Err(e) if cfg.strict_client_check => {
+                return Err(e.context("client check failed (--strict-client-check)"));
+            }
+            Err(e) => warn!("Could not count active clients, rebooting anyway: {e:#}"),
+        }
+    }
+
+    // 登录到重启之间会话可能已过期（例如等待较久）：重新登录一次再重试，最多一次，避免循环。
+    let command = cfg.command;
+    let send = || send_command(client, cfg, command);
+    let result = match retry_with_backoff(command.name(), &session.retry, send) {
+        Err(e) if is_session_expired(&e) && !cfg.dry_run => {
+            info!(
+                "Session expired before {}, logging in again",
+                command.name()
+            );
+            session.active = retry_with_backoff("login", &session.retry, || login(client, cfg))
+                .context(Failure::Login)?;
+            retry_with_backoff(command.name(), &session.retry, send)
+        }
+        other => other,
+    };
+    result.context(Failure::Reboot)?;
+    if command != RouterCommand::Reboot {
+        info!(command = command.name(), "Command dispatched.");
+        return Ok(Outcome::Done);
+    }
     info!("Reboot command dispatched.");
-    Ok(())
+    if !cfg.dry_run {
+        *cfg.last_reboot.lock().expect("last reboot lock poisoned") = Some(Local::now());
+    }
+    if cfg.verify_reboot && !cfg.dry_run {
+        if !cfg.reboot_settle.is_zero() {
+            info!(
+                settle_secs = cfg.reboot_settle.as_secs(),
+                "Waiting for the router to start shutting down before verifying"
+            );
+            thread::sleep(cfg.reboot_settle);
+        }
+        let downtime = verify::wait_for_online(client, cfg, cfg.reboot_wait)
+            .context(Failure::VerifyTimeout)?;
+        *cfg.last_downtime.lock().expect("downtime lock poisoned") = Some(downtime);
+        session.went_down = true;
+    }
+    Ok(Outcome::Done)
 }
 
 fn to_std(delta: TimeDelta) -> Duration {
@@ -296,18 +2731,2359 @@ fn to_std(delta: TimeDelta) -> Duration {
     }
 }
 
-fn origin_of(url: &Url) -> Result<Url> {
-    let mut origin = url.clone();
-    origin.set_path("");
-    origin.set_query(None);
-    origin.set_fragment(None);
-    Ok(origin)
+/// 校验 `--reboot-payload` 与 `--command` 的组合；返回已校验为 JSON 的自定义命令原文。
+fn custom_payload(args: &Args) -> Result<Option<String>> {
+    let Some(payload) = &args.reboot_payload else {
+        if args.maintenance_command == RouterCommand::Custom {
+            bail!("--command custom requires --reboot-payload");
+        }
+        return Ok(None);
+    };
+    serde_json::from_str::<serde_json::Value>(payload)
+        .context("--reboot-payload is not valid JSON")?;
+    Ok(Some(payload.clone()))
+}
+
+/// 某条命令的 JSON 请求内容：有 --reboot-payload 时用其原文，否则按 --reboot-cmdtype 生成重启模板。
+fn command_payload(command: RouterCommand, cfg: &Config) -> Result<String> {
+    match (command, &cfg.custom_payload) {
+        (_, Some(payload)) => Ok(payload.clone()),
+        (RouterCommand::Custom, None) => bail!("--command custom requires --reboot-payload"),
+        (RouterCommand::Reboot, None) => Ok(command_template(&cfg.reboot_cmdtype)),
+    }
+}
+
+/// 按 --reboot-body-mode 决定承载命令 JSON 的表单字段；raw-json 返回 `None`。
+fn reboot_field(args: &Args) -> Result<Option<String>> {
+    match (args.reboot_body_mode, &args.reboot_field_name) {
+        (RebootBodyMode::FormJsonCfg, _) => Ok(Some("jsonCfg".to_string())),
+        (RebootBodyMode::RawJson, _) => Ok(None),
+        (RebootBodyMode::FormCustom, Some(name)) if !name.is_empty() => Ok(Some(name.clone())),
+        (RebootBodyMode::FormCustom, _) => {
+            bail!("--reboot-body-mode form-custom requires --reboot-field-name")
+        }
+    }
 }
 
-fn build_url(base: &Url, path: &str) -> Result<Url> {
-    if path.starts_with("http://") || path.starts_with("https://") {
-        Url::parse(path).context("invalid absolute url")
-    } else {
-        base.join(path).context("invalid relative url")
+/// 重启命令的 JSON 模板。
+fn command_template(cmd_type: &str) -> String {
+    json!({
+        "RPCMethod": "Post",
+        "Parameter": {
+            "CmdType": cmd_type,
+        }
+    })
+    .to_string()
+}
+
+/// `list-commands`：列出 --command 的每个取值及其请求体，`<...>` 为取自其他参数的占位符。
+fn list_commands(args: &Args) {
+    let field = reboot_field(args).unwrap_or(Some("jsonCfg".to_string()));
+    for command in RouterCommand::value_variants() {
+        let Some(value) = command.to_possible_value() else {
+            continue;
+        };
+        let payload = match command {
+            RouterCommand::Reboot => command_template("<--reboot-cmdtype>"),
+            RouterCommand::Custom => "<--reboot-payload>".to_string(),
+        };
+        println!("{}", value.get_name());
+        if let Some(help) = value.get_help() {
+            println!("    {help}");
+        }
+        match &field {
+            Some(field) => println!("    {field}={payload}"),
+            None => println!("    {payload}"),
+        }
+    }
+    println!();
+    println!("--reboot-cmdtype is currently {:?}", args.reboot_cmdtype);
+    if args.reboot_payload.is_some() {
+        println!("--reboot-payload is set and replaces the reboot payload");
+    }
+}
+
+/// 解析 `--header "Name: Value"`；同名的多个条目以最后一个为准。
+fn parse_headers(entries: &[String]) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for entry in entries {
+        let (name, value) = entry
+            .split_once(':')
+            .with_context(|| format!("invalid --header {entry:?}: expected \"Name: Value\""))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .with_context(|| format!("invalid header name in --header {entry:?}"))?;
+        let value = HeaderValue::from_str(value.trim())
+            .with_context(|| format!("invalid header value in --header {entry:?}"))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// 解析 `--host`；IPv6 地址需要写成 `http://[2001:db8::1]:8080` 的形式。
+fn parse_host(host: &str) -> Result<Url> {
+    // url crate 不支持 `[fe80::1%eth0]` 这种带 zone id 的写法，报错信息也不直观，这里先拦下。
+    if let Some(literal) = host
+        .split_once('[')
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(literal, _)| literal)
+        && literal.contains('%')
+    {
+        bail!(
+            "IPv6 zone ids are not supported in {host:?}; use a global or unique-local address instead"
+        );
+    }
+    let url = Url::parse(host)?;
+    if url.host().is_none() {
+        bail!("{host:?} has no host");
+    }
+    Ok(url)
+}
+
+/// 与浏览器发送的 Origin 一致：scheme://host[:port]，不带结尾斜杠，默认端口省略；IPv6 地址带方括号。
+fn origin_of(url: &Url) -> Result<String> {
+    let origin = url.origin();
+    if !origin.is_tuple() {
+        bail!("URL {url} has no usable origin");
+    }
+    Ok(origin.ascii_serialization())
+}
+
+fn build_url(base: &Url, path: &str) -> Result<Url> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        Url::parse(path).context("invalid absolute url")
+    } else {
+        base.join(path).context("invalid relative url")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Answer, Stub, client, config};
+
+    const LOGIN_PAGE: &str =
+        r#"<form><input type="hidden" name="Frm_Logintoken" value="77"></form>"#;
+
+    /// 登录页带 token，登录 POST 返回会话 cookie。
+    fn login_router() -> Stub {
+        Stub::start(|hit| match hit.method.as_str() {
+            "GET" => Answer::ok(LOGIN_PAGE),
+            _ => Answer::ok("<html>welcome</html>").header("Set-Cookie", "SID=abc; Path=/"),
+        })
+    }
+
+    const REBOOT_PATH: &str = "/common_page/gatewayManage.lua";
+
+    /// 登录页带 token、登录成功、重启被接受的路由器；`pages` 追加或覆盖其他路径的应答。
+    fn gateway(pages: &'static [(&'static str, &'static str)]) -> Stub {
+        Stub::start(move |hit| {
+            if let Some((_, body)) = pages.iter().find(|(path, _)| *path == hit.path()) {
+                return Answer::ok(*body);
+            }
+            match (hit.method.as_str(), hit.path()) {
+                ("GET", "/") => Answer::ok(LOGIN_PAGE),
+                ("POST", "/") => Answer::ok("welcome").header("Set-Cookie", "SID=abc; Path=/"),
+                ("POST", REBOOT_PATH) => Answer::ok(r#"{"IF_ERRORID":0}"#),
+                _ => Answer::status(404, "not found"),
+            }
+        })
+    }
+
+    #[test]
+    fn login_posts_scraped_token() {
+        let stub = login_router();
+        let cfg = config(&stub.base, &[]);
+        login(&client(), &cfg).unwrap();
+
+        let posts: Vec<_> = stub
+            .hits()
+            .into_iter()
+            .filter(|h| h.method == "POST")
+            .collect();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].field("Frm_Logintoken").as_deref(), Some("77"));
+        assert_eq!(posts[0].field("user_name").as_deref(), Some("useradmin"));
+        assert_eq!(posts[0].field("Password").as_deref(), Some("secret"));
+        assert_eq!(posts[0].field("action").as_deref(), Some("login"));
+    }
+
+    #[test]
+    fn dry_run_sends_nothing() {
+        let stub = login_router();
+        let cfg = config(&stub.base, &["--dry-run"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert!(stub.hits().is_empty());
+        assert!(cfg.last_reboot.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn sha256_password_uses_scraped_token() {
+        let stub = login_router();
+        let cfg = config(&stub.base, &["--password-encoding", "sha256-token"]);
+        login(&client(), &cfg).unwrap();
+        let post = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        assert_eq!(
+            post.field("Password").as_deref(),
+            Some("ed808a9065fffd2e1f8b26c2dab53acdea252e642182ed974309b331ad41698d")
+        );
+    }
+
+    #[test]
+    fn login_falls_back_to_supplied_token() {
+        let stub = Stub::start(|hit| match hit.method.as_str() {
+            "GET" => Answer::ok("<html>no token here</html>"),
+            _ => Answer::ok("welcome"),
+        });
+        let cfg = config(&stub.base, &[]);
+        login(&client(), &cfg).unwrap();
+
+        let post = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        assert_eq!(
+            post.field("Frm_Logintoken").as_deref(),
+            Some(DEFAULT_LOGIN_TOKEN)
+        );
+    }
+
+    #[test]
+    fn login_fails_on_builtin_failure_marker() {
+        let stub = Stub::start(|hit| match hit.method.as_str() {
+            "GET" => Answer::ok(LOGIN_PAGE),
+            _ => Answer::ok("<script>alert('用户名或密码错误')</script>"),
+        });
+        let cfg = config(&stub.base, &["--max-retries", "0"]);
+        let err = login(&client(), &cfg).unwrap_err();
+        assert!(err.to_string().contains("credentials invalid"), "{err:#}");
+    }
+
+    #[test]
+    fn login_fails_when_login_form_comes_back() {
+        let stub = Stub::start(|_| Answer::ok(LOGIN_PAGE));
+        let cfg = config(&stub.base, &[]);
+        assert!(login(&client(), &cfg).is_err());
+    }
+
+    #[test]
+    fn login_body_checks_markers() {
+        let host = "http://192.168.1.1";
+        let cfg = config(host, &[]);
+        assert!(check_login_body("<html>main</html>", &cfg).is_ok());
+        assert!(check_login_body("登录失败", &cfg).is_err());
+
+        let cfg = config(host, &["--login-failure-marker", "bad login"]);
+        assert!(check_login_body("oops, bad login", &cfg).is_err());
+
+        // 指定了成功标记时只看它，不再检查失败标记。
+        let cfg = config(host, &["--login-success-marker", "logout.lp"]);
+        assert!(check_login_body("<a href=logout.lp>", &cfg).is_ok());
+        let err = check_login_body("<html>main</html>", &cfg).unwrap_err();
+        assert!(err.to_string().contains("success marker"), "{err}");
+    }
+
+    #[test]
+    fn stored_session_is_reused_without_logging_in() {
+        let stub = Stub::start(|hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::ok("welcome").header("Set-Cookie", "SID=abc; Path=/"),
+            _ => Answer::ok(r#"{"IF_ERRORID":0}"#),
+        });
+        let path = test_support::temp_path("reused-session.json");
+        let cookie_file = path.to_str().unwrap();
+        let first = config(&stub.base, &["--cookie-file", cookie_file]);
+        let first_client =
+            build_client(&test_support::args(&[]), first.cookie_jar.as_ref()).unwrap();
+        login(&first_client, &first).unwrap();
+
+        // 模拟进程重启：重新读取 cookie 文件。
+        let cfg = config(&stub.base, &["--cookie-file", cookie_file]);
+        let client = build_client(&test_support::args(&[]), cfg.cookie_jar.as_ref()).unwrap();
+        let outcome = run_once(&client, &cfg);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(outcome.unwrap(), Outcome::Done);
+
+        let logins = stub
+            .hits()
+            .into_iter()
+            .filter(|h| h.method == "POST" && h.path() == "/")
+            .count();
+        assert_eq!(logins, 1);
+        let reboot = &stub.hits_to("/common_page/gatewayManage.lua")[0];
+        assert_eq!(reboot.header("Cookie"), Some("SID=abc"));
+    }
+
+    #[test]
+    fn low_uptime_skips_the_reboot() {
+        let stub = gateway(&[("/common_page/deviceinfo.lua", r#"{"DevUpTime":"3600"}"#)]);
+        let cfg = config(&stub.base, &["--min-uptime-hours", "24"]);
+        let outcome = run_once(&client(), &cfg).unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Skipped("uptime 1h below --min-uptime-hours 24".into())
+        );
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+        assert!(cfg.last_reboot.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn long_uptime_reboots() {
+        let stub = gateway(&[("/common_page/deviceinfo.lua", r#"{"DevUpTime":"200000"}"#)]);
+        let cfg = config(&stub.base, &["--min-uptime-hours", "24"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    #[test]
+    fn unknown_uptime_reboots_anyway() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--min-uptime-hours", "24"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    const CLIENTS: &str = r#"{"Hosts":[{"MAC":"a"},{"MAC":"b"},{"MAC":"c"}]}"#;
+
+    #[test]
+    fn busy_network_defers_the_reboot() {
+        let stub = gateway(&[("/common_page/lanStatus.lua", CLIENTS)]);
+        let cfg = config(&stub.base, &["--skip-if-clients-above", "2"]);
+        assert_eq!(
+            run_once(&client(), &cfg).unwrap(),
+            Outcome::Skipped("3 active clients above --skip-if-clients-above 2".into())
+        );
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+    }
+
+    #[test]
+    fn quiet_network_reboots() {
+        let stub = gateway(&[("/common_page/lanStatus.lua", CLIENTS)]);
+        let cfg = config(&stub.base, &["--skip-if-clients-above", "3"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    #[test]
+    fn client_check_failure_depends_on_strictness() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--skip-if-clients-above", "2"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+
+        let stub = gateway(&[]);
+        let cfg = config(
+            &stub.base,
+            &["--skip-if-clients-above", "2", "--strict-client-check"],
+        );
+        let err = run_once(&client(), &cfg).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("--strict-client-check"),
+            "{err:#}"
+        );
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+    }
+
+    #[test]
+    fn reboot_url_carries_millisecond_timestamp_by_default() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &[]);
+        run_once(&client(), &cfg).unwrap();
+        let reboot = &stub.hits_to(REBOOT_PATH)[0];
+        let stamp = reboot.field("timeStamp").unwrap();
+        assert_eq!(stamp.len(), 13, "{stamp}");
+        assert!(stamp.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn timestamp_parameter_name_and_unit_are_configurable() {
+        let stub = gateway(&[]);
+        let cfg = config(
+            &stub.base,
+            &["--timestamp-param", "_", "--timestamp-format", "secs"],
+        );
+        run_once(&client(), &cfg).unwrap();
+        let reboot = &stub.hits_to(REBOOT_PATH)[0];
+        assert_eq!(reboot.field("timeStamp"), None);
+        let secs: i64 = reboot.field("_").unwrap().parse().unwrap();
+        assert!((secs - Local::now().timestamp()).abs() < 60);
+    }
+
+    #[test]
+    fn default_reboot_payload_uses_cmdtype() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--reboot-cmdtype", "HG_COMMAND_RESTART"]);
+        run_once(&client(), &cfg).unwrap();
+        let payload: Value =
+            serde_json::from_str(&stub.hits_to(REBOOT_PATH)[0].field("jsonCfg").unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({ "RPCMethod": "Post", "Parameter": { "CmdType": "HG_COMMAND_RESTART" } })
+        );
+    }
+
+    #[test]
+    fn custom_reboot_payload_is_sent_verbatim() {
+        let stub = gateway(&[]);
+        let payload = r#"{"RPCMethod":"Reboot","Delay":5}"#;
+        let cfg = config(&stub.base, &["--reboot-payload", payload]);
+        run_once(&client(), &cfg).unwrap();
+        assert_eq!(
+            stub.hits_to(REBOOT_PATH)[0].field("jsonCfg").as_deref(),
+            Some(payload)
+        );
+    }
+
+    #[test]
+    fn invalid_reboot_payload_is_rejected() {
+        let a = test_support::args(&["--reboot-payload", "{not json"]);
+        let err = build_config("test".into(), &a).unwrap_err();
+        assert!(err.to_string().contains("not valid JSON"), "{err:#}");
+
+        let argv = [
+            "tianyi-auto",
+            "--reboot-payload",
+            "{}",
+            "--reboot-cmdtype",
+            "X",
+        ];
+        assert!(Args::try_parse_from(argv).is_err());
+    }
+
+    #[test]
+    fn test_login_never_reboots() {
+        let stub = gateway(&[]);
+        test_login(&[test_support::router(&stub.base, &[])]).unwrap();
+        assert_eq!(stub.hits_to("/").len(), 2);
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+    }
+
+    #[test]
+    fn test_login_reports_failed_routers() {
+        let stub = gateway(&[]);
+        let down = test_support::closed_port_url();
+        let routers = [
+            test_support::router(&stub.base, &[]),
+            test_support::router(&down, &["--max-retries", "0"]),
+        ];
+        let err = test_login(&routers).unwrap_err();
+        assert_eq!(err.to_string(), "login test failed for 1 of 2 router(s)");
+    }
+
+    /// 用不接信号的等待器跑调度循环，避免与 signals 模块的测试互相干扰。
+    fn run_schedule(runner: &Runner, args: &Args) -> Result<()> {
+        schedule_loop(runner, args, timetable(args).unwrap(), &SignalWaiter::new())
+    }
+
+    #[test]
+    fn scheduler_exits_after_max_runs() {
+        let stub = gateway(&[]);
+        let runner = test_support::runner(vec![test_support::router(&stub.base, &[])], false);
+        let args = test_support::args(&["--interval", "1s", "--max-runs", "2"]);
+        run_schedule(&runner, &args).unwrap();
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 2);
+        let healthz = runner.health.handle(&tiny_http::Method::Get, "/healthz");
+        assert_eq!(healthz.status, 503);
+    }
+
+    #[test]
+    fn run_now_counts_toward_max_runs_only_when_asked() {
+        let stub = gateway(&[]);
+        let runner = test_support::runner(vec![test_support::router(&stub.base, &[])], false);
+        let args = test_support::args(&["--interval", "1s", "--max-runs", "1", "--run-now"]);
+        run_schedule(&runner, &args).unwrap();
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 2);
+
+        let stub = gateway(&[]);
+        let runner = test_support::runner(vec![test_support::router(&stub.base, &[])], false);
+        let args = test_support::args(&[
+            "--interval",
+            "1s",
+            "--max-runs",
+            "1",
+            "--run-now",
+            "--count-run-now",
+        ]);
+        run_schedule(&runner, &args).unwrap();
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    #[test]
+    fn control_api_triggers_a_manual_run_between_scheduled_runs() {
+        let stub = gateway(&[]);
+        let runner = test_support::runner(vec![test_support::router(&stub.base, &[])], false);
+        let control = test_support::closed_port_url();
+        let addr = control.trim_start_matches("http://");
+        let args = test_support::args(&[
+            "--interval",
+            "3s",
+            "--max-runs",
+            "1",
+            "--control-addr",
+            addr,
+            "--control-token",
+            "t0k",
+        ]);
+        let trigger = thread::spawn(move || {
+            let client = Client::new();
+            for _ in 0..100 {
+                let sent = client
+                    .post(format!("{control}/reboot"))
+                    .bearer_auth("t0k")
+                    .send();
+                if let Ok(resp) = sent {
+                    return resp.status().as_u16();
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            0
+        });
+        run_schedule(&runner, &args).unwrap();
+        assert_eq!(trigger.join().unwrap(), 202);
+        // 手动运行一次，加上计满 --max-runs 的那次计划运行。
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 2);
+    }
+
+    #[test]
+    fn catch_up_runs_once_for_missed_windows() {
+        let path = test_support::temp_path("catch-up-state.json");
+        let two_weeks_ago = Local::now() - TimeDelta::weeks(2);
+        let prev =
+            state::RunState::new(two_weeks_ago, &Ok(Outcome::Done), None, Default::default());
+        state::save(&path, &prev).unwrap();
+
+        let stub = gateway(&[]);
+        let runner = test_support::runner(vec![test_support::router(&stub.base, &[])], false);
+        let state_file = path.to_str().unwrap();
+        let args = test_support::args(&[
+            "--cron",
+            "0 4 * * Mon",
+            "--catch-up",
+            "--state-file",
+            state_file,
+            "--max-runs",
+            "1",
+        ]);
+        run_schedule(&runner, &args).unwrap();
+        let saved = state::load(&path).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+        assert!(saved.last_run > two_weeks_ago);
+    }
+
+    #[test]
+    fn custom_headers_override_defaults() {
+        let stub = gateway(&[]);
+        let cfg = config(
+            &stub.base,
+            &[
+                "--header",
+                "Referer: http://proxy.example/",
+                "--header",
+                "X-Proxy-Auth: k3y",
+            ],
+        );
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        let login = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        let reboot = stub.hits_to(REBOOT_PATH).remove(0);
+        for hit in [login, reboot] {
+            assert_eq!(hit.header("Referer"), Some("http://proxy.example/"));
+            assert_eq!(hit.header("X-Proxy-Auth"), Some("k3y"));
+        }
+    }
+
+    #[test]
+    fn default_referer_is_kept_without_overrides() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &[]);
+        login(&client(), &cfg).unwrap();
+        let login = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        assert_eq!(login.header("Referer"), Some(cfg.login_url.as_str()));
+    }
+
+    #[test]
+    fn invalid_header_entries_are_reported() {
+        let entries = |e: &str| parse_headers(&[e.to_string()]).unwrap_err().to_string();
+        assert_eq!(
+            entries("X-Missing-Colon"),
+            r#"invalid --header "X-Missing-Colon": expected "Name: Value""#
+        );
+        assert_eq!(
+            entries("Bad Name: v"),
+            r#"invalid header name in --header "Bad Name: v""#
+        );
+        assert_eq!(
+            entries("X-Bad: a\u{7f}b"),
+            "invalid header value in --header \"X-Bad: a\\u{7f}b\""
+        );
+    }
+
+    #[test]
+    fn later_header_entries_win() {
+        let headers = parse_headers(&["X-A: 1".to_string(), "x-a: 2".to_string()]).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers["x-a"], "2");
+    }
+
+    #[test]
+    fn built_client_sends_overridden_user_agent() {
+        let stub = Stub::start(|_| Answer::ok(""));
+        let args = test_support::args(&["--user-agent", "Probe/1.0"]);
+        let client = build_client(&args, None).unwrap();
+        client.get(&stub.base).send().unwrap();
+        assert_eq!(stub.hits()[0].header("User-Agent"), Some("Probe/1.0"));
+    }
+
+    #[test]
+    fn default_user_agent_is_used_for_login_and_reboot() {
+        let stub = gateway(&[]);
+        assert_eq!(
+            run_once(&client(), &config(&stub.base, &[])).unwrap(),
+            Outcome::Done
+        );
+        let hits = stub.hits();
+        assert!(!hits.is_empty());
+        for hit in hits {
+            assert_eq!(hit.header("User-Agent"), Some(DEFAULT_USER_AGENT));
+        }
+
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--user-agent", "Probe/1.0"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(
+            stub.hits_to(REBOOT_PATH)[0].header("User-Agent"),
+            Some("Probe/1.0")
+        );
+    }
+
+    #[test]
+    fn interval_replaces_the_default_cron() {
+        let start = Local::now();
+        let args = test_support::args(&["--interval", "90m"]);
+        let timetable = timetable_from(&args, start).unwrap();
+        assert_eq!(timetable.describe(0), "every 90m");
+        assert_eq!(
+            timetable.next_after(&start),
+            Some((0, start + TimeDelta::minutes(90)))
+        );
+    }
+
+    fn parsed(argv: &[&str]) -> Cli {
+        let argv = std::iter::once("tianyi-auto").chain(argv.iter().copied());
+        parse_cli(&Args::command().try_get_matches_from(argv).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn only_a_cron_that_was_given_counts_as_explicit() {
+        assert!(!parsed(&["--interval", "12h"]).cron_explicit);
+        assert!(parsed(&["--cron", "0 4 * * *"]).cron_explicit);
+
+        let path = test_support::temp_path("cron.toml");
+        fs::write(&path, "cron = [\"0 4 * * *\"]\n").unwrap();
+        let from_file = parsed(&["--config", path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+        assert!(from_file.cron_explicit);
+        assert_eq!(from_file.args.cron, ["0 4 * * *"]);
+    }
+
+    #[test]
+    fn waits_between_login_and_reboot() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--post-login-delay-ms", "300"]);
+        let started = Instant::now();
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    #[test]
+    fn dry_run_skips_the_post_login_delay() {
+        let cfg = config(
+            "http://192.168.1.1",
+            &["--dry-run", "--post-login-delay-ms", "60000"],
+        );
+        let started = Instant::now();
+        run_once(&client(), &cfg).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(30));
+    }
+
+    /// 每秒轮询一次、下发重启后立即开始验证。
+    const VERIFY_FAST: &[&str] = &[
+        "--verify-reboot",
+        "--reboot-settle-secs",
+        "0",
+        "--poll-interval-secs",
+        "1",
+    ];
+
+    #[test]
+    fn router_that_never_goes_down_is_rebooted_again() {
+        let stub = gateway(&[]);
+        let mut extra = VERIFY_FAST.to_vec();
+        extra.extend(["--reboot-wait-secs", "1", "--reboot-verify-retries", "1"]);
+        let err = run_once(&client(), &config(&stub.base, &extra)).unwrap_err();
+        assert!(verify::is_never_went_down(&err), "{err:#}");
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::VerifyTimeout));
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 2);
+    }
+
+    #[test]
+    fn router_that_comes_back_is_not_rebooted_again() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        let rebooted = AtomicBool::new(false);
+        let polls = AtomicUsize::new(0);
+        let stub = Stub::start(move |hit| match (hit.method.as_str(), hit.path()) {
+            // 重启后的第一次轮询时设备还没起来，之后恢复。
+            ("GET", "/")
+                if rebooted.load(Ordering::SeqCst) && polls.fetch_add(1, Ordering::SeqCst) == 0 =>
+            {
+                Answer::status(503, "")
+            }
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::ok("welcome"),
+            ("POST", REBOOT_PATH) => {
+                rebooted.store(true, Ordering::SeqCst);
+                Answer::ok(r#"{"IF_ERRORID":0}"#)
+            }
+            _ => Answer::status(404, ""),
+        });
+        let mut extra = VERIFY_FAST.to_vec();
+        extra.extend(["--reboot-wait-secs", "5", "--reboot-verify-retries", "3"]);
+        let cfg = config(&stub.base, &extra);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+        assert!(cfg.last_downtime.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn reboot_response_codes_are_checked() {
+        let cfg = config("http://192.168.1.1", &[]);
+        check_reboot_body(r#"{"IF_ERRORID":0}"#, &cfg).unwrap();
+        check_reboot_body(r#"{"IF_ERRORID":" 0 "}"#, &cfg).unwrap();
+        let err = check_reboot_body(
+            r#"{"IF_ERRORID":"-1","IF_ERRORSTR":"SessionTimeout"}"#,
+            &cfg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "router rejected reboot: IF_ERRORID=-1 (SessionTimeout)"
+        );
+    }
+
+    #[test]
+    fn unparseable_or_unrelated_responses_are_accepted() {
+        let cfg = config("http://192.168.1.1", &[]);
+        check_reboot_body("<html>ok</html>", &cfg).unwrap();
+        check_reboot_body(r#"{"result":"ok"}"#, &cfg).unwrap();
+    }
+
+    #[test]
+    fn success_field_and_value_are_configurable() {
+        let cfg = config(
+            "http://192.168.1.1",
+            &[
+                "--reboot-success-field",
+                "ret",
+                "--reboot-success-value",
+                "ok",
+            ],
+        );
+        check_reboot_body(r#"{"data":{"ret":"ok"}}"#, &cfg).unwrap();
+        let err = check_reboot_body(r#"{"ret":"busy"}"#, &cfg).unwrap_err();
+        assert_eq!(err.to_string(), "router rejected reboot: ret=busy");
+
+        let unchecked = config("http://192.168.1.1", &["--reboot-success-field", ""]);
+        check_reboot_body(r#"{"IF_ERRORID":"-1"}"#, &unchecked).unwrap();
+    }
+
+    #[test]
+    fn rejected_reboot_fails_the_run() {
+        let stub = gateway(&[(REBOOT_PATH, r#"{"IF_ERRORID":"1","IF_ERRORSTR":"denied"}"#)]);
+        let err = run_once(&client(), &config(&stub.base, &["--max-retries", "0"])).unwrap_err();
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::Reboot));
+        assert!(
+            format!("{err:#}").contains("IF_ERRORID=1 (denied)"),
+            "{err:#}"
+        );
+    }
+
+    /// `/hop/0` 经三次 302 到达 `/hop/3`。
+    fn redirect_chain() -> Stub {
+        Stub::start(|hit| {
+            let hop: u32 = hit.path().trim_start_matches("/hop/").parse().unwrap();
+            if hop < 3 {
+                Answer::status(302, "").header("Location", &format!("/hop/{}", hop + 1))
+            } else {
+                Answer::ok("end")
+            }
+        })
+    }
+
+    fn client_with_redirects(max: &str) -> Client {
+        build_client(&test_support::args(&["--max-redirects", max]), None).unwrap()
+    }
+
+    #[test]
+    fn zero_max_redirects_does_not_follow() {
+        let stub = redirect_chain();
+        let resp = client_with_redirects("0")
+            .get(format!("{}/hop/0", stub.base))
+            .send()
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(stub.hits().len(), 1);
+    }
+
+    #[test]
+    fn redirects_are_followed_up_to_the_limit() {
+        let stub = redirect_chain();
+        let url = format!("{}/hop/0", stub.base);
+        let resp = client_with_redirects("3").get(&url).send().unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.url().path(), "/hop/3");
+
+        let err = client_with_redirects("2").get(&url).send().unwrap_err();
+        assert!(err.is_redirect(), "{err}");
+        assert!(format!("{err:?}").contains("too many redirects (max 2)"));
+    }
+
+    #[test]
+    fn verify_host_accepts_a_reachable_login_page() {
+        let stub = gateway(&[]);
+        verify_hosts(&[test_support::router(&stub.base, &[])]).unwrap();
+        assert_eq!(stub.hits_to("/").len(), 1);
+    }
+
+    #[test]
+    fn verify_host_rejects_a_bad_status() {
+        let stub = Stub::start(|_| Answer::status(404, "not found"));
+        let router = test_support::router(&stub.base, &["--login-path", "/login.asp"]);
+        let err = verify_hosts(&[router]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "router {0} returned 404 Not Found for {0}/login.asp; check --host and --login-path",
+                stub.base
+            )
+        );
+    }
+
+    #[test]
+    fn verify_host_rejects_an_unreachable_router() {
+        let down = test_support::closed_port_url();
+        let err = verify_hosts(&[test_support::router(&down, &[])]).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with(&format!("router {down} is unreachable at"))
+        );
+    }
+
+    #[test]
+    fn verify_host_is_skipped_in_dry_run() {
+        let down = test_support::closed_port_url();
+        verify_hosts(&[test_support::router(&down, &["--dry-run"])]).unwrap();
+    }
+
+    #[test]
+    fn second_reboot_within_min_interval_is_skipped() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--min-reboot-interval", "1h"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(
+            run_once(&client(), &cfg).unwrap(),
+            Outcome::Skipped("last reboot was too recent (--min-reboot-interval)".into())
+        );
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    #[test]
+    fn min_interval_survives_a_restart_via_state_file() {
+        let path = test_support::temp_path("min-interval-state.json");
+        let recent = Local::now() - TimeDelta::minutes(10);
+        let last_reboots = [("test".to_string(), recent)].into_iter().collect();
+        let prev = state::RunState::new(recent, &Ok(Outcome::Done), None, last_reboots);
+        state::save(&path, &prev).unwrap();
+
+        let stub = gateway(&[]);
+        let state_file = path.to_str().unwrap();
+        let cfg = config(
+            &stub.base,
+            &["--min-reboot-interval", "1h", "--state-file", state_file],
+        );
+        let outcome = run_once(&client(), &cfg).unwrap();
+        let elapsed = config(
+            &stub.base,
+            &["--min-reboot-interval", "5m", "--state-file", state_file],
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(outcome, Outcome::Skipped(_)));
+        assert!(stub.hits().is_empty());
+        assert_eq!(run_once(&client(), &elapsed).unwrap(), Outcome::Done);
+    }
+
+    #[test]
+    fn dry_run_does_not_start_the_min_interval() {
+        let cfg = config(
+            "http://192.168.1.1",
+            &["--dry-run", "--min-reboot-interval", "1h"],
+        );
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+    }
+
+    #[test]
+    fn args_debug_does_not_show_the_password() {
+        let args = Args::try_parse_from(["tianyi-auto", "--password", "Sup3rS3cret!"]).unwrap();
+        let debug = format!("{args:?}");
+        assert!(!debug.contains("Sup3rS3cret!"));
+        assert!(debug.contains("password: Some(***)"), "{debug}");
+    }
+
+    #[test]
+    fn get_login_errors_do_not_leak_the_password() {
+        // 登录请求（带查询串）不断重定向到自身，直到 reqwest 报 "too many redirects"。
+        let stub = Stub::start(|hit| {
+            if hit.url.contains('?') {
+                Answer::status(302, "").header("Location", &hit.url)
+            } else {
+                Answer::ok(LOGIN_PAGE)
+            }
+        });
+        let cfg = config(&stub.base, &["--login-method", "get", "--max-retries", "0"]);
+        let err = login(&client(), &cfg).unwrap_err();
+        assert!(stub.hits().iter().any(|h| h.field("Password").is_some()));
+        let text = format!("{err:?}");
+        assert!(
+            !text.contains("secret") && !text.contains("Password="),
+            "{text}"
+        );
+    }
+
+    /// 登录页设置初始会话 cookie 的路由器。
+    fn preflight_router(page_status: u16) -> Stub {
+        Stub::start(move |hit| match hit.method.as_str() {
+            "GET" => Answer::status(page_status, LOGIN_PAGE).header("Set-Cookie", "pre=1; Path=/"),
+            _ => Answer::ok("welcome"),
+        })
+    }
+
+    #[test]
+    fn preflight_get_precedes_login_and_carries_cookie() {
+        let stub = preflight_router(200);
+        let cfg = config(&stub.base, &["--preflight", "--login-token", "42"]);
+        login(&client(), &cfg).unwrap();
+        let hits = stub.hits();
+        let methods: Vec<&str> = hits.iter().map(|h| h.method.as_str()).collect();
+        assert_eq!(methods, ["GET", "POST"]);
+        assert_eq!(hits[1].header("Cookie"), Some("pre=1"));
+        assert_eq!(hits[1].field("Frm_Logintoken").as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn no_preflight_without_the_flag() {
+        let stub = preflight_router(200);
+        let cfg = config(&stub.base, &["--login-token", "42"]);
+        login(&client(), &cfg).unwrap();
+        let methods: Vec<String> = stub.hits().into_iter().map(|h| h.method).collect();
+        assert_eq!(methods, ["POST"]);
+    }
+
+    #[test]
+    fn failed_preflight_still_logs_in() {
+        let stub = preflight_router(500);
+        let cfg = config(&stub.base, &["--preflight", "--login-token", "42"]);
+        login(&client(), &cfg).unwrap();
+        assert_eq!(stub.hits().len(), 2);
+    }
+
+    #[test]
+    fn pool_and_keepalive_options_build_a_working_client() {
+        let stub = Stub::start(|_| Answer::ok("ok"));
+        for extra in [
+            &[
+                "--tcp-keepalive-secs",
+                "60",
+                "--pool-idle-timeout-secs",
+                "30",
+            ][..],
+            &[
+                "--pool-idle-timeout-secs",
+                "0",
+                "--pool-max-idle-per-host",
+                "0",
+            ][..],
+        ] {
+            let client = build_client(&test_support::args(extra), None).unwrap();
+            for _ in 0..2 {
+                assert_eq!(client.get(&stub.base).send().unwrap().text().unwrap(), "ok");
+            }
+        }
+        assert_eq!(stub.hits().len(), 4);
+    }
+
+    #[test]
+    fn idle_connections_expire_by_default() {
+        let args = test_support::args(&[]);
+        assert_eq!(args.pool_idle_timeout_secs, 90);
+        assert_eq!(args.pool_max_idle_per_host, None);
+        assert_eq!(args.tcp_keepalive_secs, None);
+    }
+
+    /// 前 `rejections` 次重启请求按会话失效拒绝（403，或 `login_page` 时返回登录表单）。
+    fn expiring_router(rejections: usize, login_page: bool) -> Stub {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let reboots = AtomicUsize::new(0);
+        Stub::start(move |hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::ok("welcome"),
+            ("POST", REBOOT_PATH) if reboots.fetch_add(1, Ordering::SeqCst) < rejections => {
+                if login_page {
+                    Answer::ok(LOGIN_PAGE)
+                } else {
+                    Answer::status(403, "forbidden")
+                }
+            }
+            ("POST", REBOOT_PATH) => Answer::ok(r#"{"IF_ERRORID":0}"#),
+            _ => Answer::status(404, ""),
+        })
+    }
+
+    fn login_posts(stub: &Stub) -> usize {
+        stub.hits_to("/")
+            .iter()
+            .filter(|h| h.method == "POST")
+            .count()
+    }
+
+    #[test]
+    fn expired_session_logs_in_again_before_rebooting() {
+        for login_page in [false, true] {
+            let stub = expiring_router(1, login_page);
+            let cfg = config(&stub.base, &[]);
+            assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+            assert_eq!(login_posts(&stub), 2);
+            assert_eq!(stub.hits_to(REBOOT_PATH).len(), 2);
+        }
+    }
+
+    #[test]
+    fn relogin_is_attempted_only_once() {
+        let stub = expiring_router(usize::MAX, false);
+        let err = run_once(&client(), &config(&stub.base, &[])).unwrap_err();
+        assert!(is_session_expired(&err), "{err:#}");
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::Reboot));
+        assert_eq!(login_posts(&stub), 2);
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 2);
+    }
+
+    #[test]
+    fn login_form_uses_overridden_field_names() {
+        let stub = Stub::start(|hit| match hit.method.as_str() {
+            "GET" => Answer::ok(r#"<input type="hidden" name="csrf_tok" value="99">"#),
+            _ => Answer::ok("welcome"),
+        });
+        let cfg = config(
+            &stub.base,
+            &[
+                "--field-username",
+                "Username",
+                "--field-password",
+                "Passwd",
+                "--field-token",
+                "csrf_tok",
+                "--field-action",
+                "op",
+                "--field-frashnum",
+                "nonce",
+            ],
+        );
+        login(&client(), &cfg).unwrap();
+        let post = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        let mut keys: Vec<String> = url::form_urlencoded::parse(post.body.as_bytes())
+            .map(|(k, _)| k.into_owned())
+            .collect();
+        keys.sort();
+        assert_eq!(keys, ["Passwd", "Username", "csrf_tok", "nonce", "op"]);
+        assert_eq!(post.field("Passwd").as_deref(), Some("secret"));
+        assert_eq!(post.field("csrf_tok").as_deref(), Some("99"));
+        assert_eq!(post.field("op").as_deref(), Some("login"));
+    }
+
+    #[test]
+    fn get_login_sends_fields_as_query() {
+        let stub = Stub::start(|hit| {
+            if hit.url.contains('?') {
+                Answer::ok("welcome").header("Set-Cookie", "SID=abc; Path=/")
+            } else {
+                Answer::ok(LOGIN_PAGE)
+            }
+        });
+        let cfg = config(
+            &stub.base,
+            &["--login-method", "get", "--login-path", "/login.cgi"],
+        );
+        login(&client(), &cfg).unwrap();
+
+        let hits = stub.hits_to("/login.cgi");
+        assert_eq!(hits.len(), 2);
+        let login = &hits[1];
+        assert_eq!(login.method, "GET");
+        assert!(login.body.is_empty());
+        assert_eq!(login.header("Content-Type"), None);
+        let query: HashMap<String, String> =
+            url::form_urlencoded::parse(login.url.split_once('?').unwrap().1.as_bytes())
+                .into_owned()
+                .collect();
+        assert_eq!(query["user_name"], "useradmin");
+        assert_eq!(query["Password"], "secret");
+        assert_eq!(query["Frm_Logintoken"], "77");
+        assert_eq!(query["action"], "login");
+    }
+
+    #[test]
+    fn json_login_sends_a_json_body_with_configured_keys() {
+        let stub = login_router();
+        let cfg = config(
+            &stub.base,
+            &[
+                "--login-body-format",
+                "json",
+                "--field-username",
+                "username",
+                "--field-password",
+                "password",
+            ],
+        );
+        login(&client(), &cfg).unwrap();
+        let posts: Vec<_> = stub
+            .hits()
+            .into_iter()
+            .filter(|h| h.method == "POST")
+            .collect();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].header("Content-Type"), Some("application/json"));
+        let body: Value = serde_json::from_str(&posts[0].body).unwrap();
+        let body = body.as_object().unwrap();
+        let mut keys: Vec<_> = body.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "Frm_Logintoken",
+                "action",
+                "frashnum",
+                "password",
+                "username"
+            ]
+        );
+        assert_eq!(body["action"], "login");
+        assert_eq!(body["username"], "useradmin");
+        let token = body["Frm_Logintoken"].as_str().unwrap();
+        assert_eq!(
+            body["password"],
+            cfg.password_encoding.encode("secret", token)
+        );
+    }
+
+    #[test]
+    fn form_login_is_the_default() {
+        let stub = login_router();
+        login(&client(), &config(&stub.base, &[])).unwrap();
+        let post = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        assert_eq!(
+            post.header("Content-Type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(post.field("user_name").as_deref(), Some("useradmin"));
+    }
+
+    #[test]
+    fn get_login_cannot_send_json() {
+        let args = test_support::args(&["--login-method", "get", "--login-body-format", "json"]);
+        let err = build_config("test".into(), &args).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--login-body-format json requires --login-method post"
+        );
+    }
+
+    #[test]
+    fn no_reboot_only_logs_in() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--no-reboot", "--min-uptime-hours", "24"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(login_posts(&stub), 1);
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+        assert!(stub.hits_to("/common_page/deviceinfo.lua").is_empty());
+        assert!(cfg.last_reboot.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn no_reboot_with_payload_sends_the_maintenance_command() {
+        let stub = gateway(&[]);
+        let payload = r#"{"CmdType":"HG_COMMAND_DHCP_RENEW"}"#;
+        let cfg = config(&stub.base, &["--no-reboot", "--reboot-payload", payload]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        let sent = stub.hits_to(REBOOT_PATH);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].field("jsonCfg").as_deref(), Some(payload));
+        assert!(cfg.last_reboot.lock().unwrap().is_none());
+    }
+
+    const CONFIRM_PATH: &str = "/common_page/rebootConfirm.lua";
+
+    #[test]
+    fn two_step_reboot_takes_token_from_json_response() {
+        let stub = gateway(&[
+            (REBOOT_PATH, r#"{"IF_ERRORID":0,"_sessionTOKEN":"abc123"}"#),
+            (CONFIRM_PATH, r#"{"IF_ERRORID":0}"#),
+        ]);
+        let cfg = config(&stub.base, &["--reboot-confirm-path", CONFIRM_PATH]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        let confirms = stub.hits_to(CONFIRM_PATH);
+        assert_eq!(confirms.len(), 1);
+        assert_eq!(
+            confirms[0].field("_sessionTOKEN").as_deref(),
+            Some("abc123")
+        );
+    }
+
+    #[test]
+    fn two_step_reboot_takes_token_from_html_response() {
+        let stub = gateway(&[
+            (
+                REBOOT_PATH,
+                r#"<form><input type="hidden" name="confirmTok" value="t9"></form>"#,
+            ),
+            (CONFIRM_PATH, r#"{"IF_ERRORID":0}"#),
+        ]);
+        let cfg = config(
+            &stub.base,
+            &[
+                "--reboot-confirm-path",
+                CONFIRM_PATH,
+                "--reboot-confirm-field",
+                "confirmTok",
+            ],
+        );
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(
+            stub.hits_to(CONFIRM_PATH)[0].field("confirmTok").as_deref(),
+            Some("t9")
+        );
+    }
+
+    #[test]
+    fn missing_confirmation_token_fails_the_reboot() {
+        let stub = gateway(&[(CONFIRM_PATH, r#"{"IF_ERRORID":0}"#)]);
+        let cfg = config(&stub.base, &["--reboot-confirm-path", CONFIRM_PATH]);
+        let err = run_once(&client(), &cfg).unwrap_err();
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::Reboot));
+        assert!(
+            format!("{err:#}").contains(r#"confirmation token "_sessionTOKEN" not found"#),
+            "{err:#}"
+        );
+        assert!(stub.hits_to(CONFIRM_PATH).is_empty());
+    }
+
+    #[test]
+    fn built_client_sends_configured_accept_language() {
+        let stub = Stub::start(|_| Answer::ok(""));
+        client().get(&stub.base).send().unwrap();
+        let args = test_support::args(&["--accept-language", "en-US,en;q=0.9"]);
+        build_client(&args, None)
+            .unwrap()
+            .get(&stub.base)
+            .send()
+            .unwrap();
+        let hits = stub.hits();
+        assert_eq!(
+            hits[0].header("Accept-Language"),
+            Some(DEFAULT_ACCEPT_LANGUAGE)
+        );
+        assert_eq!(hits[1].header("Accept-Language"), Some("en-US,en;q=0.9"));
+    }
+
+    #[test]
+    fn invalid_accept_language_is_rejected() {
+        let args = test_support::args(&["--accept-language", "en\nX-Injected: 1"]);
+        let err = build_client(&args, None).unwrap_err();
+        assert_eq!(err.to_string(), "invalid --accept-language");
+    }
+
+    #[test]
+    fn basic_auth_header_is_sent_with_login_and_reboot() {
+        let stub = gateway(&[]);
+        let extra = ["--http-auth-user", "admin", "--http-auth-pass", "pa55"];
+        let client = build_client(&test_support::args(&extra), None).unwrap();
+        assert_eq!(
+            run_once(&client, &config(&stub.base, &extra)).unwrap(),
+            Outcome::Done
+        );
+        let hits = stub.hits();
+        assert!(hits.len() >= 3);
+        for hit in hits {
+            // base64("admin:pa55")
+            assert_eq!(hit.header("Authorization"), Some("Basic YWRtaW46cGE1NQ=="));
+        }
+    }
+
+    #[test]
+    fn no_authorization_header_by_default() {
+        let stub = gateway(&[]);
+        run_once(&client(), &config(&stub.base, &[])).unwrap();
+        assert!(
+            stub.hits()
+                .iter()
+                .all(|h| h.header("Authorization").is_none())
+        );
+    }
+
+    #[test]
+    fn http_auth_pass_requires_user() {
+        let args = test_support::args(&["--http-auth-pass", "pa55"]);
+        assert_eq!(
+            basic_auth(&args).unwrap_err().to_string(),
+            "--http-auth-pass requires --http-auth-user"
+        );
+    }
+
+    #[test]
+    fn digest_challenge_fails_login_with_a_clear_error() {
+        let stub = Stub::start(|_| {
+            Answer::status(401, "").header("WWW-Authenticate", r#"Digest realm="ZTE", nonce="1""#)
+        });
+        let err = login(&client(), &config(&stub.base, &[])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "router requires HTTP Digest authentication, which is not supported (only Basic via --http-auth-user)"
+        );
+    }
+
+    #[test]
+    fn rotation_picks_from_the_builtin_list_once_per_run() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--user-agent-rotate"]);
+        for _ in 0..3 {
+            assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        }
+        let hits = stub.hits();
+        assert!(!hits.is_empty());
+        for hit in &hits {
+            let ua = hit.header("User-Agent").unwrap();
+            assert!(USER_AGENTS.contains(&ua), "{ua}");
+        }
+        // 同一次运行内的登录与重启使用同一个 UA。
+        let reboots = stub.hits_to(REBOOT_PATH);
+        let logins: Vec<_> = stub
+            .hits_to("/")
+            .into_iter()
+            .filter(|h| h.method == "POST")
+            .collect();
+        assert_eq!((logins.len(), reboots.len()), (3, 3));
+        for (login, reboot) in logins.iter().zip(&reboots) {
+            assert_eq!(login.header("User-Agent"), reboot.header("User-Agent"));
+        }
+
+        let seen: std::collections::HashSet<_> = (0..64)
+            .map(|_| {
+                cfg.rotate_user_agent();
+                cfg.user_agent()
+            })
+            .collect();
+        assert!(seen.len() > 1);
+        assert!(
+            seen.iter()
+                .all(|ua| USER_AGENTS.contains(&ua.to_str().unwrap()))
+        );
+    }
+
+    #[test]
+    fn explicit_user_agent_disables_rotation() {
+        let stub = gateway(&[]);
+        let cfg = config(
+            &stub.base,
+            &["--user-agent-rotate", "--user-agent", "Probe/1.0"],
+        );
+        for _ in 0..3 {
+            assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        }
+        for hit in stub.hits() {
+            assert_eq!(hit.header("User-Agent"), Some("Probe/1.0"));
+        }
+    }
+
+    fn secret(text: &str) -> Secret {
+        let Ok(secret) = text.parse::<Secret>();
+        secret
+    }
+
+    fn resolved(extra: &[&str], keyring: Result<Option<&str>, &str>) -> String {
+        let mut args = test_support::args(extra);
+        resolve_password(&mut args, |service, username| {
+            assert_eq!((service, username), ("tianyi", "useradmin"));
+            keyring
+                .map(|found| found.map(secret))
+                .map_err(|e| anyhow::anyhow!(e.to_string()))
+        })
+        .unwrap();
+        args.password.unwrap().expose().to_string()
+    }
+
+    #[test]
+    fn keyring_entry_takes_precedence_over_the_password_flag() {
+        let keyring = ["--keyring-service", "tianyi"];
+        assert_eq!(resolved(&keyring, Ok(Some("from-keyring"))), "from-keyring");
+        // 条目不存在或密钥环不可用时退回 --password / ROUTER_PASSWORD。
+        assert_eq!(resolved(&keyring, Ok(None)), "secret");
+        assert_eq!(resolved(&keyring, Err("no secret service")), "secret");
+        // 未指定 --keyring-service 时根本不查询密钥环。
+        let mut args = test_support::args(&[]);
+        resolve_password(&mut args, |_, _| panic!("keyring must not be queried")).unwrap();
+        assert_eq!(args.password.unwrap().expose(), "secret");
+    }
+
+    #[test]
+    fn password_file_takes_precedence_over_the_keyring() {
+        let path = test_support::temp_path("pw-over-keyring");
+        std::fs::write(&path, "from-file\n").unwrap();
+        let mut args = test_support::args(&[
+            "--keyring-service",
+            "tianyi",
+            "--password-file",
+            path.to_str().unwrap(),
+        ]);
+        let result = resolve_password(&mut args, |_, _| panic!("keyring must not be queried"));
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(args.password.unwrap().expose(), "from-file");
+    }
+
+    /// 路由器不在线的执行器：每次运行都会失败。
+    fn failing_runner() -> Runner {
+        let router =
+            test_support::router(&test_support::closed_port_url(), &["--max-retries", "0"]);
+        test_support::runner(vec![router], false)
+    }
+
+    #[test]
+    fn failed_run_keeps_the_scheduler_going_by_default() {
+        let runner = failing_runner();
+        let args = test_support::args(&[
+            "--interval",
+            "1h",
+            "--run-now",
+            "--count-run-now",
+            "--max-runs",
+            "1",
+        ]);
+        run_schedule(&runner, &args).unwrap();
+    }
+
+    #[test]
+    fn failed_run_ends_the_scheduler_with_on_failure_exit() {
+        let runner = failing_runner();
+        let args = test_support::args(&["--interval", "1h", "--run-now", "--on-failure", "exit"]);
+        let err = run_schedule(&runner, &args).unwrap_err();
+        assert_eq!(exit::code(&err), 6);
+        let healthz = runner.health.handle(&tiny_http::Method::Get, "/healthz");
+        assert_eq!(healthz.status, 503);
+    }
+
+    fn cmd_type_sent(extra: &[&str], command: RouterCommand) -> String {
+        let payload = command_payload(command, &config("http://192.168.1.1", extra)).unwrap();
+        let payload: Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["RPCMethod"], "Post");
+        payload["Parameter"]["CmdType"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn built_in_templates_select_the_cmd_type() {
+        assert_eq!(
+            cmd_type_sent(&[], RouterCommand::Reboot),
+            "HG_COMMAND_REBOOT"
+        );
+        assert_eq!(
+            cmd_type_sent(
+                &["--reboot-cmdtype", "HG_COMMAND_RESTART"],
+                RouterCommand::Reboot
+            ),
+            "HG_COMMAND_RESTART"
+        );
+    }
+
+    #[test]
+    fn custom_command_sends_the_payload_verbatim() {
+        let payload = r#"{"RPCMethod":"Post","Parameter":{"CmdType":"HG_X"}}"#;
+        let cfg = config(
+            "http://192.168.1.1",
+            &["--command", "custom", "--reboot-payload", payload],
+        );
+        assert_eq!(
+            command_payload(RouterCommand::Custom, &cfg).unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn invalid_command_and_payload_combinations_are_rejected() {
+        let error = |extra: &[&str]| {
+            let err = build_config("test".into(), &test_support::args(extra))
+                .err()
+                .unwrap();
+            format!("{err:#}")
+        };
+        assert!(error(&["--command", "custom"]).contains("requires --reboot-payload"));
+        assert!(error(&["--reboot-payload", "{not json"]).contains("not valid JSON"));
+    }
+
+    #[test]
+    fn maintenance_command_is_sent_without_reboot_bookkeeping() {
+        let stub = gateway(&[]);
+        let payload = command_template("HG_X");
+        let cfg = config(
+            &stub.base,
+            &[
+                "--command",
+                "custom",
+                "--reboot-payload",
+                &payload,
+                "--verify-reboot",
+            ],
+        );
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        let sent = stub.hits_to(REBOOT_PATH);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].field("jsonCfg"), Some(payload));
+        // 不是重启：不记录重启时间，也不等待设备掉线。
+        assert!(cfg.last_reboot.lock().unwrap().is_none());
+        assert!(cfg.last_downtime.lock().unwrap().is_none());
+    }
+
+    /// 登录后 `/status` 的应答由 `page` 决定。
+    fn session_router(page: fn(&test_support::Hit) -> Answer) -> Stub {
+        Stub::start(move |hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::ok("welcome").header("Set-Cookie", "SID=abc; Path=/"),
+            ("GET", "/status") => page(hit),
+            _ => Answer::status(404, "not found"),
+        })
+    }
+
+    fn validated_login(page: fn(&test_support::Hit) -> Answer) -> Result<bool> {
+        let stub = session_router(page);
+        let cfg = config(&stub.base, &["--validate-path", "/status"]);
+        let result = login(&client(), &cfg);
+        assert!(!stub.hits_to("/status").is_empty());
+        result
+    }
+
+    #[test]
+    fn validate_path_accepts_a_valid_session() {
+        validated_login(|hit| match hit.header("Cookie") {
+            Some(cookie) if cookie.contains("SID=abc") => Answer::ok("<html>status</html>"),
+            _ => Answer::status(302, "").header("Location", "/"),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn validate_path_rejects_an_expired_session() {
+        let err = validated_login(|_| Answer::status(302, "").header("Location", "/")).unwrap_err();
+        assert!(
+            err.to_string().contains("redirected to the login page"),
+            "{err:#}"
+        );
+
+        let err = validated_login(|_| Answer::status(401, "")).unwrap_err();
+        assert!(err.to_string().contains("returned 401"), "{err:#}");
+
+        let err = validated_login(|_| Answer::ok(LOGIN_PAGE)).unwrap_err();
+        assert!(
+            err.to_string().contains("still shows the login form"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn failed_session_validation_fails_the_run_as_a_login_error() {
+        let stub = session_router(|_| Answer::status(403, ""));
+        let cfg = config(
+            &stub.base,
+            &["--validate-path", "/status", "--max-retries", "0"],
+        );
+        let err = run_once(&client(), &cfg).unwrap_err();
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::Login));
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+    }
+
+    fn fallback_router(hosts: &[&str]) -> Router {
+        let hosts = hosts.join(",");
+        let args = test_support::args(&[
+            "--host",
+            &hosts,
+            "--max-retries",
+            "0",
+            "--retry-base-delay-ms",
+            "1",
+        ]);
+        build_router("test".into(), &args).unwrap()
+    }
+
+    fn run_fallbacks(router: &Router) -> Result<Outcome> {
+        run_with_fallback(&router.client, &router.cfg, &router.fallbacks)
+    }
+
+    #[test]
+    fn next_host_is_tried_when_login_fails() {
+        let rejecting = Stub::start(|hit| match hit.method.as_str() {
+            "GET" => Answer::ok(LOGIN_PAGE),
+            _ => Answer::status(403, "forbidden"),
+        });
+        let good = gateway(&[]);
+        let router = fallback_router(&[&rejecting.base, &good.base]);
+        assert_eq!(router.fallbacks.len(), 1);
+        assert_eq!(run_fallbacks(&router).unwrap(), Outcome::Done);
+        assert!(rejecting.hits_to(REBOOT_PATH).is_empty());
+        assert_eq!(good.hits_to(REBOOT_PATH).len(), 1);
+
+        // 掉线的地址同样跳过。
+        let good = gateway(&[]);
+        let router = fallback_router(&[&test_support::closed_port_url(), &good.base]);
+        assert_eq!(run_fallbacks(&router).unwrap(), Outcome::Done);
+        assert_eq!(good.hits_to(REBOOT_PATH).len(), 1);
+    }
+
+    #[test]
+    fn failed_reboot_is_not_retried_on_the_next_host() {
+        let first = gateway(&[(REBOOT_PATH, r#"{"IF_ERRORID":"1"}"#)]);
+        let second = gateway(&[]);
+        let router = fallback_router(&[&first.base, &second.base]);
+        let err = run_fallbacks(&router).unwrap_err();
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::Reboot));
+        assert!(second.hits().is_empty());
+    }
+
+    #[test]
+    fn all_hosts_failing_reports_the_last_error() {
+        let router = fallback_router(&[
+            &test_support::closed_port_url(),
+            &test_support::closed_port_url(),
+        ]);
+        let err = run_fallbacks(&router).unwrap_err();
+        assert_eq!(err.to_string(), "all hosts failed");
+        assert_eq!(exit::code(&err), 6);
+    }
+
+    #[test]
+    fn fallback_hosts_share_the_last_reboot_time() {
+        let first = gateway(&[]);
+        let router = fallback_router(&[&first.base, " http://192.168.0.1 ", ""]);
+        assert_eq!(router.fallbacks.len(), 1);
+        assert_eq!(
+            router.fallbacks[0].login_url.as_str(),
+            "http://192.168.0.1/"
+        );
+        assert!(Arc::ptr_eq(
+            &router.cfg.last_reboot,
+            &router.fallbacks[0].last_reboot
+        ));
+    }
+
+    #[test]
+    fn empty_host_list_is_rejected() {
+        let args = test_support::args(&["--host", " , "]);
+        let err = build_router("test".into(), &args).err().unwrap();
+        assert_eq!(err.to_string(), "--host is empty for router test");
+    }
+
+    #[test]
+    fn cooldown_retries_are_capped_and_reset_by_success() {
+        let cooldown = Duration::from_secs(600);
+        let mut retries = 0;
+        let before = Local::now();
+        let at = next_cooldown_retry(false, cooldown, &mut retries, 2).unwrap();
+        assert!(at >= before + TimeDelta::seconds(600));
+        assert!(at <= Local::now() + TimeDelta::seconds(600));
+        assert_eq!(retries, 1);
+        assert!(next_cooldown_retry(false, cooldown, &mut retries, 2).is_some());
+        assert_eq!(retries, 2);
+        // 达到上限后回到正常调度，计数清零。
+        assert!(next_cooldown_retry(false, cooldown, &mut retries, 2).is_none());
+        assert_eq!(retries, 0);
+
+        assert!(next_cooldown_retry(false, cooldown, &mut retries, 2).is_some());
+        assert!(next_cooldown_retry(true, cooldown, &mut retries, 2).is_none());
+        assert_eq!(retries, 0);
+    }
+
+    /// 每 2 秒调度一次、共两次的循环里，登录 POST 的次数。
+    fn login_attempts_with_cooldown(max_failure_retries: &str) -> usize {
+        let stub = Stub::start(|_| Answer::status(500, "down"));
+        let router = test_support::router(&stub.base, &["--max-retries", "0"]);
+        let runner = test_support::runner(vec![router], false);
+        let args = test_support::args(&[
+            "--interval",
+            "2s",
+            "--max-runs",
+            "2",
+            "--retry-on-failure-after",
+            "1s",
+            "--max-failure-retries",
+            max_failure_retries,
+        ]);
+        run_schedule(&runner, &args).unwrap();
+        login_posts(&stub)
+    }
+
+    #[test]
+    fn failed_run_is_retried_after_the_cooldown_before_the_next_tick() {
+        // 第一次调度失败 → 1 秒后冷却重试 → 第二次调度。
+        assert_eq!(login_attempts_with_cooldown("3"), 3);
+        assert_eq!(login_attempts_with_cooldown("0"), 2);
+    }
+
+    #[test]
+    fn sni_override_resolves_the_hostname_to_the_host_ip() {
+        let sni = |host: &str| {
+            let args = test_support::args(&["--host", host, "--tls-sni", "router.lan"]);
+            sni_override(&args).map(|o| o.map(|(name, addr)| (name.to_string(), addr.to_string())))
+        };
+        assert_eq!(
+            sni("https://192.168.1.1").unwrap(),
+            Some(("router.lan".into(), "192.168.1.1:443".into()))
+        );
+        assert_eq!(
+            sni("https://[fd00::1]:8443").unwrap(),
+            Some(("router.lan".into(), "[fd00::1]:8443".into()))
+        );
+        let err = sni("https://gateway.home").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("requires --host to be an IP address")
+        );
+        assert_eq!(sni_override(&test_support::args(&[])).unwrap(), None);
+    }
+
+    #[test]
+    fn tls_options_build_a_client() {
+        for extra in [
+            &["--tls-min-version", "1.2"][..],
+            &["--tls-min-version", "1.3"],
+            &["--host", "https://192.168.1.1", "--tls-sni", "router.lan"],
+            &[
+                "--host",
+                "https://192.168.1.1",
+                "--tls-sni",
+                "router.lan",
+                "--tls-min-version",
+                "1.3",
+            ],
+        ] {
+            build_client(&test_support::args(extra), None).unwrap();
+        }
+        assert!(Args::try_parse_from(["tianyi-auto", "--tls-min-version", "1.1"]).is_err());
+    }
+
+    #[test]
+    fn tls_sni_is_used_for_urls_and_the_host_header() {
+        let stub = gateway(&[]);
+        let port = stub.base.rsplit(':').next().unwrap().to_string();
+        let args = test_support::args(&[
+            "--host",
+            &stub.base,
+            "--tls-sni",
+            "router.lan",
+            "--retry-base-delay-ms",
+            "1",
+        ]);
+        let cfg = build_config("test".into(), &args).unwrap();
+        assert_eq!(cfg.login_url.as_str(), format!("http://router.lan:{port}/"));
+        let client = build_client(&args, None).unwrap();
+        assert_eq!(run_once(&client, &cfg).unwrap(), Outcome::Done);
+        let expected = format!("router.lan:{port}");
+        for hit in stub.hits() {
+            assert_eq!(hit.header("Host"), Some(expected.as_str()));
+        }
+    }
+
+    #[test]
+    fn tls_sni_cannot_be_combined_with_fallback_hosts() {
+        let args = test_support::args(&[
+            "--host",
+            "https://192.168.1.1,https://192.168.0.1",
+            "--tls-sni",
+            "router.lan",
+        ]);
+        let err = build_router("test".into(), &args).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--tls-sni cannot be combined with multiple hosts"
+        );
+    }
+
+    #[test]
+    fn login_cookies_land_in_the_dumped_jar() {
+        let stub = login_router();
+        let args = test_support::args(&["--host", &stub.base]);
+        let jar = CookieJar::in_memory();
+        let client = build_client(&args, Some(&jar)).unwrap();
+        login(&client, &config(&stub.base, &[])).unwrap();
+        assert_eq!(jar.dump(false), ["SID=***; Domain=(host-only); Path=/"]);
+    }
+
+    /// 重启请求与其后第一次校验轮询之间的间隔；重启后第一次轮询时设备不在线，之后恢复。
+    fn reboot_to_first_poll(settle_secs: &str) -> Duration {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let rebooted_at: Arc<Mutex<Option<Instant>>> = Arc::default();
+        let first_poll_at: Arc<Mutex<Option<Instant>>> = Arc::default();
+        let (rebooted, polled) = (Arc::clone(&rebooted_at), Arc::clone(&first_poll_at));
+        let polls = AtomicUsize::new(0);
+        let stub = Stub::start(move |hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", "/") if rebooted.lock().unwrap().is_some() => {
+                if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    *polled.lock().unwrap() = Some(Instant::now());
+                    Answer::status(503, "")
+                } else {
+                    Answer::ok(LOGIN_PAGE)
+                }
+            }
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::ok("welcome"),
+            ("POST", REBOOT_PATH) => {
+                *rebooted.lock().unwrap() = Some(Instant::now());
+                Answer::ok(r#"{"IF_ERRORID":0}"#)
+            }
+            _ => Answer::status(404, ""),
+        });
+        let cfg = config(
+            &stub.base,
+            &[
+                "--verify-reboot",
+                "--reboot-settle-secs",
+                settle_secs,
+                "--poll-interval-secs",
+                "1",
+                "--reboot-wait-secs",
+                "5",
+            ],
+        );
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        let rebooted = rebooted_at.lock().unwrap().unwrap();
+        let polled = first_poll_at.lock().unwrap().unwrap();
+        polled - rebooted
+    }
+
+    #[test]
+    fn verification_starts_after_the_settle_period() {
+        assert!(reboot_to_first_poll("1") >= Duration::from_secs(1));
+        assert!(reboot_to_first_poll("0") < Duration::from_secs(1));
+        assert_eq!(
+            config("http://192.168.1.1", &[]).reboot_settle,
+            Duration::from_secs(5)
+        );
+    }
+
+    /// `--trace-http` 记录的某条日志里的头部。
+    fn traced_headers(logs: &[Value], message: &str) -> String {
+        logs.iter()
+            .find(|line| line["message"] == message)
+            .and_then(|line| line["headers"].as_str())
+            .unwrap_or_else(|| panic!("no `{message}` log line"))
+            .to_string()
+    }
+
+    #[test]
+    fn trace_http_dumps_request_and_response_headers() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--trace-http"]);
+        let (result, logs) = test_support::capture_logs(|| run_once(&client(), &cfg));
+        assert_eq!(result.unwrap(), Outcome::Done);
+
+        let login = traced_headers(&logs, "login request");
+        for expected in [
+            "content-type: application/x-www-form-urlencoded",
+            &format!("origin: {}", stub.base),
+            &format!("user-agent: {DEFAULT_USER_AGENT}"),
+            "upgrade-insecure-requests: 1",
+            &format!("referer: {}/", stub.base),
+        ] {
+            assert!(login.contains(expected), "{expected:?} missing in {login}");
+        }
+        let response = traced_headers(&logs, "login response");
+        assert!(response.contains("set-cookie: ***"), "{response}");
+        assert!(!response.contains("SID=abc"), "{response}");
+
+        let reboot = traced_headers(&logs, "reboot request");
+        for expected in [
+            "x-requested-with: XMLHttpRequest",
+            "accept: application/json, text/javascript, */*; q=0.01",
+            "content-type: application/x-www-form-urlencoded; charset=UTF-8",
+        ] {
+            assert!(
+                reboot.contains(expected),
+                "{expected:?} missing in {reboot}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_header_dump_without_trace_http() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &[]);
+        let (result, logs) = test_support::capture_logs(|| run_once(&client(), &cfg));
+        assert_eq!(result.unwrap(), Outcome::Done);
+        assert!(logs.iter().all(|line| line.get("headers").is_none()));
+    }
+
+    #[test]
+    fn connect_timeout_does_not_cut_short_a_slow_response() {
+        let stub = Stub::start(|_| {
+            thread::sleep(Duration::from_millis(1500));
+            Answer::ok("slow")
+        });
+        let slow = |extra: &[&str]| {
+            build_client(&test_support::args(extra), None)
+                .unwrap()
+                .get(&stub.base)
+                .send()
+        };
+        // 建连很快，读响应慢：只受 --timeout-secs 约束。
+        let resp = slow(&["--connect-timeout-secs", "1", "--timeout-secs", "5"]).unwrap();
+        assert_eq!(resp.text().unwrap(), "slow");
+        let err = slow(&["--connect-timeout-secs", "5", "--timeout-secs", "1"]).unwrap_err();
+        assert!(err.is_timeout(), "{err}");
+    }
+
+    #[test]
+    fn connect_timeout_bounds_an_unanswered_connect() {
+        // 不可路由的地址：SYN 没有回应（或立即报网络不可达），都不应等满 --timeout-secs。
+        let client = build_client(
+            &test_support::args(&["--connect-timeout-secs", "1", "--timeout-secs", "30"]),
+            None,
+        )
+        .unwrap();
+        let started = Instant::now();
+        let err = client.get("http://10.255.255.1:81/").send().unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5), "{err}");
+        assert!(err.is_connect() || err.is_timeout(), "{err}");
+    }
+
+    fn frashnum_sent(page: &'static str, extra: &[&str]) -> Option<String> {
+        let stub = Stub::start(move |hit| match hit.method.as_str() {
+            "GET" => Answer::ok(page),
+            _ => Answer::ok("welcome"),
+        });
+        login(&client(), &config(&stub.base, extra)).unwrap();
+        let post = stub
+            .hits()
+            .into_iter()
+            .find(|h| h.method == "POST")
+            .unwrap();
+        post.field("frashnum")
+    }
+
+    #[test]
+    fn auto_frashnum_uses_the_scraped_value_or_falls_back() {
+        let page = r#"<input type="hidden" name="frashnum" value="1718">"#;
+        assert_eq!(
+            frashnum_sent(page, &["--auto-frashnum"]).as_deref(),
+            Some("1718")
+        );
+        assert_eq!(
+            frashnum_sent(LOGIN_PAGE, &["--auto-frashnum", "--frashnum", "42"]).as_deref(),
+            Some("42")
+        );
+        // 未开启 --auto-frashnum 时沿用 --frashnum（默认为空）。
+        assert_eq!(frashnum_sent(page, &[]).as_deref(), Some(""));
+    }
+
+    const MAIN_PAGE: &str = "/common_page/main.lp";
+
+    #[test]
+    fn scraped_csrf_token_is_sent_with_the_reboot() {
+        let stub = gateway(&[(
+            MAIN_PAGE,
+            r#"<html><head><meta name="csrf-token" content="t0k3n"></head></html>"#,
+        )]);
+        let cfg = config(&stub.base, &["--csrf-header-name", "X-CSRF-Token"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(MAIN_PAGE).len(), 1);
+        let reboot = &stub.hits_to(REBOOT_PATH)[0];
+        assert_eq!(reboot.header("X-CSRF-Token"), Some("t0k3n"));
+    }
+
+    #[test]
+    fn missing_csrf_token_is_only_fatal_when_required() {
+        let stub = gateway(&[(MAIN_PAGE, "<html>no token here</html>")]);
+        let cfg = config(&stub.base, &["--csrf-header-name", "X-CSRF-Token"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(REBOOT_PATH)[0].header("X-CSRF-Token"), None);
+
+        let stub = gateway(&[(MAIN_PAGE, "<html>no token here</html>")]);
+        let cfg = config(
+            &stub.base,
+            &[
+                "--csrf-header-name",
+                "X-CSRF-Token",
+                "--require-csrf",
+                "--max-retries",
+                "0",
+            ],
+        );
+        let err = run_once(&client(), &cfg).unwrap_err();
+        assert_eq!(err.downcast_ref::<Failure>(), Some(&Failure::Reboot));
+        assert!(format!("{err:#}").contains("--require-csrf"), "{err:#}");
+        assert!(stub.hits_to(REBOOT_PATH).is_empty());
+    }
+
+    #[test]
+    fn no_csrf_page_is_loaded_without_the_flag() {
+        let stub = gateway(&[]);
+        assert_eq!(
+            run_once(&client(), &config(&stub.base, &[])).unwrap(),
+            Outcome::Done
+        );
+        assert!(stub.hits_to(MAIN_PAGE).is_empty());
+    }
+
+    /// 按给定 --reboot-body-mode 参数运行一次，返回收到的重启请求。
+    fn reboot_request(extra: &[&str]) -> test_support::Hit {
+        let stub = gateway(&[]);
+        assert_eq!(
+            run_once(&client(), &config(&stub.base, extra)).unwrap(),
+            Outcome::Done
+        );
+        let mut reboots = stub.hits_to(REBOOT_PATH);
+        assert_eq!(reboots.len(), 1);
+        reboots.remove(0)
+    }
+
+    #[test]
+    fn default_body_mode_wraps_the_payload_in_json_cfg() {
+        let hit = reboot_request(&[]);
+        assert_eq!(
+            hit.header("Content-Type"),
+            Some("application/x-www-form-urlencoded; charset=UTF-8")
+        );
+        assert_eq!(
+            hit.field("jsonCfg"),
+            Some(command_template("HG_COMMAND_REBOOT"))
+        );
+        let explicit = reboot_request(&["--reboot-body-mode", "form-jsonCfg"]);
+        assert_eq!(explicit.body, hit.body);
+    }
+
+    #[test]
+    fn raw_json_mode_sends_the_payload_as_the_body() {
+        let hit = reboot_request(&["--reboot-body-mode", "raw-json"]);
+        assert_eq!(hit.header("Content-Type"), Some("application/json"));
+        assert_eq!(hit.body, command_template("HG_COMMAND_REBOOT"));
+    }
+
+    #[test]
+    fn form_custom_mode_uses_the_named_field() {
+        let hit = reboot_request(&[
+            "--reboot-body-mode",
+            "form-custom",
+            "--reboot-field-name",
+            "cfgData",
+        ]);
+        assert_eq!(
+            hit.header("Content-Type"),
+            Some("application/x-www-form-urlencoded; charset=UTF-8")
+        );
+        assert_eq!(hit.field("jsonCfg"), None);
+        assert_eq!(
+            hit.field("cfgData"),
+            Some(command_template("HG_COMMAND_REBOOT"))
+        );
+
+        for extra in [
+            &["--reboot-body-mode", "form-custom"][..],
+            &[
+                "--reboot-body-mode",
+                "form-custom",
+                "--reboot-field-name",
+                "",
+            ],
+        ] {
+            let err = build_config("test".into(), &test_support::args(extra))
+                .err()
+                .unwrap();
+            assert_eq!(
+                err.to_string(),
+                "--reboot-body-mode form-custom requires --reboot-field-name"
+            );
+        }
+    }
+
+    #[test]
+    fn logs_out_after_the_reboot() {
+        let stub = gateway(&[("/logout.lp", "")]);
+        let cfg = config(
+            &stub.base,
+            &[
+                "--logout-path",
+                "/logout.lp",
+                "--field-action",
+                "Act",
+                "--header",
+                "X-Requested-With: XMLHttpRequest",
+            ],
+        );
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+
+        let hits = stub.hits();
+        let last = hits.last().unwrap();
+        assert_eq!((last.method.as_str(), last.path()), ("POST", "/logout.lp"));
+        assert_eq!(last.field("Act").as_deref(), Some("logout"));
+        assert_eq!(last.field("action"), None);
+        assert_eq!(last.header("Cookie"), Some("SID=abc"));
+        assert_eq!(last.header("X-Requested-With"), Some("XMLHttpRequest"));
+    }
+
+    #[test]
+    fn no_logout_without_a_session_cookie() {
+        let stub = Stub::start(|hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::ok("welcome"),
+            ("POST", REBOOT_PATH) => Answer::ok(r#"{"IF_ERRORID":0}"#),
+            _ => Answer::ok(""),
+        });
+        let cfg = config(&stub.base, &["--logout-path", "/logout.lp"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to(REBOOT_PATH).len(), 1);
+        assert!(stub.hits_to("/logout.lp").is_empty());
+    }
+
+    #[test]
+    fn no_logout_after_a_failed_login() {
+        let stub = Stub::start(|hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", "/") => Answer::ok(LOGIN_PAGE),
+            ("POST", "/") => Answer::status(500, "error"),
+            _ => Answer::ok(""),
+        });
+        let cfg = config(
+            &stub.base,
+            &["--logout-path", "/logout.lp", "--max-retries", "0"],
+        );
+        assert!(run_once(&client(), &cfg).is_err());
+        assert!(stub.hits_to("/logout.lp").is_empty());
+    }
+
+    #[test]
+    fn failed_logout_does_not_fail_the_run() {
+        let stub = gateway(&[]);
+        let cfg = config(&stub.base, &["--logout-path", "/logout.lp"]);
+        assert_eq!(run_once(&client(), &cfg).unwrap(), Outcome::Done);
+        assert_eq!(stub.hits_to("/logout.lp").len(), 1);
+    }
+
+    #[test]
+    fn each_router_entry_gets_its_own_cookie_file() {
+        let base = test_support::args(&["--cookie-file", "/tmp/cookies.json"]);
+        let entries = ["http://10.0.0.1", "http://10.0.0.2"]
+            .map(|host| RouterEntry {
+                host: Some(host.into()),
+                ..RouterEntry::default()
+            })
+            .into();
+        let routers = router_args(&base, entries);
+        assert_eq!(routers.len(), 2);
+        assert_eq!(routers[1].0, "http://10.0.0.2");
+        assert_eq!(routers[1].1.host, "http://10.0.0.2");
+        assert_eq!(
+            routers[1].1.cookie_file.as_deref(),
+            Some(std::path::Path::new("/tmp/cookies.json.http___10_0_0_2"))
+        );
+    }
+
+    #[test]
+    fn without_router_entries_the_host_is_the_only_router() {
+        let base = test_support::args(&["--host", "http://10.0.0.9"]);
+        let routers = router_args(&base, Vec::new());
+        assert_eq!(routers.len(), 1);
+        assert_eq!(routers[0].0, "http://10.0.0.9");
+    }
+
+    #[test]
+    fn https_host_builds_https_urls() {
+        let cfg = config("https://192.168.1.1", &["--insecure-tls"]);
+        assert_eq!(cfg.login_url.as_str(), "https://192.168.1.1/");
+        assert_eq!(
+            cfg.reboot_url.as_str(),
+            "https://192.168.1.1/common_page/gatewayManage.lua"
+        );
+        assert!(build_client(&test_support::args(&["--insecure-tls"]), None).is_ok());
+    }
+
+    #[test]
+    fn unreadable_ca_cert_names_the_file() {
+        let path = test_support::temp_path("missing-ca.pem");
+        let a = test_support::args(&["--ca-cert", path.to_str().unwrap()]);
+        let err = build_client(&a, None).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("reading CA certificate"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn invalid_ca_cert_is_rejected() {
+        let path = test_support::temp_path("invalid-ca.pem");
+        fs::write(
+            &path,
+            "-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        let a = test_support::args(&["--ca-cert", path.to_str().unwrap()]);
+        let result = build_client(&a, None);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn requests_go_through_proxy() {
+        let proxy = Stub::start(|_| Answer::ok("via proxy"));
+        let a = test_support::args(&["--proxy", &proxy.base]);
+        let body = build_client(&a, None)
+            .unwrap()
+            .get("http://192.0.2.1/common_page/main.lp")
+            .send()
+            .unwrap()
+            .text()
+            .unwrap();
+        assert_eq!(body, "via proxy");
+        assert_eq!(proxy.hits()[0].url, "http://192.0.2.1/common_page/main.lp");
+    }
+
+    #[test]
+    fn invalid_proxy_is_rejected() {
+        let a = test_support::args(&["--proxy", "not a url"]);
+        let err = build_client(&a, None).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("invalid --proxy URL"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn proxy_and_no_proxy_conflict() {
+        let argv = [
+            "tianyi-auto",
+            "--proxy",
+            "socks5://127.0.0.1:1080",
+            "--no-proxy",
+        ];
+        assert!(Args::try_parse_from(argv).is_err());
+    }
+
+    fn origin(url: &str) -> String {
+        origin_of(&Url::parse(url).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn origin_drops_path_and_trailing_slash() {
+        assert_eq!(
+            origin("http://192.168.1.1/common_page/main.lp?x=1"),
+            "http://192.168.1.1"
+        );
+        assert_eq!(origin("http://192.168.1.1"), "http://192.168.1.1");
+    }
+
+    #[test]
+    fn origin_keeps_non_default_port_only() {
+        assert_eq!(
+            origin("http://192.168.1.1:8080/"),
+            "http://192.168.1.1:8080"
+        );
+        assert_eq!(origin("http://192.168.1.1:80/"), "http://192.168.1.1");
+        assert_eq!(origin("https://router.lan/"), "https://router.lan");
+        assert_eq!(origin("https://router.lan:443/x"), "https://router.lan");
+        assert_eq!(
+            origin("https://router.lan:8443/x"),
+            "https://router.lan:8443"
+        );
+    }
+
+    #[test]
+    fn origin_brackets_ipv6() {
+        assert_eq!(
+            origin("http://[2001:db8::1]:8080/x"),
+            "http://[2001:db8::1]:8080"
+        );
+    }
+
+    #[test]
+    fn ipv6_zone_ids_are_rejected() {
+        let err = parse_host("http://[fe80::1%eth0]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"IPv6 zone ids are not supported in "http://[fe80::1%eth0]"; use a global or unique-local address instead"#
+        );
+        assert!(parse_host("http://[fe80::1%25eth0]:8080/").is_err());
+        assert!(parse_host("http://[fe80::1]").is_ok());
+    }
+
+    #[test]
+    fn global_ipv6_with_port_builds_urls() {
+        let base = parse_host("http://[2001:db8::1]:8080").unwrap();
+        let login = build_url(&base, "/").unwrap();
+        assert_eq!(login.as_str(), "http://[2001:db8::1]:8080/");
+        let reboot = build_url(&base, "/common_page/gatewayManage.lua").unwrap();
+        assert_eq!(
+            reboot.as_str(),
+            "http://[2001:db8::1]:8080/common_page/gatewayManage.lua"
+        );
+        assert_eq!(origin_of(&reboot).unwrap(), "http://[2001:db8::1]:8080");
+
+        let cfg = config("http://[2001:db8::1]:8080", &[]);
+        assert_eq!(cfg.login_url.as_str(), "http://[2001:db8::1]:8080/");
+    }
+
+    #[test]
+    fn host_without_authority_is_rejected() {
+        assert!(parse_host("192.168.1.1").is_err());
+        assert_eq!(
+            parse_host("data:text/plain,x").unwrap_err().to_string(),
+            r#""data:text/plain,x" has no host"#
+        );
+    }
+
+    #[test]
+    fn origin_of_opaque_url_is_an_error() {
+        assert!(origin_of(&Url::parse("data:text/plain,hi").unwrap()).is_err());
+    }
+
+    #[test]
+    fn digest_challenge_is_detected_case_insensitively() {
+        assert!(is_digest_challenge(r#"Digest realm="router", nonce="abc""#));
+        assert!(is_digest_challenge(r#"  digest realm="router""#));
+        assert!(!is_digest_challenge(r#"Basic realm="router""#));
+        assert!(!is_digest_challenge("Dig"));
     }
 }
@@ -0,0 +1,160 @@
+use crate::Outcome;
+use crate::http_server::Reply;
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tiny_http::Method;
+
+/// 单台路由器的运行统计。
+#[derive(Debug, Default, Clone)]
+pub struct RouterStats {
+    pub successes: u64,
+    pub failures: u64,
+    /// 按条件跳过的运行，不计入成功
+    pub skipped: u64,
+    pub last_success_timestamp: Option<i64>,
+    pub last_run_duration: Duration,
+}
+
+/// 与调度循环共享的指标，`/metrics` 以 Prometheus 文本格式输出。
+#[derive(Debug, Default)]
+pub struct Metrics {
+    routers: Mutex<BTreeMap<String, RouterStats>>,
+}
+
+impl Metrics {
+    pub fn record(&self, router: &str, result: &anyhow::Result<Outcome>, duration: Duration) {
+        let mut routers = self.routers.lock().expect("metrics lock poisoned");
+        let stats = routers.entry(router.to_string()).or_default();
+        match result {
+            Ok(Outcome::Done) => {
+                stats.successes += 1;
+                stats.last_success_timestamp = Some(Local::now().timestamp());
+            }
+            Ok(Outcome::Skipped(_)) => stats.skipped += 1,
+            Err(_) => stats.failures += 1,
+        }
+        stats.last_run_duration = duration;
+    }
+
+    pub fn render(&self) -> String {
+        render(&self.routers.lock().expect("metrics lock poisoned"))
+    }
+
+    pub fn handle(&self, method: &Method, url: &str) -> Reply {
+        match (method, url) {
+            (Method::Get, "/metrics") => Reply {
+                status: 200,
+                content_type: "text/plain; version=0.0.4; charset=utf-8",
+                body: self.render(),
+            },
+            _ => Reply::not_found(),
+        }
+    }
+}
+
+pub fn render(routers: &BTreeMap<String, RouterStats>) -> String {
+    let mut out = String::new();
+    metric(
+        &mut out,
+        "tianyi_reboot_success_total",
+        "counter",
+        "Successful runs.",
+        routers.iter().map(|(r, s)| (r, s.successes.to_string())),
+    );
+    metric(
+        &mut out,
+        "tianyi_reboot_failure_total",
+        "counter",
+        "Failed runs.",
+        routers.iter().map(|(r, s)| (r, s.failures.to_string())),
+    );
+    metric(
+        &mut out,
+        "tianyi_reboot_skipped_total",
+        "counter",
+        "Runs skipped by --min-uptime-hours, --skip-if-clients-above or --min-reboot-interval.",
+        routers.iter().map(|(r, s)| (r, s.skipped.to_string())),
+    );
+    metric(
+        &mut out,
+        "tianyi_reboot_last_success_timestamp_seconds",
+        "gauge",
+        "Unix time of the last successful run.",
+        routers
+            .iter()
+            .filter_map(|(r, s)| s.last_success_timestamp.map(|ts| (r, ts.to_string()))),
+    );
+    metric(
+        &mut out,
+        "tianyi_reboot_last_run_duration_seconds",
+        "gauge",
+        "Duration of the last run.",
+        routers
+            .iter()
+            .map(|(r, s)| (r, format!("{:.3}", s.last_run_duration.as_secs_f64()))),
+    );
+    out
+}
+
+fn metric<'a>(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl Iterator<Item = (&'a String, String)>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (router, value) in samples {
+        let _ = writeln!(out, "{name}{{router=\"{}\"}} {value}", escape_label(router));
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn counts_runs_per_router() {
+        let metrics = Metrics::default();
+        metrics.record("home", &Ok(Outcome::Done), Duration::from_millis(1500));
+        metrics.record(
+            "home",
+            &Err(anyhow!("login rejected")),
+            Duration::from_secs(2),
+        );
+        metrics.record("office", &Ok(Outcome::Done), Duration::from_secs(1));
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE tianyi_reboot_success_total counter\n"));
+        assert!(text.contains("tianyi_reboot_success_total{router=\"home\"} 1\n"));
+        assert!(text.contains("tianyi_reboot_failure_total{router=\"home\"} 1\n"));
+        assert!(text.contains("tianyi_reboot_failure_total{router=\"office\"} 0\n"));
+        assert!(text.contains("tianyi_reboot_last_run_duration_seconds{router=\"home\"} 2.000\n"));
+        assert!(text.contains("tianyi_reboot_last_success_timestamp_seconds{router=\"office\"} "));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn serves_only_get_metrics() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.handle(&Method::Get, "/metrics").status, 200);
+        assert_eq!(metrics.handle(&Method::Post, "/metrics").status, 404);
+        assert_eq!(metrics.handle(&Method::Get, "/").status, 404);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{Args, Config, Outcome};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+pub use email::SmtpTls;
+
+mod discord;
+mod email;
+mod gotify;
+mod matrix;
+mod mqtt;
+mod ntfy;
+mod pushover;
+mod slack;
+mod telegram;
+mod webhook;
+
+/// 失败原因写进聊天类消息时的最大长度（字符）。
+const MAX_ERROR_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Success,
+    Failure,
+    Skipped,
+}
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Success => "reboot_success",
+            EventKind::Failure => "reboot_failure",
+            EventKind::Skipped => "reboot_skipped",
+        }
+    }
+}
+
+/// 一次运行（单台路由器）的结果。
+#[derive(Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    pub router: String,
+    pub host: String,
+    pub timestamp: DateTime<Local>,
+    pub error: Option<String>,
+    /// 跳过原因（仅 `Skipped`）
+    pub reason: Option<String>,
+}
+
+impl Event {
+    pub fn from_result(cfg: &Config, result: &Result<Outcome>) -> Self {
+        let (kind, reason) = match result {
+            Ok(Outcome::Done) => (EventKind::Success, None),
+            Ok(Outcome::Skipped(reason)) => (EventKind::Skipped, Some(reason.clone())),
+            Err(_) => (EventKind::Failure, None),
+        };
+        Event {
+            kind,
+            router: cfg.name.clone(),
+            host: cfg.login_url.host_str().unwrap_or_default().to_string(),
+            timestamp: Local::now(),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+            reason,
+        }
+    }
+
+    /// 聊天类后端（Telegram 等）使用的纯文本消息。
+    pub fn message(&self) -> String {
+        let mut text = format!(
+            "[tianyi-auto] {} on {} ({}) at {}",
+            self.kind.name(),
+            self.router,
+            self.host,
+            self.timestamp.format("%Y-%m-%d %H:%M:%S %:z"),
+        );
+        if let Some(reason) = &self.reason {
+            text.push_str("\nReason: ");
+            text.push_str(reason);
+        }
+        if let Some(err) = &self.error {
+            text.push_str("\nError: ");
+            text.push_str(&truncate(err, MAX_ERROR_CHARS));
+        }
+        text
+    }
+}
+
+/// 按字符截断，超出部分用省略号表示。
+pub fn truncate(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(max_chars.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// 一个通知渠道。
+trait Backend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn send(&self, client: &Client, event: &Event) -> Result<()>;
+}
+
+/// 通知设置；所有后端共用一个 HTTP 客户端（遵循 `--timeout-secs`）。
+pub struct Notifier {
+    client: Client,
+    notify_on: Vec<EventKind>,
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl Notifier {
+    pub fn from_args(args: &Args) -> Result<Self> {
+        let mut backends: Vec<Box<dyn Backend>> = Vec::new();
+        if let Some(url) = &args.webhook_url {
+            backends.push(Box::new(webhook::Webhook::new(url)?));
+        }
+        if let Some(backend) = telegram::Telegram::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+        if let Some(url) = &args.discord_webhook_url {
+            backends.push(Box::new(discord::Discord::new(url)?));
+        }
+        if let Some(url) = &args.slack_webhook_url {
+            backends.push(Box::new(slack::Slack::new(url)?));
+        }
+        if let Some(backend) = ntfy::Ntfy::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+        if let Some(backend) = email::Email::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+        if let Some(backend) = gotify::Gotify::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+        if let Some(backend) = pushover::Pushover::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+        if let Some(backend) = matrix::Matrix::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+        if let Some(backend) = mqtt::Mqtt::from_args(args)? {
+            backends.push(Box::new(backend));
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(args.timeout_secs))
+            .build()
+            .context("building notification HTTP client")?;
+        Ok(Notifier {
+            client,
+            notify_on: args.notify_on.clone(),
+            backends,
+        })
+    }
+
+    /// 只检查通知设置、不建立任何连接（`validate` 子命令）。
+    pub fn validate(args: &Args) -> Result<()> {
+        if let Some(broker) = &args.mqtt_broker {
+            mqtt::parse_broker(broker)?;
+        }
+        // MQTT 后端构造时就会开始连接 broker，这里跳过。
+        let mut offline = args.clone();
+        offline.mqtt_broker = None;
+        Notifier::from_args(&offline).map(drop)
+    }
+
+    /// 发送通知；投递失败只记录日志，绝不影响调度循环。
+    pub fn notify(&self, event: &Event) {
+        if !self.notify_on.contains(&event.kind) {
+            debug!(
+                event = event.kind.name(),
+                "Notification filtered by --notify-on"
+            );
+            return;
+        }
+        for backend in &self.backends {
+            match backend.send(&self.client, event) {
+                Ok(()) => debug!(backend = backend.name(), "Notification delivered"),
+                Err(e) => warn!(backend = backend.name(), "Notification failed: {e:#}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Answer, Stub, args, event};
+    use serde_json::Value;
+
+    #[test]
+    fn webhook_receives_failure_event() {
+        let stub = Stub::start(|_| Answer::ok(""));
+        let url = format!("{}/hook", stub.base);
+        let notifier = Notifier::from_args(&args(&["--webhook-url", &url])).unwrap();
+        notifier.notify(&event(Err(anyhow::anyhow!("login rejected"))));
+
+        let hits = stub.hits_to("/hook");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].method, "POST");
+        let body: Value = serde_json::from_str(&hits[0].body).unwrap();
+        assert_eq!(body["event"], "reboot_failure");
+        assert_eq!(body["router"], "test");
+        assert_eq!(body["error"], "login rejected");
+    }
+
+    #[test]
+    fn notify_on_filters_events() {
+        let stub = Stub::start(|_| Answer::ok(""));
+        let notifier = Notifier::from_args(&args(&[
+            "--webhook-url",
+            &stub.base,
+            "--notify-on",
+            "failure",
+        ]))
+        .unwrap();
+        notifier.notify(&event(Ok(Outcome::Done)));
+        assert!(stub.hits().is_empty());
+    }
+
+    #[test]
+    fn webhook_error_status_does_not_panic() {
+        let stub = Stub::start(|_| Answer::status(500, "boom"));
+        let notifier = Notifier::from_args(&args(&["--webhook-url", &stub.base])).unwrap();
+        notifier.notify(&event(Ok(Outcome::Done)));
+        assert_eq!(stub.hits().len(), 1);
+    }
+
+    #[test]
+    fn invalid_webhook_url_is_rejected() {
+        let err = Notifier::from_args(&args(&["--webhook-url", "not a url"]))
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("--webhook-url"), "{err:#}");
+    }
+}
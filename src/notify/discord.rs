@@ -0,0 +1,82 @@
+use super::{Backend, Event, truncate};
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use url::Url;
+
+/// Discord 消息 `content` 的长度上限（字符）。
+const MAX_CONTENT_CHARS: usize = 2000;
+
+/// `--discord-webhook-url`：Discord 频道的 webhook。
+pub struct Discord {
+    url: Url,
+}
+
+impl Discord {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Discord {
+            url: Url::parse(url).context("invalid --discord-webhook-url")?,
+        })
+    }
+}
+
+impl Backend for Discord {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        // webhook URL 里带有 token，错误信息中去掉 URL。
+        client
+            .post(self.url.clone())
+            .json(&payload(event))
+            .send()
+            .map_err(|e| anyhow::anyhow!("discord request failed: {}", e.without_url()))?
+            .error_for_status()
+            .map_err(|e| anyhow::anyhow!("discord returned error status: {}", e.without_url()))?;
+        Ok(())
+    }
+}
+
+fn payload(event: &Event) -> Value {
+    json!({ "content": truncate(&event.message(), MAX_CONTENT_CHARS) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::notify::Notifier;
+    use crate::test_support::{Answer, Stub, args, closed_port_url, event};
+
+    #[test]
+    fn payload_is_content_only() {
+        let body = payload(&event(Ok(Outcome::Done)));
+        let object = body.as_object().unwrap();
+        assert_eq!(object.len(), 1);
+        let content = body["content"].as_str().unwrap();
+        assert!(content.starts_with("[tianyi-auto] reboot_success on test (192.168.1.1)"));
+    }
+
+    #[test]
+    fn long_messages_are_truncated_to_the_discord_limit() {
+        let mut event = event(Ok(Outcome::Skipped(String::new())));
+        event.reason = Some("x".repeat(3000));
+        let content = payload(&event)["content"].as_str().unwrap().to_string();
+        assert_eq!(content.chars().count(), MAX_CONTENT_CHARS);
+        assert!(content.ends_with('…'));
+    }
+
+    #[test]
+    fn posts_to_the_webhook_and_survives_an_outage() {
+        let stub = Stub::start(|_| Answer::status(204, ""));
+        let url = format!("{}/api/webhooks/1/token", stub.base);
+        let notifier = Notifier::from_args(&args(&["--discord-webhook-url", &url])).unwrap();
+        notifier.notify(&event(Ok(Outcome::Done)));
+        assert_eq!(stub.hits_to("/api/webhooks/1/token").len(), 1);
+
+        let down =
+            Notifier::from_args(&args(&["--discord-webhook-url", &closed_port_url()])).unwrap();
+        down.notify(&event(Ok(Outcome::Done)));
+    }
+}
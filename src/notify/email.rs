@@ -0,0 +1,191 @@
+use super::{Backend, Event};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, MessageBuilder};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// 隐式 TLS（SMTPS）的标准端口。
+const SMTPS_PORT: u16 = 465;
+const SUBMISSION_PORT: u16 = 587;
+
+/// SMTP 连接的加密方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Upgrade a plain connection with STARTTLS (usually port 587)
+    Starttls,
+    /// TLS from the first byte (SMTPS, usually port 465)
+    Implicit,
+    /// No encryption (local relays only)
+    None,
+}
+
+/// `--smtp-host`：每次运行后发一封简短的邮件。
+pub struct Email {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl Email {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(host) = &args.smtp_host else {
+            return Ok(None);
+        };
+        let from = args
+            .email_from
+            .as_deref()
+            .context("--email-from is required with --smtp-host")?
+            .parse::<Mailbox>()
+            .context("invalid --email-from")?;
+        if args.email_to.is_empty() {
+            bail!("--email-to is required with --smtp-host");
+        }
+        let to = args
+            .email_to
+            .iter()
+            .map(|addr| {
+                addr.parse::<Mailbox>()
+                    .with_context(|| format!("invalid --email-to {addr:?}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // 未指定 --smtp-tls 时按端口推断：465 为隐式 TLS，其余走 STARTTLS。
+        let port = args.smtp_port.unwrap_or(match args.smtp_tls {
+            Some(SmtpTls::Implicit) => SMTPS_PORT,
+            _ => SUBMISSION_PORT,
+        });
+        let tls = args.smtp_tls.unwrap_or(if port == SMTPS_PORT {
+            SmtpTls::Implicit
+        } else {
+            SmtpTls::Starttls
+        });
+        let builder = match tls {
+            SmtpTls::Implicit => SmtpTransport::relay(host).context("invalid --smtp-host")?,
+            SmtpTls::Starttls => {
+                SmtpTransport::starttls_relay(host).context("invalid --smtp-host")?
+            }
+            SmtpTls::None => SmtpTransport::builder_dangerous(host),
+        };
+        let mut builder = builder
+            .port(port)
+            .timeout(Some(Duration::from_secs(args.timeout_secs)));
+        match (&args.smtp_user, &args.smtp_pass) {
+            (Some(user), Some(pass)) => {
+                builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+            }
+            (None, None) => {}
+            _ => bail!("--smtp-user and --smtp-pass must be set together"),
+        }
+
+        Ok(Some(Email {
+            transport: builder.build(),
+            from,
+            to,
+        }))
+    }
+
+    fn message(&self, event: &Event) -> Result<Message> {
+        let builder: MessageBuilder = Message::builder()
+            .from(self.from.clone())
+            .subject(format!(
+                "[tianyi-auto] {} on {}",
+                event.kind.name(),
+                event.router
+            ))
+            .header(ContentType::TEXT_PLAIN);
+        self.to
+            .iter()
+            .fold(builder, |b, to| b.to(to.clone()))
+            .body(event.message())
+            .context("building email")
+    }
+}
+
+impl Backend for Email {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn send(&self, _client: &Client, event: &Event) -> Result<()> {
+        self.transport
+            .send(&self.message(event)?)
+            .context("sending email")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{args, event};
+
+    fn email(extra: &[&str]) -> Result<Option<Email>> {
+        let mut argv = vec!["--smtp-host", "smtp.example.com"];
+        argv.extend_from_slice(extra);
+        Email::from_args(&args(&argv))
+    }
+
+    #[test]
+    fn builds_envelope_and_body_without_sending() {
+        let email = email(&[
+            "--email-from",
+            "Router <router@example.com>",
+            "--email-to",
+            "ops@example.com",
+            "--email-to",
+            "me@example.com",
+        ])
+        .unwrap()
+        .unwrap();
+        let message = email
+            .message(&event(Err(anyhow::anyhow!("reboot rejected"))))
+            .unwrap();
+
+        let envelope = message.envelope();
+        assert_eq!(envelope.from().unwrap().to_string(), "router@example.com");
+        let to: Vec<String> = envelope.to().iter().map(|a| a.to_string()).collect();
+        assert_eq!(to, ["ops@example.com", "me@example.com"]);
+
+        let text = String::from_utf8(message.formatted()).unwrap();
+        assert!(text.contains("Subject: [tianyi-auto] reboot_failure on test"));
+        assert!(text.contains("Error: reboot rejected"));
+    }
+
+    #[test]
+    fn sender_and_recipient_are_required() {
+        assert!(Email::from_args(&args(&[])).unwrap().is_none());
+        let err = |extra: &[&str]| format!("{:#}", email(extra).err().unwrap());
+        assert_eq!(err(&[]), "--email-from is required with --smtp-host");
+        assert_eq!(
+            err(&["--email-from", "a@example.com"]),
+            "--email-to is required with --smtp-host"
+        );
+        assert!(err(&["--email-from", "a@example.com", "--email-to", "nope"]).contains("nope"));
+    }
+
+    #[test]
+    fn credentials_must_come_in_pairs() {
+        let base = [
+            "--email-from",
+            "a@example.com",
+            "--email-to",
+            "b@example.com",
+        ];
+        let mut user_only = base.to_vec();
+        user_only.extend(["--smtp-user", "a"]);
+        assert_eq!(
+            email(&user_only).err().unwrap().to_string(),
+            "--smtp-user and --smtp-pass must be set together"
+        );
+        let mut both = base.to_vec();
+        both.extend(["--smtp-user", "a", "--smtp-pass", "p", "--smtp-tls", "none"]);
+        assert!(email(&both).unwrap().is_some());
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Backend, Event, EventKind};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use url::Url;
+
+/// Gotify 的优先级 0-10；客户端一般把 8 以上当作需要打扰的通知。
+const SUCCESS_PRIORITY: u8 = 5;
+const FAILURE_PRIORITY: u8 = 8;
+
+/// `--gotify-url` + `--gotify-token`：POST 到自建 Gotify 的 `/message`。
+pub struct Gotify {
+    url: Url,
+}
+
+impl Gotify {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let (url, token) = match (&args.gotify_url, &args.gotify_token) {
+            (None, None) => return Ok(None),
+            (Some(url), Some(token)) => (url, token),
+            _ => bail!("--gotify-url and --gotify-token must be set together"),
+        };
+        Ok(Some(Gotify {
+            url: message_url(url, token)?,
+        }))
+    }
+}
+
+impl Backend for Gotify {
+    fn name(&self) -> &'static str {
+        "gotify"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        // URL 里带着应用 token，错误信息中去掉 URL。
+        client
+            .post(self.url.clone())
+            .json(&payload(event))
+            .send()
+            .map_err(|e| anyhow::anyhow!("gotify request failed: {}", e.without_url()))?
+            .error_for_status()
+            .map_err(|e| anyhow::anyhow!("gotify returned error status: {}", e.without_url()))?;
+        Ok(())
+    }
+}
+
+/// `<gotify-url>/message?token=<token>`；服务部署在子路径下时保留原路径。
+fn message_url(base: &str, token: &str) -> Result<Url> {
+    let mut url = Url::parse(base).context("invalid --gotify-url")?;
+    let path = format!("{}/message", url.path().trim_end_matches('/'));
+    url.set_path(&path);
+    url.query_pairs_mut().clear().append_pair("token", token);
+    Ok(url)
+}
+
+fn payload(event: &Event) -> Value {
+    let priority = match event.kind {
+        EventKind::Success | EventKind::Skipped => SUCCESS_PRIORITY,
+        EventKind::Failure => FAILURE_PRIORITY,
+    };
+    json!({
+        "title": format!("{} on {}", event.kind.name(), event.router),
+        "message": event.message(),
+        "priority": priority,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::test_support::{Answer, Stub, args, client, event};
+
+    #[test]
+    fn message_url_keeps_subpath_and_sets_token() {
+        let url = message_url("https://push.example.com/gotify/?x=1", "AbC").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://push.example.com/gotify/message?token=AbC"
+        );
+    }
+
+    #[test]
+    fn posts_json_with_failure_priority() {
+        let stub = Stub::start(|_| Answer::ok("{}"));
+        let gotify = Gotify::from_args(&args(&[
+            "--gotify-url",
+            &stub.base,
+            "--gotify-token",
+            "AbC",
+        ]))
+        .unwrap()
+        .unwrap();
+        let failure = event(Err(anyhow::anyhow!("reboot rejected")));
+        gotify.send(&client(), &failure).unwrap();
+
+        let hits = stub.hits_to("/message");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field("token").as_deref(), Some("AbC"));
+        let body: Value = serde_json::from_str(&hits[0].body).unwrap();
+        assert_eq!(body["title"], "reboot_failure on test");
+        assert_eq!(body["message"], failure.message());
+        assert_eq!(body["priority"], 8);
+        assert_eq!(payload(&event(Ok(Outcome::Done)))["priority"], 5);
+    }
+
+    #[test]
+    fn url_and_token_must_be_set_together() {
+        assert!(Gotify::from_args(&args(&[])).unwrap().is_none());
+        assert!(Gotify::from_args(&args(&["--gotify-token", "AbC"])).is_err());
+    }
+
+    #[test]
+    fn error_does_not_leak_the_token() {
+        let stub = Stub::start(|_| Answer::status(401, ""));
+        let gotify = Gotify::from_args(&args(&[
+            "--gotify-url",
+            &stub.base,
+            "--gotify-token",
+            "AbC",
+        ]))
+        .unwrap()
+        .unwrap();
+        let err = gotify
+            .send(&client(), &event(Ok(Outcome::Done)))
+            .unwrap_err();
+        assert!(!format!("{err:#}").contains("AbC"), "{err:#}");
+    }
+}
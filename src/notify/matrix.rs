@@ -0,0 +1,185 @@
+use super::{Backend, Event};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use std::sync::atomic::{AtomicU64, Ordering};
+use url::Url;
+
+/// `--matrix-homeserver` + `--matrix-token` + `--matrix-room-id`：通过 client-server API 往房间发一条文本消息。
+pub struct Matrix {
+    homeserver: Url,
+    token: String,
+    room_id: String,
+    /// 事务 id 的序号；与进程启动时间拼接，保证同一 access token 下不重复
+    sent: AtomicU64,
+    started: i64,
+}
+
+impl Matrix {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let (homeserver, token, room_id) = match (
+            &args.matrix_homeserver,
+            &args.matrix_token,
+            &args.matrix_room_id,
+        ) {
+            (None, None, None) => return Ok(None),
+            (Some(homeserver), Some(token), Some(room_id)) => (homeserver, token, room_id),
+            _ => bail!(
+                "--matrix-homeserver, --matrix-token and --matrix-room-id must be set together"
+            ),
+        };
+        let homeserver = Url::parse(homeserver).context("invalid --matrix-homeserver")?;
+        if homeserver.cannot_be_a_base() {
+            bail!("invalid --matrix-homeserver: {homeserver} cannot be a base URL");
+        }
+        Ok(Some(Matrix {
+            homeserver,
+            token: token.clone(),
+            room_id: room_id.clone(),
+            sent: AtomicU64::new(0),
+            started: chrono::Local::now().timestamp_millis(),
+        }))
+    }
+
+    fn next_txn_id(&self) -> String {
+        let n = self.sent.fetch_add(1, Ordering::Relaxed);
+        format!("tianyi-auto-{}-{n}", self.started)
+    }
+}
+
+impl Backend for Matrix {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        client
+            .put(send_url(
+                &self.homeserver,
+                &self.room_id,
+                &self.next_txn_id(),
+            ))
+            .bearer_auth(&self.token)
+            .json(&payload(event))
+            .send()
+            .context("matrix request failed")?
+            .error_for_status()
+            .context("matrix returned error status")?;
+        Ok(())
+    }
+}
+
+/// `PUT /_matrix/client/v3/rooms/{roomId}/send/m.room.message/{txnId}`；房间 id 作为单个路径段编码。
+fn send_url(homeserver: &Url, room_id: &str, txn_id: &str) -> Url {
+    let mut url = homeserver.clone();
+    url.path_segments_mut()
+        .expect("checked in from_args")
+        .pop_if_empty()
+        .extend([
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            room_id,
+            "send",
+            "m.room.message",
+            txn_id,
+        ]);
+    url
+}
+
+fn payload(event: &Event) -> Value {
+    json!({
+        "msgtype": "m.text",
+        "body": event.message(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::test_support::{Answer, Stub, args, client, event};
+
+    fn matrix(homeserver: &str) -> Matrix {
+        Matrix::from_args(&args(&[
+            "--matrix-homeserver",
+            homeserver,
+            "--matrix-token",
+            "syt_tok",
+            "--matrix-room-id",
+            "!room:example.org",
+        ]))
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn send_url_keeps_subpath_and_ends_with_txn_id() {
+        let url = send_url(
+            &Url::parse("https://matrix.example.com/base/").unwrap(),
+            "!room:example.org",
+            "txn-1",
+        );
+        assert_eq!(
+            url.as_str(),
+            "https://matrix.example.com/base/_matrix/client/v3/rooms/!room:example.org/send/m.room.message/txn-1"
+        );
+    }
+
+    #[test]
+    fn puts_message_event_with_unique_txn_ids() {
+        let stub = Stub::start(|_| Answer::ok(r#"{"event_id":"$1"}"#));
+        let matrix = matrix(&stub.base);
+        let done = event(Ok(Outcome::Done));
+        matrix.send(&client(), &done).unwrap();
+        matrix.send(&client(), &done).unwrap();
+
+        let hits = stub.hits();
+        assert_eq!(hits.len(), 2);
+        let prefix = "/_matrix/client/v3/rooms/!room:example.org/send/m.room.message/";
+        let mut txn_ids = Vec::new();
+        for hit in &hits {
+            assert_eq!(hit.method, "PUT");
+            assert_eq!(hit.header("Authorization"), Some("Bearer syt_tok"));
+            let txn_id = hit.path().strip_prefix(prefix).unwrap();
+            assert!(txn_id.starts_with("tianyi-auto-"), "{txn_id}");
+            txn_ids.push(txn_id.to_string());
+            let body: Value = serde_json::from_str(&hit.body).unwrap();
+            assert_eq!(body["msgtype"], "m.text");
+            assert_eq!(body["body"], done.message());
+        }
+        assert_ne!(txn_ids[0], txn_ids[1]);
+    }
+
+    #[test]
+    fn all_three_flags_are_required() {
+        assert!(Matrix::from_args(&args(&[])).unwrap().is_none());
+        let err = Matrix::from_args(&args(&["--matrix-token", "syt_tok"]))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("must be set together"));
+        assert!(
+            Matrix::from_args(&args(&[
+                "--matrix-homeserver",
+                "mailto:ops@example.com",
+                "--matrix-token",
+                "syt_tok",
+                "--matrix-room-id",
+                "!room:example.org",
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn error_status_fails_the_send() {
+        let stub = Stub::start(|_| Answer::status(403, r#"{"errcode":"M_FORBIDDEN"}"#));
+        let err = matrix(&stub.base)
+            .send(&client(), &event(Ok(Outcome::Done)))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "matrix returned error status");
+        assert!(!format!("{err:#}").contains("syt_tok"));
+    }
+}
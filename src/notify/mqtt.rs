@@ -0,0 +1,201 @@
+use super::{Backend, Event, EventKind};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+use rumqttc::{Client as MqttClient, MqttOptions, Packet, QoS};
+use serde_json::{Value, json};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
+
+const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// 与 broker 断开后重新连接前的等待时间。
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// 发布后等待 broker 确认（PUBACK）的最长时间。
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `--mqtt-broker`：向 `--mqtt-topic` 发布 retained 的 JSON 状态，Home Assistant 重连后即可看到最近一次结果。
+pub struct Mqtt {
+    client: MqttClient,
+    topic: String,
+    /// 事件循环线程每收到一个 PUBACK 发一次；锁同时保证同一时间只有一条消息在等确认
+    acks: Mutex<Receiver<()>>,
+}
+
+impl Mqtt {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(broker) = &args.mqtt_broker else {
+            return Ok(None);
+        };
+        let (host, port) = parse_broker(broker)?;
+        let mut options =
+            MqttOptions::new(format!("tianyi-auto-{}", std::process::id()), host, port);
+        options.set_keep_alive(KEEP_ALIVE);
+        match (&args.mqtt_user, &args.mqtt_pass) {
+            (Some(user), Some(pass)) => {
+                options.set_credentials(user.clone(), pass.clone());
+            }
+            (None, None) => {}
+            _ => bail!("--mqtt-user and --mqtt-pass must be set together"),
+        }
+
+        let (client, mut connection) = MqttClient::new(options, 10);
+        let (ack_tx, acks) = mpsc::channel();
+        // 事件循环必须持续轮询才能真正发出消息；出错（broker 断开）时稍后自动重连，不影响调度。
+        thread::Builder::new()
+            .name("mqtt".into())
+            .spawn(move || {
+                for notification in connection.iter() {
+                    match notification {
+                        Ok(rumqttc::Event::Incoming(Packet::PubAck(ack))) => {
+                            debug!(?ack, "MQTT publish acknowledged");
+                            let _ = ack_tx.send(());
+                        }
+                        Ok(event) => debug!(?event, "MQTT event"),
+                        Err(e) => {
+                            warn!("MQTT connection error: {e}");
+                            thread::sleep(RECONNECT_DELAY);
+                        }
+                    }
+                }
+            })
+            .context("spawning MQTT thread")?;
+
+        Ok(Some(Mqtt {
+            client,
+            topic: args.mqtt_topic.clone(),
+            acks: Mutex::new(acks),
+        }))
+    }
+}
+
+impl Backend for Mqtt {
+    fn name(&self) -> &'static str {
+        "mqtt"
+    }
+
+    fn send(&self, _client: &Client, event: &Event) -> Result<()> {
+        let acks = self.acks.lock().expect("MQTT ack lock poisoned");
+        // 丢弃之前超时后才到达的确认。
+        while acks.try_recv().is_ok() {}
+        self.client
+            .try_publish(
+                &self.topic,
+                QoS::AtLeastOnce,
+                true,
+                payload(event).to_string(),
+            )
+            .context("queueing MQTT publish")?;
+        // try_publish 只是放进事件循环的队列；等到 broker 确认再返回，
+        // 否则 --once 或 --max-runs 结束时进程退出，消息还没发出去。
+        acks.recv_timeout(ACK_TIMEOUT).with_context(|| {
+            format!(
+                "MQTT broker did not acknowledge the publish within {}s",
+                ACK_TIMEOUT.as_secs()
+            )
+        })
+    }
+}
+
+/// 接受 `mqtt://host:port`、`tcp://host:port` 或 `host[:port]`。
+pub fn parse_broker(broker: &str) -> Result<(String, u16)> {
+    let with_scheme = if broker.contains("://") {
+        broker.to_string()
+    } else {
+        format!("mqtt://{broker}")
+    };
+    let url = Url::parse(&with_scheme).context("invalid --mqtt-broker")?;
+    if !matches!(url.scheme(), "mqtt" | "tcp") {
+        bail!("unsupported --mqtt-broker scheme {:?}", url.scheme());
+    }
+    let host = url
+        .host_str()
+        .context("--mqtt-broker has no host")?
+        .trim_matches(['[', ']'])
+        .to_string();
+    Ok((host, url.port().unwrap_or(DEFAULT_PORT)))
+}
+
+fn payload(event: &Event) -> Value {
+    let mut body = json!({
+        "event": event.kind.name(),
+        "result": match event.kind {
+            EventKind::Success => "success",
+            EventKind::Failure => "failure",
+            EventKind::Skipped => "skipped",
+        },
+        "router": event.router,
+        "host": event.host,
+        "timestamp": event.timestamp.to_rfc3339(),
+    });
+    if let Some(err) = &event.error {
+        body["error"] = json!(err);
+    }
+    if let Some(reason) = &event.reason {
+        body["reason"] = json!(reason);
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::test_support::{args, closed_port_url, event};
+
+    #[test]
+    fn payload_carries_event_result_and_host() {
+        let failure = event(Err(anyhow::anyhow!("login rejected")));
+        let body = payload(&failure);
+        assert_eq!(body["event"], "reboot_failure");
+        assert_eq!(body["result"], "failure");
+        assert_eq!(body["router"], "test");
+        assert_eq!(body["host"], "192.168.1.1");
+        assert_eq!(body["timestamp"], failure.timestamp.to_rfc3339());
+        assert_eq!(body["error"], "login rejected");
+        assert!(body.get("reason").is_none());
+
+        let skipped = payload(&event(Ok(Outcome::Skipped("uptime".into()))));
+        assert_eq!(skipped["result"], "skipped");
+        assert_eq!(skipped["reason"], "uptime");
+    }
+
+    #[test]
+    fn broker_addresses() {
+        let parse = |b: &str| parse_broker(b).unwrap();
+        assert_eq!(parse("broker.lan"), ("broker.lan".into(), 1883));
+        assert_eq!(parse("broker.lan:1884"), ("broker.lan".into(), 1884));
+        assert_eq!(parse("tcp://10.0.0.2:1885"), ("10.0.0.2".into(), 1885));
+        assert_eq!(parse("mqtt://[2001:db8::2]"), ("2001:db8::2".into(), 1883));
+        assert!(parse_broker("ws://broker.lan").is_err());
+    }
+
+    #[test]
+    fn publishes_to_the_configured_topic() {
+        let broker = closed_port_url().replace("http://", "tcp://");
+        let mqtt = Mqtt::from_args(&args(&[
+            "--mqtt-broker",
+            &broker,
+            "--mqtt-topic",
+            "home/router/status",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(mqtt.topic, "home/router/status");
+    }
+
+    #[test]
+    fn credentials_must_come_in_pairs() {
+        let err = Mqtt::from_args(&args(&["--mqtt-broker", "broker.lan", "--mqtt-user", "ha"]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--mqtt-user and --mqtt-pass must be set together"
+        );
+    }
+}
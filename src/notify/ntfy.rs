@@ -0,0 +1,113 @@
+use super::{Backend, Event, EventKind};
+use crate::Args;
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use url::Url;
+
+/// ntfy 的 `high` 优先级；失败事件至少以此发送。
+const FAILURE_PRIORITY: u8 = 4;
+
+/// `--ntfy-url`：向 ntfy 主题 POST 纯文本消息，标题/优先级/标签放在请求头里。
+pub struct Ntfy {
+    url: Url,
+    priority: u8,
+}
+
+impl Ntfy {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(url) = &args.ntfy_url else {
+            return Ok(None);
+        };
+        Ok(Some(Ntfy {
+            url: Url::parse(url).context("invalid --ntfy-url")?,
+            priority: args.ntfy_priority,
+        }))
+    }
+
+    fn priority(&self, event: &Event) -> u8 {
+        match event.kind {
+            EventKind::Success | EventKind::Skipped => self.priority,
+            EventKind::Failure => self.priority.max(FAILURE_PRIORITY),
+        }
+    }
+}
+
+impl Backend for Ntfy {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        // ntfy 会把已知的 tag 名显示为 emoji。
+        let tag = match event.kind {
+            EventKind::Success => "white_check_mark",
+            EventKind::Failure => "rotating_light",
+            EventKind::Skipped => "fast_forward",
+        };
+        client
+            .post(self.url.clone())
+            .header(
+                "Title",
+                format!("{} on {}", event.kind.name(), event.router),
+            )
+            .header("Priority", self.priority(event).to_string())
+            .header("Tags", tag)
+            .body(event.message())
+            .send()
+            .context("ntfy request failed")?
+            .error_for_status()
+            .context("ntfy returned error status")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::test_support::{Answer, Stub, args, client, event};
+
+    #[test]
+    fn posts_plain_text_with_title_priority_and_tags() {
+        let stub = Stub::start(|_| Answer::ok("{}"));
+        let url = format!("{}/router-alerts", stub.base);
+        let ntfy = Ntfy::from_args(&args(&["--ntfy-url", &url, "--ntfy-priority", "2"]))
+            .unwrap()
+            .unwrap();
+        let failure = event(Err(anyhow::anyhow!("reboot rejected")));
+        ntfy.send(&client(), &failure).unwrap();
+        ntfy.send(&client(), &event(Ok(Outcome::Done))).unwrap();
+
+        let hits = stub.hits_to("/router-alerts");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].header("Title"), Some("reboot_failure on test"));
+        assert_eq!(hits[0].header("Priority"), Some("4"));
+        assert_eq!(hits[0].header("Tags"), Some("rotating_light"));
+        assert_eq!(hits[0].body, failure.message());
+        assert_eq!(hits[1].header("Priority"), Some("2"));
+        assert_eq!(hits[1].header("Tags"), Some("white_check_mark"));
+    }
+
+    #[test]
+    fn failure_keeps_a_higher_configured_priority() {
+        let ntfy = Ntfy::from_args(&args(&[
+            "--ntfy-url",
+            "https://ntfy.sh/t",
+            "--ntfy-priority",
+            "5",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(ntfy.priority(&event(Err(anyhow::anyhow!("x")))), 5);
+    }
+
+    #[test]
+    fn delivery_error_is_reported_not_fatal() {
+        let stub = Stub::start(|_| Answer::status(500, ""));
+        let ntfy = Ntfy::from_args(&args(&["--ntfy-url", &stub.base]))
+            .unwrap()
+            .unwrap();
+        let err = ntfy.send(&client(), &event(Ok(Outcome::Done))).unwrap_err();
+        assert_eq!(err.to_string(), "ntfy returned error status");
+    }
+}
@@ -0,0 +1,117 @@
+use super::{Backend, Event, EventKind};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+
+const API_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// PushOver 的 `high` 优先级（绕过静默时段但不需要确认）。
+const FAILURE_PRIORITY: &str = "1";
+const SUCCESS_PRIORITY: &str = "0";
+
+/// `--pushover-token` + `--pushover-user`：通过 PushOver API 推送到手机。
+pub struct Pushover {
+    token: String,
+    user: String,
+}
+
+impl Pushover {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        match (&args.pushover_token, &args.pushover_user) {
+            (None, None) => Ok(None),
+            (Some(token), Some(user)) => Ok(Some(Pushover {
+                token: token.clone(),
+                user: user.clone(),
+            })),
+            _ => bail!("--pushover-token and --pushover-user must be set together"),
+        }
+    }
+
+    fn form<'a>(
+        &'a self,
+        event: &'a Event,
+        title: &'a str,
+        message: &'a str,
+    ) -> [(&'a str, &'a str); 5] {
+        let priority = match event.kind {
+            EventKind::Success | EventKind::Skipped => SUCCESS_PRIORITY,
+            EventKind::Failure => FAILURE_PRIORITY,
+        };
+        [
+            ("token", self.token.as_str()),
+            ("user", self.user.as_str()),
+            ("title", title),
+            ("message", message),
+            ("priority", priority),
+        ]
+    }
+}
+
+impl Backend for Pushover {
+    fn name(&self) -> &'static str {
+        "pushover"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        let title = format!("{} on {}", event.kind.name(), event.router);
+        let message = event.message();
+        client
+            .post(API_URL)
+            .form(&self.form(event, &title, &message))
+            .send()
+            .context("pushover request failed")?
+            .error_for_status()
+            .context("pushover returned error status")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::test_support::{args, event};
+
+    fn pushover() -> Pushover {
+        Pushover::from_args(&args(&[
+            "--pushover-token",
+            "app-token",
+            "--pushover-user",
+            "user-key",
+        ]))
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn form_fields_for_a_failure() {
+        let pushover = pushover();
+        let failure = event(Err(anyhow::anyhow!("reboot rejected")));
+        let message = failure.message();
+        let form = pushover.form(&failure, "reboot_failure on test", &message);
+        assert_eq!(
+            form,
+            [
+                ("token", "app-token"),
+                ("user", "user-key"),
+                ("title", "reboot_failure on test"),
+                ("message", message.as_str()),
+                ("priority", "1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn success_uses_normal_priority() {
+        let pushover = pushover();
+        let done = event(Ok(Outcome::Done));
+        let form = pushover.form(&done, "", "");
+        assert_eq!(form[4], ("priority", "0"));
+    }
+
+    #[test]
+    fn token_and_user_must_be_set_together() {
+        assert!(Pushover::from_args(&args(&[])).unwrap().is_none());
+        assert!(Pushover::from_args(&args(&["--pushover-user", "u"])).is_err());
+    }
+}
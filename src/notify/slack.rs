@@ -0,0 +1,75 @@
+use super::{Backend, Event};
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use url::Url;
+
+/// `--slack-webhook-url`：Slack incoming webhook。
+pub struct Slack {
+    url: Url,
+}
+
+impl Slack {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Slack {
+            url: Url::parse(url).context("invalid --slack-webhook-url")?,
+        })
+    }
+}
+
+impl Backend for Slack {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        // webhook URL 本身就是凭据，错误信息中去掉 URL。
+        client
+            .post(self.url.clone())
+            .json(&payload(event))
+            .send()
+            .map_err(|e| anyhow::anyhow!("slack request failed: {}", e.without_url()))?
+            .error_for_status()
+            .map_err(|e| anyhow::anyhow!("slack returned error status: {}", e.without_url()))?;
+        Ok(())
+    }
+}
+
+fn payload(event: &Event) -> Value {
+    json!({ "text": event.message() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::Notifier;
+    use crate::test_support::{Answer, Stub, args, event};
+
+    #[test]
+    fn payload_carries_router_result_and_timestamp() {
+        let event = event(Err(anyhow::anyhow!("reboot rejected")));
+        let body = payload(&event);
+        assert_eq!(body.as_object().unwrap().len(), 1);
+        let text = body["text"].as_str().unwrap();
+        assert!(text.contains("reboot_failure on test (192.168.1.1)"));
+        assert!(text.contains(&event.timestamp.format("%Y-%m-%d %H:%M:%S").to_string()));
+        assert!(text.ends_with("\nError: reboot rejected"));
+    }
+
+    #[test]
+    fn slack_and_discord_are_both_notified() {
+        let stub = Stub::start(|_| Answer::ok("ok"));
+        let slack = format!("{}/slack", stub.base);
+        let discord = format!("{}/discord", stub.base);
+        let notifier = Notifier::from_args(&args(&[
+            "--slack-webhook-url",
+            &slack,
+            "--discord-webhook-url",
+            &discord,
+        ]))
+        .unwrap();
+        notifier.notify(&event(Err(anyhow::anyhow!("reboot rejected"))));
+        assert_eq!(stub.hits_to("/slack").len(), 1);
+        assert_eq!(stub.hits_to("/discord").len(), 1);
+    }
+}
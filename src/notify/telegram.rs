@@ -0,0 +1,91 @@
+use super::{Backend, Event};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+
+const API_BASE: &str = "https://api.telegram.org";
+
+/// Telegram Bot API `sendMessage`。
+pub struct Telegram {
+    bot_token: String,
+    chat_id: String,
+}
+
+impl Telegram {
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        match (&args.telegram_bot_token, &args.telegram_chat_id) {
+            (Some(bot_token), Some(chat_id)) => Ok(Some(Telegram {
+                bot_token: bot_token.clone(),
+                chat_id: chat_id.clone(),
+            })),
+            (None, None) => Ok(None),
+            _ => bail!("--telegram-bot-token and --telegram-chat-id must be set together"),
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("{API_BASE}/bot{}/sendMessage", self.bot_token)
+    }
+
+    fn payload(&self, event: &Event) -> Value {
+        json!({
+            "chat_id": self.chat_id,
+            "text": event.message(),
+            "disable_web_page_preview": true,
+        })
+    }
+}
+
+impl Backend for Telegram {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        // 注意不要把带 bot token 的 URL 打进日志。
+        client
+            .post(self.url())
+            .json(&self.payload(event))
+            .send()
+            .map_err(|e| anyhow::anyhow!("telegram request failed: {}", e.without_url()))?
+            .error_for_status()
+            .map_err(|e| anyhow::anyhow!("telegram returned error status: {}", e.without_url()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{args, event};
+
+    #[test]
+    fn token_and_chat_id_must_be_set_together() {
+        assert!(Telegram::from_args(&args(&[])).unwrap().is_none());
+        assert!(Telegram::from_args(&args(&["--telegram-bot-token", "123:abc"])).is_err());
+        assert!(Telegram::from_args(&args(&["--telegram-chat-id", "42"])).is_err());
+    }
+
+    #[test]
+    fn sends_message_to_chat() {
+        let telegram = Telegram::from_args(&args(&[
+            "--telegram-bot-token",
+            "123:abc",
+            "--telegram-chat-id",
+            "42",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            telegram.url(),
+            "https://api.telegram.org/bot123:abc/sendMessage"
+        );
+
+        let payload = telegram.payload(&event(Err(anyhow::anyhow!("router unreachable"))));
+        assert_eq!(payload["chat_id"], "42");
+        let text = payload["text"].as_str().unwrap();
+        assert!(text.starts_with("[tianyi-auto] reboot_failure on test (192.168.1.1)"));
+        assert!(text.ends_with("\nError: router unreachable"));
+    }
+}
@@ -0,0 +1,65 @@
+use super::{Backend, Event};
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use url::Url;
+
+/// `--webhook-url`：POST 一个 JSON 事件。
+pub struct Webhook {
+    url: Url,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Webhook {
+            url: Url::parse(url).context("invalid --webhook-url")?,
+        })
+    }
+}
+
+impl Backend for Webhook {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn send(&self, client: &Client, event: &Event) -> Result<()> {
+        client
+            .post(self.url.clone())
+            .json(&payload(event))
+            .send()
+            .context("webhook request failed")?
+            .error_for_status()
+            .context("webhook returned error status")?;
+        Ok(())
+    }
+}
+
+fn payload(event: &Event) -> Value {
+    let mut body = json!({
+        "event": event.kind.name(),
+        "router": event.router,
+        "timestamp": event.timestamp.to_rfc3339(),
+    });
+    if let Some(err) = &event.error {
+        body["error"] = json!(err);
+    }
+    if let Some(reason) = &event.reason {
+        body["reason"] = json!(reason);
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use crate::test_support::event;
+
+    #[test]
+    fn success_payload_has_no_error() {
+        let body = payload(&event(Ok(Outcome::Done)));
+        assert_eq!(body["event"], "reboot_success");
+        assert!(body.get("error").is_none());
+        assert!(body["timestamp"].as_str().unwrap().contains('T'));
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use opentelemetry::KeyValue;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::TracerProvider;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// `--otlp-endpoint`：把 tracing 的 span（每台路由器一次运行，及其 login/reboot/wait_for_online 子 span）
+/// 通过 OTLP/HTTP 导出，例如 `http://collector:4318/v1/traces`。
+/// 使用同步导出器：运行频率很低，不值得为批量导出引入异步运行时。
+pub fn layer<S>(endpoint: &str) -> Result<OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .context("building OTLP exporter")?;
+    let provider = provider(exporter);
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    // 全局持有 provider，保证整个进程生命周期内导出器都可用。
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+fn provider(
+    exporter: impl opentelemetry_sdk::export::trace::SpanExporter + 'static,
+) -> TracerProvider {
+    TracerProvider::builder()
+        .with_simple_exporter(exporter)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            env!("CARGO_PKG_NAME"),
+        )]))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Answer, Stub};
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter as Export};
+    use std::future::{Future, ready};
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// 把导出的 span 收集到内存里。
+    #[derive(Debug, Clone, Default)]
+    struct Collected(Arc<Mutex<Vec<SpanData>>>);
+
+    impl Export for Collected {
+        fn export(
+            &mut self,
+            batch: Vec<SpanData>,
+        ) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(ready(Ok(())))
+        }
+    }
+
+    #[test]
+    fn run_exports_router_span_with_login_and_reboot_children() {
+        let stub = Stub::start(|hit| match (hit.method.as_str(), hit.path()) {
+            ("GET", _) => {
+                Answer::ok(r#"<form><input type="hidden" name="Frm_Logintoken" value="77"></form>"#)
+            }
+            ("POST", "/") => Answer::ok("welcome").header("Set-Cookie", "SID=abc; Path=/"),
+            _ => Answer::ok(r#"{"IF_ERRORID":0}"#),
+        });
+        let runner = test_support::runner(vec![test_support::router(&stub.base, &[])], false);
+        let collected = Collected::default();
+        let provider = provider(collected.clone());
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || runner.run_all()).unwrap();
+
+        let spans = collected.0.lock().unwrap();
+        let find = |name: &str| {
+            spans
+                .iter()
+                .find(|s| s.name == name)
+                .unwrap_or_else(|| panic!("no `{name}` span"))
+        };
+        let router = find("router");
+        let attribute = |span: &SpanData, key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.as_str().into_owned())
+        };
+        assert_eq!(attribute(router, "host").as_deref(), Some("127.0.0.1"));
+        assert!(attribute(router, "result").is_some());
+        for child in ["login", "reboot"] {
+            let span = find(child);
+            assert_eq!(span.parent_span_id, router.span_context.span_id());
+            assert_eq!(span.span_context.trace_id(), router.span_context.trace_id());
+        }
+        assert_eq!(
+            attribute(find("reboot"), "status_code").as_deref(),
+            Some("200")
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// 登录表单里 `Password` 字段的编码方式，不同固件版本要求不同。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PasswordEncoding {
+    /// Plaintext (default)
+    #[default]
+    None,
+    /// Base64 of the plaintext
+    Base64,
+    /// Lowercase hex SHA-256 of password + Frm_Logintoken
+    Sha256Token,
+}
+
+impl PasswordEncoding {
+    pub fn encode(self, password: &str, login_token: &str) -> String {
+        match self {
+            PasswordEncoding::None => password.to_string(),
+            PasswordEncoding::Base64 => STANDARD.encode(password),
+            PasswordEncoding::Sha256Token => {
+                let digest = Sha256::digest(format!("{password}{login_token}"));
+                digest.iter().map(|b| format!("{b:02x}")).collect()
+            }
+        }
+    }
+}
+
+/// 路由器密码：`Debug` 只输出 `***`，避免随 `Args`/`Config` 的调试输出或 panic 信息泄露。
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Secret(s.to_string()))
+    }
+}
+
+/// 从文件读取密码（`--password-file`）。
+pub fn read_file(path: &Path) -> Result<Secret> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("reading password file {}", path.display()))?;
+    non_empty(trim_newline(&text), "password file")
+}
+
+/// 从标准输入读取密码（`--password-stdin`），读到 EOF 为止。
+pub fn read_stdin() -> Result<Secret> {
+    let mut text = String::new();
+    io::stdin()
+        .read_to_string(&mut text)
+        .context("reading password from stdin")?;
+    non_empty(trim_newline(&text), "stdin")
+}
+
+/// `--keyring-service`：按 service + 用户名从系统密钥环读取密码；条目不存在时返回 `None`。
+#[cfg(feature = "keyring")]
+pub fn read_keyring(service: &str, username: &str) -> Result<Option<Secret>> {
+    let entry = keyring::Entry::new(service, username).context("opening keyring entry")?;
+    match entry.get_password() {
+        Ok(password) => non_empty(&password, "keyring").map(Some),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).context("reading password from keyring"),
+    }
+}
+
+/// `set-password` 子命令：把密码写入系统密钥环。
+#[cfg(feature = "keyring")]
+pub fn store_keyring(service: &str, username: &str, password: &Secret) -> Result<()> {
+    keyring::Entry::new(service, username)
+        .context("opening keyring entry")?
+        .set_password(password.expose())
+        .context("writing password to keyring")
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn read_keyring(_service: &str, _username: &str) -> Result<Option<Secret>> {
+    bail!("built without the `keyring` feature")
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store_keyring(_service: &str, _username: &str, _password: &Secret) -> Result<()> {
+    bail!("set-password requires building with `--features keyring`")
+}
+
+/// 只去掉末尾的一个换行（`\n` 或 `\r\n`），其余空白视为密码的一部分。
+fn trim_newline(s: &str) -> &str {
+    s.strip_suffix("\r\n")
+        .or_else(|| s.strip_suffix('\n'))
+        .unwrap_or(s)
+}
+
+fn non_empty(password: &str, source: &str) -> Result<Secret> {
+    if password.is_empty() {
+        bail!("password from {source} is empty");
+    }
+    Ok(Secret(password.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password_file(name: &str, contents: &str) -> Result<Secret> {
+        let path = crate::test_support::temp_path(name);
+        fs::write(&path, contents).unwrap();
+        let result = read_file(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn password_file_trims_one_trailing_newline() {
+        assert_eq!(
+            password_file("pw-lf", "s3cret\n").unwrap().expose(),
+            "s3cret"
+        );
+        assert_eq!(
+            password_file("pw-crlf", "s3cret\r\n").unwrap().expose(),
+            "s3cret"
+        );
+        assert_eq!(
+            password_file("pw-two", "s3cret\n\n").unwrap().expose(),
+            "s3cret\n"
+        );
+        assert_eq!(
+            password_file("pw-space", " s3cret ").unwrap().expose(),
+            " s3cret "
+        );
+    }
+
+    #[test]
+    fn empty_password_file_is_an_error() {
+        let err = password_file("pw-empty", "\n").unwrap_err();
+        assert_eq!(err.to_string(), "password from password file is empty");
+    }
+
+    #[test]
+    fn missing_password_file_names_the_path() {
+        let path = crate::test_support::temp_path("pw-missing");
+        let err = read_file(&path).unwrap_err();
+        assert!(
+            err.to_string().contains(&path.display().to_string()),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn secret_debug_is_masked() {
+        let secret: Secret = "s3cret".parse().unwrap();
+        assert_eq!(format!("{secret:?}"), "***");
+    }
+
+    #[test]
+    fn plaintext_is_sent_as_is() {
+        assert_eq!(PasswordEncoding::None.encode("admin", "5"), "admin");
+    }
+
+    #[test]
+    fn base64_encodes_utf8_bytes() {
+        assert_eq!(PasswordEncoding::Base64.encode("admin", "5"), "YWRtaW4=");
+        assert_eq!(PasswordEncoding::Base64.encode("密码", "5"), "5a+G56CB");
+    }
+
+    #[test]
+    fn sha256_hashes_password_followed_by_token() {
+        assert_eq!(
+            PasswordEncoding::Sha256Token.encode("admin", "5"),
+            "69b5406538c5c09580cecc0c7bafbf3f960267ffa7478e89d8352da814529cb7"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::Args;
+use anyhow::{Context, Result};
+use clap::{Command, CommandFactory};
+use regex::Regex;
+use reqwest::blocking::Client;
+use std::sync::LazyLock;
+use url::Url;
+
+/// 登录页里常见的型号写法，例如 `ZXHN F650`、`F7607P`；只用于日志，不参与匹配。
+static MODEL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:ZXHN\s*)?[FEH]\d{3,4}[A-Z]{0,3}(?:V\d)?\b").expect("valid regex")
+});
+
+/// 一个内置机型配置：登录页中出现 `markers` 任一文本即匹配；`None` 表示沿用当前值。
+pub struct Profile {
+    pub name: &'static str,
+    markers: &'static [&'static str],
+    login_path: Option<&'static str>,
+    reboot_path: Option<&'static str>,
+    reboot_referer: Option<&'static str>,
+    /// 重启请求体里的 CmdType
+    reboot_cmdtype: Option<&'static str>,
+    field_token: Option<&'static str>,
+    auto_token: bool,
+}
+
+/// 按顺序匹配，越具体的放在越前面。每一项都必须来自抓包或厂商文档，并注明出处；
+/// 标记文本要足够独特，不能是各家网页都会出现的路径片段。
+const PROFILES: &[Profile] = &[
+    // 出处：本工具最初的默认值，即作者对天翼网关 Web 管理界面的抓包——登录页 `/`、
+    // 重启接口 `/common_page/gatewayManage.lua`（Referer `/common_page/main.lp`）、
+    // CmdType `HG_COMMAND_REBOOT`，登录表单带每次会话不同的 `Frm_Logintoken`。
+    Profile {
+        name: "tianyi-gateway",
+        markers: &["天翼网关"],
+        login_path: Some("/"),
+        reboot_path: Some("/common_page/gatewayManage.lua"),
+        reboot_referer: Some("/common_page/main.lp"),
+        reboot_cmdtype: Some("HG_COMMAND_REBOOT"),
+        field_token: Some("Frm_Logintoken"),
+        auto_token: true,
+    },
+];
+
+/// 从登录页识别到的型号文本与匹配到的配置。
+pub struct Detection {
+    pub model: Option<String>,
+    pub profile: Option<&'static Profile>,
+}
+
+/// `--auto-detect`：用一个不带 cookie 存储的客户端 GET 登录页，提取型号并匹配内置配置。
+pub fn detect_model(client: &Client, login_url: &Url) -> Result<Detection> {
+    let page = client
+        .get(login_url.clone())
+        .send()
+        .context("login page request failed")?
+        .error_for_status()
+        .context("login page returned error status")?
+        .text()
+        .context("reading login page body")?;
+    Ok(detect(&page))
+}
+
+pub fn detect(page: &str) -> Detection {
+    Detection {
+        model: MODEL.find(page).map(|m| m.as_str().to_string()),
+        profile: PROFILES
+            .iter()
+            .find(|p| p.markers.iter().any(|m| page.contains(m))),
+    }
+}
+
+impl Profile {
+    /// 只覆盖仍是内置默认值的参数；命令行或配置文件里显式改过的保持不变。
+    pub fn apply(&self, args: &mut Args) {
+        let command = Args::command();
+        let set = |target: &mut String, id: &str, value: Option<&str>| {
+            if let Some(value) = value
+                && default_value(&command, id).as_deref() == Some(target.as_str())
+            {
+                *target = value.to_string();
+            }
+        };
+        set(&mut args.login_path, "login_path", self.login_path);
+        set(&mut args.reboot_path, "reboot_path", self.reboot_path);
+        set(
+            &mut args.reboot_referer,
+            "reboot_referer",
+            self.reboot_referer,
+        );
+        set(
+            &mut args.reboot_cmdtype,
+            "reboot_cmdtype",
+            self.reboot_cmdtype,
+        );
+        set(&mut args.field_token, "field_token", self.field_token);
+        args.auto_token |= self.auto_token;
+    }
+}
+
+/// clap 为参数 `id` 声明的默认值（不读取环境变量，也不做完整解析）。
+fn default_value(command: &Command, id: &str) -> Option<String> {
+    let arg = command.get_arguments().find(|arg| arg.get_id() == id)?;
+    arg.get_default_values()
+        .first()?
+        .to_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Answer, Stub};
+
+    const TIANYI_PAGE: &str = r#"<html><head><title>天翼网关</title></head>
+<body><div class="model">ZXHN F650</div>
+<form><input type="hidden" name="Frm_Logintoken" value="3"></form></body></html>"#;
+
+    #[test]
+    fn tianyi_login_page_selects_the_gateway_profile() {
+        let detection = detect(TIANYI_PAGE);
+        assert_eq!(detection.model.as_deref(), Some("ZXHN F650"));
+        assert_eq!(detection.profile.map(|p| p.name), Some("tianyi-gateway"));
+    }
+
+    #[test]
+    fn unknown_pages_keep_the_defaults() {
+        let detection = detect("<title>Router F7607P</title>");
+        assert_eq!(detection.model.as_deref(), Some("F7607P"));
+        assert!(detection.profile.is_none());
+
+        let detection = detect("<title>Login</title><a href=\"/common_page/main.lp\">");
+        assert!(detection.model.is_none());
+        assert!(detection.profile.is_none());
+    }
+
+    #[test]
+    fn apply_only_replaces_default_values() {
+        let profile = Profile {
+            name: "sample",
+            markers: &["sample"],
+            login_path: Some("/cgi-bin/login"),
+            reboot_path: Some("/cgi-bin/reboot"),
+            reboot_referer: None,
+            reboot_cmdtype: Some("SAMPLE_REBOOT"),
+            field_token: None,
+            auto_token: true,
+        };
+        let defaults = test_support::args(&[]);
+        let mut args = test_support::args(&["--reboot-path", "/mine.lua"]);
+        profile.apply(&mut args);
+        assert_eq!(args.login_path, "/cgi-bin/login");
+        assert_eq!(args.reboot_path, "/mine.lua");
+        assert_eq!(args.reboot_referer, defaults.reboot_referer);
+        assert_eq!(args.reboot_cmdtype, "SAMPLE_REBOOT");
+        assert_eq!(args.field_token, defaults.field_token);
+        assert!(args.auto_token);
+    }
+
+    #[test]
+    fn every_profile_setting_has_a_clap_default() {
+        let command = Args::command();
+        for id in [
+            "login_path",
+            "reboot_path",
+            "reboot_referer",
+            "reboot_cmdtype",
+            "field_token",
+        ] {
+            assert!(default_value(&command, id).is_some(), "{id}");
+        }
+        assert_eq!(
+            default_value(&command, "host"),
+            Some("http://192.168.1.1".into())
+        );
+        assert_eq!(default_value(&command, "no_such_arg"), None);
+    }
+
+    #[test]
+    fn detect_model_fetches_the_login_page() {
+        let stub = Stub::start(|_| Answer::ok(TIANYI_PAGE));
+        let url = Url::parse(&stub.base).unwrap();
+        let detection = detect_model(&test_support::client(), &url).unwrap();
+        assert_eq!(detection.profile.map(|p| p.name), Some("tianyi-gateway"));
+        assert_eq!(stub.hits_to("/").len(), 1);
+
+        let stub = Stub::start(|_| Answer::status(500, TIANYI_PAGE));
+        let url = Url::parse(&stub.base).unwrap();
+        let err = detect_model(&test_support::client(), &url).err().unwrap();
+        assert_eq!(err.to_string(), "login page returned error status");
+    }
+}
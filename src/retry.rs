@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use rand::Rng;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// 一次运行内所有重试（登录+重启）的总时长上限（`--retry-deadline`）
+    pub deadline: Option<Duration>,
+    started: Option<Instant>,
+    clock: Clock,
+}
+
+/// 计时与退避等待；测试里换成虚拟时间，让 deadline 的判断与真实耗时无关。
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    now: fn() -> Instant,
+    sleep: fn(Duration),
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock {
+            now: Instant::now,
+            sleep: thread::sleep,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration, deadline: Option<Duration>) -> Self {
+        RetryPolicy {
+            max_retries,
+            base_delay,
+            deadline,
+            started: None,
+            clock: Clock::default(),
+        }
+    }
+
+    /// 从现在开始计算 deadline；未调用时不限制总时长。
+    pub fn starting_now(self) -> Self {
+        RetryPolicy {
+            started: Some((self.clock.now)()),
+            ..self
+        }
+    }
+
+    fn remaining(&self) -> Option<Duration> {
+        let (deadline, started) = (self.deadline?, self.started?);
+        let elapsed = (self.clock.now)().saturating_duration_since(started);
+        Some(deadline.saturating_sub(elapsed))
+    }
+
+    /// 第 n 次失败后的等待：base * 2^(n-1)，再叠加至多一半的随机抖动。
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+        let jitter_ms = rand::thread_rng().gen_range(0..=exp.as_millis() as u64 / 2);
+        exp + Duration::from_millis(jitter_ms)
+    }
+}
+
+/// 只重试连接失败/超时这类瞬时错误；鉴权被拒等错误直接返回，避免反复打路由器。
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_timeout() || e.is_connect())
+}
+
+pub fn retry_with_backoff<T>(
+    what: &str,
+    policy: &RetryPolicy,
+    mut op: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        debug!(attempt, "{what}: attempt");
+        match op() {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt <= policy.max_retries && is_retryable(&e) => {
+                let delay = policy.delay_for(attempt);
+                // 等完这次退避就会越过 deadline：直接放弃，避免拖进下一个调度窗口。
+                if policy.remaining().is_some_and(|left| left <= delay) {
+                    let deadline = policy.deadline.unwrap_or_default();
+                    return Err(e).with_context(|| {
+                        format!("{what}: retry deadline of {}s exceeded", deadline.as_secs())
+                    });
+                }
+                warn!(
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "{what} failed, retrying: {e:#}"
+                );
+                (policy.clock.sleep)(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Answer, Stub, closed_port_url};
+    use reqwest::blocking::Client;
+    use std::cell::Cell;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fast(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new(max_retries, Duration::from_millis(1), None)
+    }
+
+    #[test]
+    fn retries_until_the_router_answers() {
+        // 前两次请求故意超时，第三次正常应答。
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&served);
+        let stub = Stub::start(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                thread::sleep(Duration::from_millis(500));
+            }
+            Answer::ok("up")
+        });
+        let client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let mut attempts = 0;
+        let body = retry_with_backoff("login", &fast(3), || {
+            attempts += 1;
+            Ok(client.get(&stub.base).send()?.text()?)
+        })
+        .unwrap();
+        assert_eq!(body, "up");
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let url = closed_port_url();
+        let client = Client::new();
+        let mut attempts = 0;
+        let result = retry_with_backoff("login", &fast(2), || {
+            attempts += 1;
+            Ok(client.get(&url).send()?.status())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn rejected_login_is_not_retried() {
+        let mut attempts = 0;
+        let result: Result<()> = retry_with_backoff("login", &fast(3), || {
+            attempts += 1;
+            anyhow::bail!("login rejected: credentials invalid")
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    thread_local! {
+        static EPOCH: Instant = Instant::now();
+        static ELAPSED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    }
+
+    /// 虚拟时钟：只有 `advance`（包括退避等待）会让时间前进。
+    fn virtual_now() -> Instant {
+        EPOCH.with(|epoch| *epoch) + ELAPSED.get()
+    }
+
+    fn advance(by: Duration) {
+        ELAPSED.set(ELAPSED.get() + by);
+    }
+
+    fn virtual_policy(max_retries: u32, base_delay_ms: u64, deadline_ms: u64) -> RetryPolicy {
+        RetryPolicy {
+            clock: Clock {
+                now: virtual_now,
+                sleep: advance,
+            },
+            ..RetryPolicy::new(
+                max_retries,
+                Duration::from_millis(base_delay_ms),
+                Some(Duration::from_millis(deadline_ms)),
+            )
+        }
+        .starting_now()
+    }
+
+    /// 每次尝试先耗掉 `cost`，再以连接失败（可重试）结束；返回尝试次数、虚拟耗时与错误。
+    fn run_failing(policy: &RetryPolicy, cost: &[u64]) -> (usize, Duration, anyhow::Error) {
+        let url = closed_port_url();
+        let client = Client::new();
+        let started = virtual_now();
+        let mut attempts = 0;
+        let err = retry_with_backoff("reboot", policy, || {
+            advance(Duration::from_millis(
+                cost.get(attempts).copied().unwrap_or(0),
+            ));
+            attempts += 1;
+            Ok(client.get(&url).send()?.status())
+        })
+        .unwrap_err();
+        (attempts, virtual_now() - started, err)
+    }
+
+    #[test]
+    fn deadline_stops_retrying_before_the_backoff_would_cross_it() {
+        // 第一次尝试耗时 700ms，之后剩余 300ms：第一次退避（100~150ms）还来得及，
+        // 第二次失败后只剩 150~200ms，而下一次退避要 200~300ms，于是直接放弃。
+        let policy = virtual_policy(100, 100, 1000);
+        let (attempts, elapsed, err) = run_failing(&policy, &[700]);
+        assert_eq!(attempts, 2);
+        assert!(
+            (Duration::from_millis(800)..=Duration::from_millis(850)).contains(&elapsed),
+            "{elapsed:?}"
+        );
+        assert_eq!(err.to_string(), "reboot: retry deadline of 1s exceeded");
+        assert!(is_retryable(&err));
+    }
+
+    #[test]
+    fn backoff_never_sleeps_past_the_deadline() {
+        for deadline_ms in [50, 120, 333, 1000, 2500] {
+            let policy = virtual_policy(100, 10, deadline_ms);
+            let (_, elapsed, err) = run_failing(&policy, &[]);
+            assert!(
+                elapsed < Duration::from_millis(deadline_ms),
+                "{deadline_ms}ms deadline: slept {elapsed:?}"
+            );
+            assert!(
+                err.to_string().contains("retry deadline"),
+                "{deadline_ms}ms deadline: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn attempt_that_overruns_the_deadline_is_not_retried() {
+        let policy = virtual_policy(5, 1, 1000);
+        let (attempts, elapsed, err) = run_failing(&policy, &[1500]);
+        assert_eq!(attempts, 1);
+        assert_eq!(elapsed, Duration::from_millis(1500));
+        assert_eq!(err.to_string(), "reboot: retry deadline of 1s exceeded");
+    }
+
+    #[test]
+    fn max_retries_still_applies_within_the_deadline() {
+        let policy = virtual_policy(3, 1, 60_000);
+        let (attempts, _, err) = run_failing(&policy, &[]);
+        assert_eq!(attempts, 4);
+        assert!(!err.to_string().contains("retry deadline"), "{err}");
+    }
+
+    #[test]
+    fn deadline_only_counts_once_started() {
+        let policy = RetryPolicy {
+            started: None,
+            ..virtual_policy(2, 1, 0)
+        };
+        let (attempts, _, _) = run_failing(&policy, &[]);
+        assert_eq!(attempts, 3);
+
+        let (attempts, _, err) = run_failing(&policy.starting_now(), &[]);
+        assert_eq!(attempts, 1);
+        assert_eq!(err.to_string(), "reboot: retry deadline of 0s exceeded");
+    }
+
+    #[test]
+    fn delay_doubles_with_bounded_jitter() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), None);
+        for (attempt, base_ms) in [(1, 100), (2, 200), (3, 400)] {
+            let delay = policy.delay_for(attempt).as_millis() as u64;
+            assert!(
+                (base_ms..=base_ms * 3 / 2).contains(&delay),
+                "attempt {attempt}: {delay}ms"
+            );
+        }
+    }
+}
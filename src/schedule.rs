@@ -0,0 +1,542 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use cron::Schedule;
+use rand::Rng;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::debug;
+
+/// cron 宏及其展开（6 段，前置秒）。
+const CRON_MACROS: &[(&str, &str)] = &[
+    ("@hourly", "0 0 * * * *"),
+    ("@daily", "0 0 0 * * *"),
+    ("@weekly", "0 0 0 * * Sun"),
+    ("@monthly", "0 0 0 1 * *"),
+    ("@yearly", "0 0 0 1 1 *"),
+];
+
+/// 展开 `@daily` 等宏；将标准 5 段 cron（min hour dom mon dow）补齐为 cron crate 需要的 6 段（前置秒）。
+/// 6/7 段表达式原样返回。
+pub fn normalize_cron(expr: &str) -> String {
+    let expr = expr.trim();
+    if let Some((_, expanded)) = CRON_MACROS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(expr))
+    {
+        expanded.to_string()
+    } else if expr.split_whitespace().count() == 5 {
+        format!("0 {expr}")
+    } else {
+        expr.to_string()
+    }
+}
+
+/// 规范化后再交给 cron crate 解析。
+pub fn parse_schedule(expr: &str) -> Result<Schedule> {
+    let normalized = normalize_cron(expr);
+    debug!(cron = %normalized, "Normalized cron expression");
+    Schedule::from_str(&normalized)
+        .with_context(|| format!("invalid cron expression `{normalized}`"))
+}
+
+/// 多个 cron 表达式合并后的调度：下一次运行取所有表达式中最早的那个。
+/// `timezone` 为空时按本机时区（`Local`）解释表达式。
+pub struct CronSet {
+    entries: Vec<(String, Schedule)>,
+    timezone: Option<Tz>,
+}
+
+impl CronSet {
+    /// 空表达式会被忽略；全部为空时使用 `default`。任一表达式非法则报告其序号与原文。
+    pub fn parse(exprs: &[String], default: &str, timezone: Option<Tz>) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (i, expr) in exprs.iter().enumerate() {
+            if expr.trim().is_empty() {
+                continue;
+            }
+            let schedule =
+                parse_schedule(expr).with_context(|| format!("cron #{} `{}`", i + 1, expr))?;
+            // 例如把年份固定在过去的 7 段表达式：语法合法但永远不会再触发。
+            let has_future = match timezone {
+                Some(tz) => schedule.upcoming(tz).next().is_some(),
+                None => schedule.upcoming(Local).next().is_some(),
+            };
+            if !has_future {
+                bail!(
+                    "cron #{} `{}` has no future occurrences (is it pinned to a past date?)",
+                    i + 1,
+                    expr
+                );
+            }
+            entries.push((expr.trim().to_string(), schedule));
+        }
+        if entries.is_empty() {
+            entries.push((default.to_string(), parse_schedule(default)?));
+        }
+        Ok(CronSet { entries, timezone })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn expr(&self, index: usize) -> &str {
+        &self.entries[index].0
+    }
+
+    /// 严格晚于 `now` 的最近一次触发，以及触发它的表达式序号。
+    /// 指定了时区时在该时区里计算（夏令时切换由 cron crate 处理），结果换算回本地时间。
+    pub fn next_after(&self, now: &DateTime<Local>) -> Option<(usize, DateTime<Local>)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, s))| {
+                let next = match self.timezone {
+                    Some(tz) => s
+                        .after(&now.with_timezone(&tz))
+                        .next()
+                        .map(|t| t.with_timezone(&Local)),
+                    None => s.after(now).next(),
+                };
+                next.map(|t| (i, t))
+            })
+            .min_by_key(|(_, t)| *t)
+    }
+}
+
+/// 调度方式：cron 表达式，或从进程启动起每隔固定时长（`--interval`）。
+pub enum Timetable {
+    Cron(CronSet),
+    Interval {
+        start: DateTime<Local>,
+        every: TimeDelta,
+        label: String,
+    },
+}
+
+impl Timetable {
+    pub fn len(&self) -> usize {
+        match self {
+            Timetable::Cron(crons) => crons.len(),
+            Timetable::Interval { .. } => 1,
+        }
+    }
+
+    /// 按 `--timezone` 显示时间；未指定时区（或按间隔调度）时显示本地时间。
+    pub fn display_time(&self, t: &DateTime<Local>) -> String {
+        match self {
+            Timetable::Cron(CronSet {
+                timezone: Some(tz), ..
+            }) => t.with_timezone(tz).to_string(),
+            _ => t.to_string(),
+        }
+    }
+
+    /// 日志里展示的触发来源：cron 原文或 `every 12h`。
+    pub fn describe(&self, index: usize) -> &str {
+        match self {
+            Timetable::Cron(crons) => crons.expr(index),
+            Timetable::Interval { label, .. } => label,
+        }
+    }
+
+    /// 严格晚于 `now` 的最近一次触发，以及触发它的表达式序号。
+    pub fn next_after(&self, now: &DateTime<Local>) -> Option<(usize, DateTime<Local>)> {
+        match self {
+            Timetable::Cron(crons) => crons.next_after(now),
+            Timetable::Interval { start, every, .. } => {
+                let elapsed = (*now - *start).num_milliseconds().max(-1);
+                let periods = i32::try_from(elapsed / every.num_milliseconds() + 1).ok()?;
+                Some((0, *start + *every * periods))
+            }
+        }
+    }
+
+    /// `since` 之后、`now` 之前（含）最早错过的一次触发；多次错过也只返回一个。
+    pub fn missed_between(
+        &self,
+        since: &DateTime<Local>,
+        now: &DateTime<Local>,
+    ) -> Option<(usize, DateTime<Local>)> {
+        self.next_after(since).filter(|(_, t)| t <= now)
+    }
+
+    /// 从 `from` 之后的 N 次触发；多个表达式同一时刻触发只算一次。
+    pub fn upcoming(&self, from: &DateTime<Local>, count: usize) -> Vec<(usize, DateTime<Local>)> {
+        let mut out = Vec::with_capacity(count);
+        let mut cursor = *from;
+        while out.len() < count {
+            let Some((i, t)) = self.next_after(&cursor) else {
+                break;
+            };
+            out.push((i, t));
+            cursor = t;
+        }
+        out
+    }
+}
+
+/// 解析 `--interval`：`90s`、`15m`、`12h`、`1d`，也可以组合如 `1h30m`。
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            _ => bail!("invalid duration `{text}`: unknown unit `{c}` (use s, m, h or d)"),
+        };
+        let value: u64 = digits
+            .parse()
+            .with_context(|| format!("invalid duration `{text}`: missing number before `{c}`"))?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .with_context(|| format!("duration `{text}` is too large"))?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        bail!("invalid duration `{text}`: missing unit after `{digits}` (use s, m, h or d)");
+    }
+    if total == 0 {
+        bail!("invalid duration `{text}`: must be greater than zero");
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// `simulate --from/--until` 的时间：RFC 3339（带时区偏移），或按本地时间解释的
+/// `2025-03-30 02:30[:00]` / `2025-03-30`。
+pub fn parse_timestamp(text: &str) -> Result<DateTime<Local>, String> {
+    let text = text.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(text) {
+        return Ok(t.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            format!("invalid timestamp `{text}` (use RFC 3339 or YYYY-MM-DD[ HH:MM[:SS]])")
+        })?;
+    // 夏令时切换造成的重复时刻取较早的一个；不存在的时刻报错。
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("`{text}` does not exist in the local time zone"))
+}
+
+/// 人类可读的时间间隔，例如 `3d 4h 5m`。
+pub fn format_delta(delta: TimeDelta) -> String {
+    let total = delta.num_seconds().max(0);
+    let (days, hours, minutes, secs) = (
+        total / 86_400,
+        total % 86_400 / 3600,
+        total % 3600 / 60,
+        total % 60,
+    );
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m {secs}s")
+    }
+}
+
+/// 在 `next` 之后加上 `[0, max_secs]` 的均匀随机偏移，但不会越过下一次触发时间 `following`。
+pub fn jittered(
+    next: DateTime<Local>,
+    following: Option<DateTime<Local>>,
+    max_secs: u64,
+) -> DateTime<Local> {
+    let mut bound = max_secs as i64;
+    if let Some(following) = following {
+        bound = bound.min((following - next).num_seconds() - 1);
+    }
+    if bound <= 0 {
+        return next;
+    }
+    next + TimeDelta::seconds(rand::thread_rng().gen_range(0..=bound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(text: &str) -> DateTime<Local> {
+        parse_timestamp(text).unwrap()
+    }
+
+    #[test]
+    fn five_field_cron_gets_leading_seconds() {
+        assert_eq!(normalize_cron("0 4 * * Mon"), "0 0 4 * * Mon");
+        assert_eq!(normalize_cron("  */15 * * * *  "), "0 */15 * * * *");
+    }
+
+    #[test]
+    fn six_and_seven_field_cron_is_kept() {
+        assert_eq!(normalize_cron("30 0 4 * * Mon"), "30 0 4 * * Mon");
+        assert_eq!(normalize_cron("0 0 4 * * Mon 2030"), "0 0 4 * * Mon 2030");
+    }
+
+    #[test]
+    fn five_field_cron_fires_at_minute_precision() {
+        let schedule = parse_schedule("30 4 * * Mon").unwrap();
+        let next = schedule.after(&local("2030-01-01 00:00")).next().unwrap();
+        assert_eq!(next, local("2030-01-07 04:30:00"));
+    }
+
+    fn crons(exprs: &[&str]) -> CronSet {
+        let exprs: Vec<String> = exprs.iter().map(|e| e.to_string()).collect();
+        CronSet::parse(&exprs, "0 0 4 * * Mon", None).unwrap()
+    }
+
+    #[test]
+    fn upcoming_lists_runs_in_order_without_duplicates() {
+        let timetable = Timetable::Cron(crons(&["0 4 * * *", "0 4 * * Mon"]));
+        let runs = timetable.upcoming(&local("2030-01-06 12:00"), 3);
+        let times: Vec<_> = runs.iter().map(|(_, t)| *t).collect();
+        assert_eq!(
+            times,
+            [
+                local("2030-01-07 04:00"),
+                local("2030-01-08 04:00"),
+                local("2030-01-09 04:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn earliest_schedule_wins_and_reports_its_index() {
+        let set = crons(&["0 4 * * Mon", "30 2 * * *"]);
+        assert_eq!(set.len(), 2);
+        let (index, next) = set.next_after(&local("2030-01-06 12:00")).unwrap();
+        assert_eq!((index, next), (1, local("2030-01-07 02:30")));
+        assert_eq!(set.expr(index), "30 2 * * *");
+    }
+
+    #[test]
+    fn blank_schedules_fall_back_to_default() {
+        let set = crons(&["", "  "]);
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.expr(0), "0 0 4 * * Mon");
+    }
+
+    #[test]
+    fn invalid_schedule_reports_its_position() {
+        let exprs = vec!["0 4 * * Mon".to_string(), "bogus".to_string()];
+        let err = CronSet::parse(&exprs, "0 0 4 * * Mon", None).err().unwrap();
+        assert!(err.to_string().starts_with("cron #2 `bogus`"), "{err:#}");
+    }
+
+    #[test]
+    fn jitter_stays_within_window() {
+        let next = local("2030-01-07 04:00");
+        for _ in 0..100 {
+            let t = jittered(next, None, 600);
+            assert!(t >= next && t <= next + TimeDelta::seconds(600), "{t}");
+        }
+    }
+
+    #[test]
+    fn jitter_never_reaches_the_following_run() {
+        let next = local("2030-01-07 04:00");
+        let following = next + TimeDelta::seconds(30);
+        for _ in 0..100 {
+            assert!(jittered(next, Some(following), 3600) < following);
+        }
+    }
+
+    #[test]
+    fn no_jitter_when_there_is_no_room() {
+        let next = local("2030-01-07 04:00");
+        assert_eq!(jittered(next, None, 0), next);
+        assert_eq!(jittered(next, Some(next + TimeDelta::seconds(1)), 60), next);
+    }
+
+    #[test]
+    fn missed_windows_collapse_into_one_catch_up() {
+        let weekly = Timetable::Cron(crons(&["0 4 * * Mon"]));
+        let two_weeks_ago = local("2029-12-24 05:00");
+        let missed = weekly.missed_between(&two_weeks_ago, &local("2030-01-07 12:00"));
+        assert_eq!(missed, Some((0, local("2029-12-31 04:00"))));
+    }
+
+    #[test]
+    fn nothing_missed_since_the_last_window() {
+        let weekly = Timetable::Cron(crons(&["0 4 * * Mon"]));
+        let last_run = local("2030-01-07 04:00");
+        assert_eq!(
+            weekly.missed_between(&last_run, &local("2030-01-13 23:59")),
+            None
+        );
+        assert_eq!(
+            weekly.missed_between(&last_run, &local("2030-01-14 04:00")),
+            Some((0, local("2030-01-14 04:00")))
+        );
+    }
+
+    #[test]
+    fn durations_combine_units() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(
+            parse_duration(" 12h ").unwrap(),
+            Duration::from_secs(43_200)
+        );
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        let err = |text: &str| parse_duration(text).unwrap_err().to_string();
+        assert_eq!(
+            err("12"),
+            "invalid duration `12`: missing unit after `12` (use s, m, h or d)"
+        );
+        assert_eq!(
+            err("12w"),
+            "invalid duration `12w`: unknown unit `w` (use s, m, h or d)"
+        );
+        assert_eq!(err("h"), "invalid duration `h`: missing number before `h`");
+        assert_eq!(
+            err("0m"),
+            "invalid duration `0m`: must be greater than zero"
+        );
+        assert_eq!(
+            err("999999999999999d"),
+            "duration `999999999999999d` is too large"
+        );
+    }
+
+    #[test]
+    fn interval_fires_at_multiples_of_start() {
+        let start = local("2030-01-07 00:00");
+        let every = Timetable::Interval {
+            start,
+            every: TimeDelta::hours(12),
+            label: "every 12h".into(),
+        };
+        assert_eq!(
+            every.next_after(&start),
+            Some((0, local("2030-01-07 12:00")))
+        );
+        assert_eq!(
+            every.next_after(&local("2030-01-07 12:00")),
+            Some((0, local("2030-01-08 00:00")))
+        );
+        assert_eq!(
+            every.next_after(&local("2030-01-08 05:00")),
+            Some((0, local("2030-01-08 12:00")))
+        );
+        assert_eq!(every.describe(0), "every 12h");
+    }
+
+    #[test]
+    fn macros_fire_at_expected_times() {
+        let now = local("2030-01-07 10:30");
+        for (name, expected) in [
+            ("@hourly", "2030-01-07 11:00"),
+            ("@daily", "2030-01-08 00:00"),
+            ("@weekly", "2030-01-13 00:00"),
+            ("@monthly", "2030-02-01 00:00"),
+            ("@yearly", "2031-01-01 00:00"),
+        ] {
+            let next = crons(&[name]).next_after(&now).map(|(_, t)| t);
+            assert_eq!(next, Some(local(expected)), "{name}");
+        }
+    }
+
+    #[test]
+    fn macros_are_case_insensitive_and_trimmed() {
+        assert_eq!(normalize_cron(" @Daily "), "0 0 0 * * *");
+        assert_eq!(normalize_cron("@WEEKLY"), "0 0 0 * * Sun");
+        assert!(parse_schedule("@fortnightly").is_err());
+    }
+
+    #[test]
+    fn timezone_keeps_local_hour_across_dst_changes() {
+        use chrono::Timelike;
+        let tz = chrono_tz::America::New_York;
+        let exprs = vec!["0 4 * * *".to_string()];
+        let set = Timetable::Cron(CronSet::parse(&exprs, "0 0 4 * * Mon", Some(tz)).unwrap());
+        // 2030 年纽约的两次切换：3 月 10 日进入夏令时，11 月 3 日退出。
+        for (start, expected) in [
+            ((3, 9), ["03-09", "03-10", "03-11"]),
+            ((11, 2), ["11-02", "11-03", "11-04"]),
+        ] {
+            let from = tz
+                .with_ymd_and_hms(2030, start.0, start.1, 0, 0, 0)
+                .unwrap()
+                .with_timezone(&Local);
+            let days: Vec<String> = set
+                .upcoming(&from, 3)
+                .into_iter()
+                .map(|(_, t)| {
+                    let t = t.with_timezone(&tz);
+                    assert_eq!((t.hour(), t.minute()), (4, 0), "{t}");
+                    t.format("%m-%d").to_string()
+                })
+                .collect();
+            assert_eq!(days, expected);
+        }
+    }
+
+    #[test]
+    fn timezone_is_used_for_display() {
+        let exprs = vec!["0 4 * * *".to_string()];
+        let tz = chrono_tz::Asia::Shanghai;
+        let set = Timetable::Cron(CronSet::parse(&exprs, "0 0 4 * * Mon", Some(tz)).unwrap());
+        let (_, next) = set.next_after(&local("2030-01-07 12:00")).unwrap();
+        assert!(
+            set.display_time(&next).ends_with("04:00:00 CST"),
+            "{}",
+            set.display_time(&next)
+        );
+    }
+
+    #[test]
+    fn year_pinned_to_the_past_is_rejected() {
+        let err = CronSet::parse(&["0 0 4 1 1 * 2020".into()], "@daily", None)
+            .err()
+            .unwrap();
+        let msg = format!("{err:#}");
+        assert!(msg.contains("cron #1"), "{msg}");
+        assert!(msg.contains("has no future occurrences"), "{msg}");
+    }
+
+    #[test]
+    fn year_pinned_schedule_runs_out_without_panicking() {
+        let crons = CronSet::parse(&["0 0 4 1 1 * 2099".into()], "@daily", None).unwrap();
+        let (_, next) = crons.next_after(&local("2030-06-01 00:00")).unwrap();
+        assert_eq!(next, local("2099-01-01 04:00"));
+        assert!(crons.next_after(&local("2099-01-02 00:00")).is_none());
+    }
+
+    #[test]
+    fn format_delta_picks_largest_units() {
+        assert_eq!(format_delta(TimeDelta::seconds(273_900)), "3d 4h 5m");
+        assert_eq!(format_delta(TimeDelta::seconds(3_660)), "1h 1m");
+        assert_eq!(format_delta(TimeDelta::seconds(65)), "1m 5s");
+        assert_eq!(format_delta(TimeDelta::seconds(-5)), "0m 0s");
+    }
+
+    #[test]
+    fn invalid_cron_reports_normalized_expression() {
+        let err = parse_schedule("61 4 * * Mon").unwrap_err();
+        assert!(format!("{err:#}").contains("`0 61 4 * * Mon`"), "{err:#}");
+    }
+}
@@ -0,0 +1,147 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+static INPUT_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<input\b[^>]*>").expect("valid regex"));
+static META_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<meta\b[^>]*>").expect("valid regex"));
+
+/// 固件页面里常见的 CSRF token 名称（meta 的 name，或表单字段名）。
+const CSRF_NAMES: &[&str] = &[
+    "csrf-token",
+    "csrf_token",
+    "csrfToken",
+    "_csrf",
+    "X-CSRF-Token",
+];
+
+/// 从 HTML 中提取指定 name 的 `<input>` 的 value（属性顺序不限，单双引号均可）。
+pub fn input_value(html: &str, name: &str) -> Option<String> {
+    INPUT_TAG
+        .find_iter(html)
+        .map(|m| m.as_str())
+        .find(|tag| attr(tag, "name").as_deref() == Some(name))
+        .and_then(|tag| attr(tag, "value"))
+}
+
+fn attr(tag: &str, attr: &str) -> Option<String> {
+    let re = Regex::new(&format!(
+        r#"(?i)\b{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#,
+        regex::escape(attr)
+    ))
+    .ok()?;
+    let caps = re.captures(tag)?;
+    caps.get(1)
+        .or_else(|| caps.get(2))
+        .map(|m| m.as_str().to_string())
+}
+
+/// 部分固件不在 input 里写死，而是用脚本赋值：`getObj("Frm_Logintoken").value = "123";`
+fn script_value(html: &str, name: &str) -> Option<String> {
+    let re = Regex::new(&format!(
+        r#"["']{}["']\s*\)\s*\.value\s*=\s*["']([^"']*)["']"#,
+        regex::escape(name)
+    ))
+    .ok()?;
+    re.captures(html)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().to_string())
+}
+
+/// 登录表单里 token 字段的默认名称。
+pub const TOKEN_FIELD: &str = "Frm_Logintoken";
+
+/// 按名称取表单字段的值：先找 `<input>`，再找脚本赋值。
+pub fn field_value(html: &str, name: &str) -> Option<String> {
+    input_value(html, name).or_else(|| script_value(html, name))
+}
+
+/// 登录页里的 token（字段名默认为 `Frm_Logintoken`，每次会话不同）。
+pub fn login_token(html: &str, field: &str) -> Option<String> {
+    field_value(html, field)
+}
+
+/// 登录后页面里的 CSRF token：先找 `<meta name="csrf-token" content="...">`，再按常见字段名找。
+pub fn csrf_token(html: &str) -> Option<String> {
+    let is_csrf = |name: &str| CSRF_NAMES.iter().any(|c| c.eq_ignore_ascii_case(name));
+    META_TAG
+        .find_iter(html)
+        .map(|m| m.as_str())
+        .find(|tag| attr(tag, "name").is_some_and(|name| is_csrf(&name)))
+        .and_then(|tag| attr(tag, "content"))
+        .or_else(|| CSRF_NAMES.iter().find_map(|name| field_value(html, name)))
+}
+
+/// 页面里仍有登录表单（token 字段），说明又回到了登录页。
+pub fn is_login_form(html: &str) -> bool {
+    login_token(html, TOKEN_FIELD).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_from_hidden_input() {
+        let html =
+            r#"<input type="hidden" name="Frm_Logintoken" id="Frm_Logintoken" value="12" />"#;
+        assert_eq!(login_token(html, TOKEN_FIELD).as_deref(), Some("12"));
+    }
+
+    #[test]
+    fn token_with_single_quotes_and_any_attribute_order() {
+        let html = "<INPUT value='34' type='hidden' name='Frm_Logintoken'>";
+        assert_eq!(login_token(html, TOKEN_FIELD).as_deref(), Some("34"));
+    }
+
+    #[test]
+    fn token_from_script_assignment() {
+        let html = r#"<script>getObj("Frm_Logintoken").value = "56";</script>"#;
+        assert_eq!(login_token(html, TOKEN_FIELD).as_deref(), Some("56"));
+    }
+
+    #[test]
+    fn custom_token_field_name() {
+        let html = r#"<input name="loginToken" value="78"><input name="Frm_Logintoken" value="1">"#;
+        assert_eq!(login_token(html, "loginToken").as_deref(), Some("78"));
+    }
+
+    /// 同时带 token 与 frashnum 的登录页片段。
+    const LOGIN_FIXTURE: &str = r#"<form name="fLogin" method="post">
+<input type="hidden" name="Frm_Logintoken" id="Frm_Logintoken" value="12" />
+<input type="hidden" name="frashnum" id="frashnum" value="1718" />
+<input type="text" name="user_name" value="" />
+</form>"#;
+
+    #[test]
+    fn frashnum_from_login_page() {
+        assert_eq!(
+            field_value(LOGIN_FIXTURE, "frashnum").as_deref(),
+            Some("1718")
+        );
+        assert_eq!(field_value(LOGIN_FIXTURE, "user_name").as_deref(), Some(""));
+        let script = r#"<script>getObj('frashnum').value = '903';</script>"#;
+        assert_eq!(field_value(script, "frashnum").as_deref(), Some("903"));
+        assert_eq!(field_value("<form></form>", "frashnum"), None);
+    }
+
+    #[test]
+    fn csrf_token_from_meta_input_or_script() {
+        let meta = r#"<head><meta charset="utf-8"><meta content="m3ta" name="CSRF-Token"></head>"#;
+        assert_eq!(csrf_token(meta).as_deref(), Some("m3ta"));
+        let input = r#"<input type="hidden" name="csrf_token" value="1npu7">"#;
+        assert_eq!(csrf_token(input).as_deref(), Some("1npu7"));
+        let script = r#"<script>getObj("_csrf").value = "scr1pt";</script>"#;
+        assert_eq!(csrf_token(script).as_deref(), Some("scr1pt"));
+        assert_eq!(
+            csrf_token(r#"<meta name="viewport" content="width=device-width">"#),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_token_is_none() {
+        assert_eq!(login_token("<input name=\"user_name\">", TOKEN_FIELD), None);
+        assert!(!is_login_form("<html>status</html>"));
+    }
+}
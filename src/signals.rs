@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// 一次等待结束的原因。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wakeup {
+    Elapsed,
+    /// SIGINT/SIGTERM：退出调度循环
+    Shutdown,
+    /// SIGHUP 或控制接口：立即执行一次
+    Trigger,
+}
+
+/// 可被信号打断的等待：收到 SIGINT/SIGTERM 后立即返回，而不是睡满整个 cron 间隔；
+/// SIGHUP 请求立即运行一次，多次 SIGHUP 在被处理前只会合并成一次。
+pub struct SignalWaiter {
+    rx: Receiver<()>,
+    shutdown: Arc<AtomicBool>,
+    trigger: Arc<AtomicBool>,
+    tx: Sender<()>,
+}
+
+/// 从其他线程请求立即运行一次（与 SIGHUP 共用同一标志位）。
+#[derive(Clone)]
+pub struct Trigger {
+    flag: Arc<AtomicBool>,
+    tx: Sender<()>,
+}
+
+impl Trigger {
+    /// 已有待处理的手动运行时返回 `false`（本次请求与之合并）。
+    pub fn fire(&self) -> bool {
+        if self.flag.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        // 调度循环退出后接收端已关闭，忽略即可。
+        let _ = self.tx.send(());
+        true
+    }
+}
+
+impl SignalWaiter {
+    /// 不监听信号的等待器，只能被 `Trigger` 唤醒（`install` 在此基础上接入信号）。
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        SignalWaiter {
+            rx,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            trigger: Arc::new(AtomicBool::new(false)),
+            tx,
+        }
+    }
+
+    pub fn install() -> Result<Self> {
+        let mut signals =
+            Signals::new([SIGINT, SIGTERM, SIGHUP]).context("installing signal handlers")?;
+        let waiter = SignalWaiter::new();
+        let signal_tx = waiter.tx.clone();
+        let (shutdown_flag, trigger_flag) =
+            (Arc::clone(&waiter.shutdown), Arc::clone(&waiter.trigger));
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                if signal == SIGHUP {
+                    if trigger_flag.swap(true, Ordering::SeqCst) {
+                        info!("SIGHUP received, manual run already pending");
+                        continue;
+                    }
+                    info!("SIGHUP received, scheduling an immediate run");
+                } else {
+                    info!(signal, "Received termination signal");
+                    shutdown_flag.store(true, Ordering::SeqCst);
+                }
+                if signal_tx.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(waiter)
+    }
+
+    pub fn trigger(&self) -> Trigger {
+        Trigger {
+            flag: Arc::clone(&self.trigger),
+            tx: self.tx.clone(),
+        }
+    }
+
+    fn pending(&self) -> Option<Wakeup> {
+        if self.shutdown.load(Ordering::SeqCst) {
+            Some(Wakeup::Shutdown)
+        } else if self.trigger.swap(false, Ordering::SeqCst) {
+            Some(Wakeup::Trigger)
+        } else {
+            None
+        }
+    }
+
+    pub fn wait(&self, timeout: Duration) -> Wakeup {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(wakeup) = self.pending() {
+                return wakeup;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.rx.recv_timeout(remaining) {
+                // 标志位可能已被上一次等待消费，此时继续等剩余时间。
+                Ok(()) => continue,
+                Err(RecvTimeoutError::Timeout) => return Wakeup::Elapsed,
+                Err(RecvTimeoutError::Disconnected) => {
+                    // 信号线程不会退出；万一退出了，退化为普通 sleep。
+                    thread::sleep(remaining);
+                    return Wakeup::Elapsed;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signal_hook::low_level::raise;
+
+    #[test]
+    fn manual_trigger_wakes_the_wait_once() {
+        let waiter = SignalWaiter::new();
+        let trigger = waiter.trigger();
+        let fired = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            trigger.fire()
+        });
+        assert_eq!(waiter.wait(Duration::from_secs(30)), Wakeup::Trigger);
+        assert!(fired.join().unwrap());
+        assert_eq!(waiter.wait(Duration::from_millis(50)), Wakeup::Elapsed);
+    }
+
+    #[test]
+    fn shutdown_takes_precedence_over_trigger() {
+        let waiter = SignalWaiter::new();
+        waiter.trigger().fire();
+        waiter.shutdown.store(true, Ordering::SeqCst);
+        assert_eq!(waiter.wait(Duration::from_secs(30)), Wakeup::Shutdown);
+    }
+
+    #[test]
+    fn trigger_after_the_loop_exits_is_ignored() {
+        let trigger = SignalWaiter::new().trigger();
+        assert!(trigger.fire());
+    }
+
+    // 信号是进程级的：所有断言放在同一个测试里，避免并发测试互相干扰。
+    #[test]
+    fn signals_interrupt_the_wait() {
+        let waiter = SignalWaiter::install().unwrap();
+        let started = Instant::now();
+
+        raise(SIGHUP).unwrap();
+        raise(SIGHUP).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(waiter.wait(Duration::from_secs(30)), Wakeup::Trigger);
+        // 处理前收到的多个 SIGHUP 只算一次。
+        assert_eq!(waiter.wait(Duration::from_millis(100)), Wakeup::Elapsed);
+
+        let trigger = waiter.trigger();
+        assert!(trigger.fire());
+        assert!(!trigger.fire());
+        assert_eq!(waiter.wait(Duration::from_secs(30)), Wakeup::Trigger);
+
+        raise(SIGTERM).unwrap();
+        assert_eq!(waiter.wait(Duration::from_secs(30)), Wakeup::Shutdown);
+        // 收到 SIGTERM 之后每次等待都立即返回。
+        assert_eq!(waiter.wait(Duration::from_secs(30)), Wakeup::Shutdown);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::Outcome;
+use crate::cookies::tmp_path;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunResult {
+    Success,
+    Failure,
+    /// 所有路由器都按条件跳过了重启
+    Skipped,
+}
+
+/// `--state-file` 的内容：最近一次运行的结果与下一次计划时间，供重启后及外部工具查看。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunState {
+    pub last_run: DateTime<Local>,
+    pub last_result: RunResult,
+    pub last_error: Option<String>,
+    pub next_run: Option<DateTime<Local>>,
+    /// 每台路由器最近一次实际下发重启的时间，供 --min-reboot-interval 在重启后恢复
+    #[serde(default)]
+    pub last_reboots: BTreeMap<String, DateTime<Local>>,
+}
+
+impl RunState {
+    pub fn new(
+        last_run: DateTime<Local>,
+        result: &Result<Outcome>,
+        next_run: Option<DateTime<Local>>,
+        last_reboots: BTreeMap<String, DateTime<Local>>,
+    ) -> Self {
+        RunState {
+            last_run,
+            last_result: match result {
+                Ok(Outcome::Done) => RunResult::Success,
+                Ok(Outcome::Skipped(_)) => RunResult::Skipped,
+                Err(_) => RunResult::Failure,
+            },
+            last_error: result.as_ref().err().map(|e| format!("{e:#}")),
+            next_run,
+            last_reboots,
+        }
+    }
+}
+
+/// 文件不存在时返回 `None`。
+pub fn load(path: &Path) -> Result<Option<RunState>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("reading state file {}", path.display()))?;
+    let state = serde_json::from_str(&text)
+        .with_context(|| format!("parsing state file {}", path.display()))?;
+    Ok(Some(state))
+}
+
+/// 先写临时文件再 rename，与 cookie 文件相同。
+pub fn save(path: &Path, state: &RunState) -> Result<()> {
+    let tmp = tmp_path(path);
+    let json = serde_json::to_vec_pretty(state).context("serializing run state")?;
+    fs::write(&tmp, json).with_context(|| format!("writing temp state file {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replacing state file {}", path.display()))?;
+    debug!(path = %path.display(), "Run state saved");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2030, 1, 7, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn state_roundtrips_through_the_file() {
+        let path = temp_path("state-roundtrip.json");
+        let result = Err(anyhow::anyhow!("reboot rejected").context("router test"));
+        let state = RunState::new(at(4), &result, Some(at(5)), BTreeMap::new());
+        save(&path, &state).unwrap();
+        assert!(!tmp_path(&path).exists());
+
+        let loaded = load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.last_run, at(4));
+        assert_eq!(loaded.last_result, RunResult::Failure);
+        assert_eq!(
+            loaded.last_error.as_deref(),
+            Some("router test: reboot rejected")
+        );
+        assert_eq!(loaded.next_run, Some(at(5)));
+    }
+
+    #[test]
+    fn result_maps_to_run_result() {
+        let done = RunState::new(at(4), &Ok(Outcome::Done), None, BTreeMap::new());
+        assert_eq!(done.last_result, RunResult::Success);
+        assert_eq!(done.last_error, None);
+        let skipped = Ok(Outcome::Skipped("uptime".into()));
+        let skipped = RunState::new(at(4), &skipped, None, BTreeMap::new());
+        assert_eq!(skipped.last_result, RunResult::Skipped);
+    }
+
+    #[test]
+    fn missing_state_file_is_not_an_error() {
+        assert!(load(&temp_path("state-missing.json")).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_state_file_names_the_path() {
+        let path = temp_path("state-corrupt.json");
+        fs::write(&path, "{not json").unwrap();
+        let err = load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            format!("{err:#}").contains(&path.display().to_string()),
+            "{err:#}"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Config, SessionExpired, scrape};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use reqwest::blocking::Client;
+use reqwest::header::USER_AGENT;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use std::time::Duration;
+use url::Url;
+
+static UPTIME_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)["']?DevUpTime["']?\s*[:=]\s*["']?(\d+)"#).expect("valid regex")
+});
+
+static MAC_ADDRESS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[0-9a-f]{2}(?:[:-][0-9a-f]{2}){5}\b").expect("valid regex")
+});
+
+/// 带会话 GET 一个状态类页面；又回到登录页视为会话失效。
+fn fetch_page(client: &Client, cfg: &Config, url: &Url, what: &str) -> Result<String> {
+    let body = client
+        .get(url.clone())
+        .header(USER_AGENT, cfg.user_agent())
+        .send()
+        .with_context(|| format!("{what} request failed"))?
+        .error_for_status()
+        .with_context(|| format!("{what} endpoint returned error status"))?
+        .text()
+        .with_context(|| format!("reading {what} response body"))?;
+    if scrape::is_login_form(&body) {
+        return Err(SessionExpired.into());
+    }
+    Ok(body)
+}
+
+/// 查询路由器状态页并解析系统运行时长。
+pub fn fetch_uptime(client: &Client, cfg: &Config) -> Result<Duration> {
+    parse_uptime(&fetch_page(client, cfg, &cfg.status_url, "status")?)
+}
+
+/// 查询 DHCP/已连接设备列表并统计在线客户端数量。
+pub fn fetch_active_clients(client: &Client, cfg: &Config) -> Result<usize> {
+    parse_client_count(&fetch_page(client, cfg, &cfg.clients_url, "client list")?)
+}
+
+/// 解析 ZTE 状态数据里的 `DevUpTime`（秒）；优先按 JSON 查找，失败再按文本匹配。
+pub fn parse_uptime(body: &str) -> Result<Duration> {
+    let from_json = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| find_key(&v, "DevUpTime").and_then(as_u64));
+    let secs = match from_json {
+        Some(secs) => secs,
+        None => UPTIME_FIELD
+            .captures(body)
+            .and_then(|c| c[1].parse().ok())
+            .context("DevUpTime not found in status response")?,
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// 统计客户端数量：JSON 时取最大的对象数组长度，否则按不重复的 MAC 地址计数。
+pub fn parse_client_count(body: &str) -> Result<usize> {
+    if let Ok(value) = serde_json::from_str::<Value>(body)
+        && let Some(count) = largest_object_array(&value)
+    {
+        return Ok(count);
+    }
+    let macs: HashSet<String> = MAC_ADDRESS
+        .find_iter(body)
+        .map(|m| m.as_str().to_ascii_lowercase().replace('-', ":"))
+        .collect();
+    if macs.is_empty() && !body.to_ascii_lowercase().contains("mac") {
+        bail!("no client entries found in client list response");
+    }
+    Ok(macs.len())
+}
+
+fn largest_object_array(value: &Value) -> Option<usize> {
+    match value {
+        Value::Array(items) => {
+            let own = items.iter().all(Value::is_object).then_some(items.len());
+            items
+                .iter()
+                .filter_map(largest_object_array)
+                .chain(own)
+                .max()
+        }
+        Value::Object(map) => map.values().filter_map(largest_object_array).max(),
+        _ => None,
+    }
+}
+
+/// 运行时长低于阈值时跳过重启。
+pub fn should_skip_reboot(uptime: Duration, min_uptime: Duration) -> bool {
+    uptime < min_uptime
+}
+
+/// 递归查找键（不区分大小写），返回第一个匹配的值。
+pub fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+            .or_else(|| map.values().find_map(|v| find_key(v, key))),
+        Value::Array(items) => items.iter().find_map(|v| find_key(v, key)),
+        _ => None,
+    }
+}
+
+fn as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uptime_from_nested_json() {
+        let body = r#"{"DeviceInfo":{"DevUpTime":"93784","SoftwareVer":"V1.0"}}"#;
+        assert_eq!(parse_uptime(body).unwrap(), Duration::from_secs(93784));
+    }
+
+    #[test]
+    fn uptime_from_script_text() {
+        let body = "<script>var DevUpTime = '7200';</script>";
+        assert_eq!(parse_uptime(body).unwrap(), Duration::from_secs(7200));
+    }
+
+    #[test]
+    fn missing_uptime_is_an_error() {
+        assert!(parse_uptime("<html>status</html>").is_err());
+    }
+
+    #[test]
+    fn clients_counted_from_largest_json_array() {
+        let body = r#"{"WAN":[{"ip":"1"}],"LAN":{"Hosts":[{"mac":"a"},{"mac":"b"},{"mac":"c"}]}}"#;
+        assert_eq!(parse_client_count(body).unwrap(), 3);
+    }
+
+    #[test]
+    fn clients_counted_by_distinct_mac_addresses() {
+        let body = "<td>AA:BB:CC:00:11:22</td><td>aa-bb-cc-00-11-22</td><td>aa:bb:cc:00:11:33</td>";
+        assert_eq!(parse_client_count(body).unwrap(), 2);
+        assert_eq!(parse_client_count("<th>MAC</th>").unwrap(), 0);
+    }
+
+    #[test]
+    fn unrecognized_client_list_is_an_error() {
+        assert!(parse_client_count("<html>hello</html>").is_err());
+    }
+
+    #[test]
+    fn skips_only_below_threshold() {
+        let day = Duration::from_secs(86_400);
+        assert!(should_skip_reboot(Duration::from_secs(3600), day));
+        assert!(!should_skip_reboot(day, day));
+    }
+}
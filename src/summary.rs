@@ -0,0 +1,122 @@
+use crate::Outcome;
+use anyhow::Result;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// `--summary`：跨多次运行累计的统计，进程退出前打印。
+#[derive(Debug, Default)]
+pub struct Summary {
+    totals: Mutex<Totals>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Totals {
+    runs: u64,
+    successes: u64,
+    failures: u64,
+    skipped: u64,
+    /// 校验重启时观察到的掉线时长之和
+    downtime: Duration,
+    verified_reboots: u64,
+    last_error: Option<String>,
+}
+
+impl Summary {
+    /// 记录一轮运行（对所有路由器）的结果。
+    pub fn record_run(&self, result: &Result<Outcome>) {
+        let mut totals = self.totals.lock().expect("summary lock poisoned");
+        totals.runs += 1;
+        match result {
+            Ok(Outcome::Done) => totals.successes += 1,
+            Ok(Outcome::Skipped(_)) => totals.skipped += 1,
+            Err(e) => {
+                totals.failures += 1;
+                totals.last_error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    pub fn add_downtime(&self, downtime: Duration) {
+        let mut totals = self.totals.lock().expect("summary lock poisoned");
+        totals.downtime += downtime;
+        totals.verified_reboots += 1;
+    }
+
+    pub fn print(&self) {
+        print!(
+            "{}",
+            render(&self.totals.lock().expect("summary lock poisoned"))
+        );
+    }
+}
+
+fn render(totals: &Totals) -> String {
+    let mut out = format!(
+        "Summary: {} run(s), {} succeeded, {} skipped, {} failed\n",
+        totals.runs, totals.successes, totals.skipped, totals.failures
+    );
+    if totals.verified_reboots > 0 {
+        out.push_str(&format!(
+            "Downtime observed: {}s over {} verified reboot(s)\n",
+            totals.downtime.as_secs(),
+            totals.verified_reboots
+        ));
+    }
+    if let Some(e) = &totals.last_error {
+        out.push_str(&format!("Last error: {e}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{closed_port_url, router, runner};
+
+    fn rendered(summary: &Summary) -> String {
+        render(&summary.totals.lock().unwrap())
+    }
+
+    #[test]
+    fn summary_reflects_a_sequence_of_runs() {
+        let summary = Summary::default();
+        summary.record_run(&Ok(Outcome::Done));
+        summary.record_run(&Err(anyhow::anyhow!("first failure")));
+        summary.record_run(&Ok(Outcome::Skipped("busy".into())));
+        summary.add_downtime(Duration::from_secs(40));
+        summary.record_run(&Ok(Outcome::Done));
+        summary.add_downtime(Duration::from_secs(45));
+        summary.record_run(&Err(anyhow::anyhow!("login failed").context("router home")));
+        assert_eq!(
+            rendered(&summary),
+            "Summary: 5 run(s), 2 succeeded, 1 skipped, 2 failed\n\
+             Downtime observed: 85s over 2 verified reboot(s)\n\
+             Last error: router home: login failed\n"
+        );
+    }
+
+    #[test]
+    fn quiet_summary_has_only_the_counts() {
+        assert_eq!(
+            rendered(&Summary::default()),
+            "Summary: 0 run(s), 0 succeeded, 0 skipped, 0 failed\n"
+        );
+    }
+
+    #[test]
+    fn runner_records_every_run() {
+        let ok = runner(vec![router("http://192.168.1.1", &["--dry-run"])], false);
+        ok.run_all().unwrap();
+        ok.run_all().unwrap();
+        assert!(rendered(&ok.summary).starts_with("Summary: 2 run(s), 2 succeeded,"));
+
+        let down = runner(
+            vec![router(&closed_port_url(), &["--max-retries", "0"])],
+            false,
+        );
+        assert!(down.run_all().is_err());
+        let out = rendered(&down.summary);
+        assert!(out.contains("1 failed"), "{out}");
+        assert!(out.contains("Last error: "), "{out}");
+    }
+}
@@ -0,0 +1,230 @@
+//! 单元测试共用：本地 HTTP 桩路由器，以及按命令行参数构造 `Args`/`Config`。
+use crate::fleet::{Router, Runner};
+use crate::health::Health;
+use crate::logging::JsonLines;
+use crate::metrics::Metrics;
+use crate::notify::{Event, Notifier};
+use crate::summary::Summary;
+use crate::{Args, Config, Outcome, build_client, build_config};
+use clap::Parser;
+use reqwest::blocking::Client;
+use std::io::{self, Read};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Response, Server};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// 桩路由器收到的一次请求。
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub method: String,
+    /// 路径加查询串，例如 `/login?x=1`
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Hit {
+    pub fn path(&self) -> &str {
+        self.url.split('?').next().unwrap_or_default()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 表单或查询串里某个字段的值。
+    pub fn field(&self, name: &str) -> Option<String> {
+        let query = self.url.split_once('?').map(|(_, q)| q).unwrap_or_default();
+        url::form_urlencoded::parse(self.body.as_bytes())
+            .chain(url::form_urlencoded::parse(query.as_bytes()))
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+/// 桩路由器的一次应答。
+pub struct Answer {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Answer {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Answer::status(200, body)
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Answer {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// 在随机端口上应答请求的本地 HTTP 服务，记录收到的所有请求。
+pub struct Stub {
+    pub base: String,
+    hits: Arc<Mutex<Vec<Hit>>>,
+}
+
+impl Stub {
+    pub fn start<F>(handler: F) -> Stub
+    where
+        F: Fn(&Hit) -> Answer + Send + 'static,
+    {
+        let server = Server::http("127.0.0.1:0").expect("bind stub server");
+        let addr = server.server_addr().to_ip().expect("tcp listener");
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&hits);
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let hit = Hit {
+                    method: request.method().to_string(),
+                    url: request.url().to_string(),
+                    headers: request
+                        .headers()
+                        .iter()
+                        .map(|h| (h.field.to_string(), h.value.to_string()))
+                        .collect(),
+                    body,
+                };
+                let answer = handler(&hit);
+                recorded.lock().unwrap().push(hit);
+                let mut response =
+                    Response::from_string(answer.body).with_status_code(answer.status);
+                for (name, value) in &answer.headers {
+                    response = response.with_header(
+                        Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap(),
+                    );
+                }
+                let _ = request.respond(response);
+            }
+        });
+        Stub {
+            base: format!("http://{addr}"),
+            hits,
+        }
+    }
+
+    pub fn hits(&self) -> Vec<Hit> {
+        self.hits.lock().unwrap().clone()
+    }
+
+    pub fn hits_to(&self, path: &str) -> Vec<Hit> {
+        self.hits()
+            .into_iter()
+            .filter(|hit| hit.path() == path)
+            .collect()
+    }
+}
+
+/// 一个没有任何服务监听的本地地址，用来模拟路由器掉线。
+pub fn closed_port_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    format!("http://{addr}")
+}
+
+/// `tianyi-auto --password secret <extra...>`。
+pub fn args(extra: &[&str]) -> Args {
+    let argv = ["tianyi-auto", "--password", "secret"]
+        .into_iter()
+        .chain(extra.iter().copied());
+    Args::try_parse_from(argv).unwrap()
+}
+
+/// 指向 `host` 的单台路由器配置；未指定时重试间隔压到 1ms，避免测试等待。
+pub fn config(host: &str, extra: &[&str]) -> Config {
+    let mut argv = vec!["--host", host];
+    if !extra.contains(&"--retry-base-delay-ms") {
+        argv.extend(["--retry-base-delay-ms", "1"]);
+    }
+    argv.extend_from_slice(extra);
+    build_config("test".into(), &args(&argv)).unwrap()
+}
+
+/// 默认参数下的 HTTP 客户端（内存 cookie 存储）。
+pub fn client() -> Client {
+    build_client(&args(&[]), None).unwrap()
+}
+
+/// 没有备用地址的单台路由器，以 `host` 命名。
+pub fn router(host: &str, extra: &[&str]) -> Router {
+    let mut cfg = config(host, extra);
+    cfg.name = host.to_string();
+    Router {
+        cfg,
+        fallbacks: Vec::new(),
+        client: client(),
+    }
+}
+
+/// 不发通知、不导出 InfluxDB 的调度执行器。
+pub fn runner(routers: Vec<Router>, parallel: bool) -> Runner {
+    Runner {
+        routers,
+        parallel,
+        notifier: Notifier::from_args(&args(&[])).unwrap(),
+        metrics: Arc::new(Metrics::default()),
+        influx: None,
+        health: Arc::new(Health::default()),
+        summary: Summary::default(),
+    }
+}
+
+/// 路由器 `test`（192.168.1.1）一次运行的通知事件。
+pub fn event(result: anyhow::Result<Outcome>) -> Event {
+    Event::from_result(&config("http://192.168.1.1", &[]), &result)
+}
+
+/// 临时目录下本进程专用的文件路径（不会自动创建或删除）。
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tianyi-auto-test-{}-{name}", std::process::id()))
+}
+
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 在当前线程上执行 `f`，并按 `--log-format json` 的格式收集期间的日志，每行解析为一个 JSON 对象。
+pub fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, Vec<serde_json::Value>) {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&buf);
+    let subscriber = tracing_subscriber::registry().with(
+        tracing_subscriber::fmt::layer()
+            .event_format(JsonLines)
+            .with_writer(move || LogBuffer(Arc::clone(&sink))),
+    );
+    let result = tracing::subscriber::with_default(subscriber, f);
+    let out = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    let lines = out
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    (result, lines)
+}
@@ -0,0 +1,338 @@
+use crate::Config;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+use reqwest::header::USER_AGENT;
+use std::error::Error as _;
+use std::fmt;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, info_span, warn};
+use url::Url;
+
+/// `--startup-grace` 的首次轮询间隔；之后每次翻倍，最长 `WARMUP_MAX_DELAY`。
+const WARMUP_INITIAL_DELAY: Duration = Duration::from_secs(1);
+const WARMUP_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// 宿主机与路由器同时上电时，路由器的 Web 服务往往比本程序晚就绪：以指数退避轮询 `url`，
+/// 收到任何 HTTP 响应即返回 `true`；`grace` 内始终不可达返回 `false`，由调用方决定是否继续。
+pub fn wait_until_reachable(client: &Client, url: &Url, grace: Duration) -> bool {
+    let deadline = Instant::now() + grace;
+    let mut delay = WARMUP_INITIAL_DELAY;
+    loop {
+        match client.get(url.clone()).send() {
+            Ok(resp) => {
+                debug!(status = %resp.status(), "Warmup poll response");
+                return true;
+            }
+            Err(e) => debug!("Warmup poll failed: {e}"),
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        thread::sleep(delay.min(deadline - now));
+        delay = (delay * 2).min(WARMUP_MAX_DELAY);
+    }
+}
+
+/// 重启命令下发后轮询登录页，直到路由器先掉线再恢复响应，返回观察到的掉线时长。
+/// 重启窗口内的连接失败是预期行为；超过 `timeout` 仍未恢复则报错，以便通知能触发。
+pub fn wait_for_online(client: &Client, cfg: &Config, timeout: Duration) -> Result<Duration> {
+    let _span = info_span!("wait_for_online", timeout_secs = timeout.as_secs()).entered();
+    let start = Instant::now();
+    let deadline = start + timeout;
+    let mut went_down: Option<Instant> = None;
+
+    loop {
+        let request = client
+            .get(cfg.login_url.clone())
+            .header(USER_AGENT, cfg.user_agent());
+        let online = match request.send() {
+            Ok(resp) => {
+                debug!(status = %resp.status(), "Verify poll response");
+                resp.status().is_success()
+            }
+            Err(e) => match classify(&e) {
+                PollFailure::RouterDown => {
+                    debug!("Verify poll failed (expected while rebooting): {e}");
+                    false
+                }
+                PollFailure::Misconfigured => {
+                    warn!("Verify poll failed with a DNS or local network error: {e}");
+                    return Err(e).context(
+                        "cannot poll the router because of a DNS, local network or configuration problem, not a reboot",
+                    );
+                }
+            },
+        };
+
+        match (online, went_down) {
+            (true, Some(down_at)) => {
+                let downtime = down_at.elapsed();
+                info!(
+                    downtime_secs = downtime.as_secs(),
+                    "Router is back online after reboot"
+                );
+                return Ok(downtime);
+            }
+            (false, None) => {
+                info!("Router went offline, waiting for it to come back");
+                went_down = Some(Instant::now());
+            }
+            _ => {}
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            if went_down.is_some() {
+                bail!(
+                    "router did not come back online within {}s",
+                    timeout.as_secs()
+                );
+            }
+            return Err(NeverWentDown { timeout }.into());
+        }
+        thread::sleep(cfg.poll_interval.min(deadline - now));
+    }
+}
+
+/// 校验轮询失败的原因。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PollFailure {
+    /// 连接被拒绝、重置或中断、超时、主机不可达：路由器正在重启，继续轮询到超时
+    RouterDown,
+    /// 请求无法构造、域名解析失败、本机网络不可用等：与重启无关，继续轮询也不会好转
+    Misconfigured,
+}
+
+/// 沿错误链找出第一个能说明原因的错误；链上没有 I/O 错误（如 reqwest 自己的超时）时视为路由器尚未恢复。
+fn classify(err: &reqwest::Error) -> PollFailure {
+    if err.is_builder() {
+        return PollFailure::Misconfigured;
+    }
+    classify_chain(err.source())
+}
+
+fn classify_chain(mut source: Option<&(dyn std::error::Error + 'static)>) -> PollFailure {
+    while let Some(cause) = source {
+        // hyper-util 的解析失败没有单独的错误类型，只能看消息；它包着的 I/O 错误种类并不可靠。
+        if cause.to_string().starts_with("dns error") {
+            return PollFailure::Misconfigured;
+        }
+        if let Some(io) = cause.downcast_ref::<io::Error>() {
+            return classify_io(io.kind());
+        }
+        source = cause.source();
+    }
+    PollFailure::RouterDown
+}
+
+/// 只有重启期间路由器本身会造成的错误才继续轮询；本机网络不可用、地址不可用等其余错误一律提前放弃。
+fn classify_io(kind: io::ErrorKind) -> PollFailure {
+    match kind {
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::TimedOut
+        | io::ErrorKind::HostUnreachable => PollFailure::RouterDown,
+        _ => PollFailure::Misconfigured,
+    }
+}
+
+/// 整个等待窗口内路由器都可访问：重启请求被界面接受但没有生效。
+#[derive(Debug)]
+pub struct NeverWentDown {
+    timeout: Duration,
+}
+
+impl fmt::Display for NeverWentDown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "router never went offline within {}s",
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for NeverWentDown {}
+
+pub fn is_never_went_down(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<NeverWentDown>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Answer, Stub, client, closed_port_url, config};
+    use std::net::TcpListener;
+
+    /// 仿照 hyper-util 的 `ConnectError`：一条消息加上底层的 I/O 错误。
+    #[derive(Debug)]
+    struct ConnectError {
+        msg: &'static str,
+        cause: io::Error,
+    }
+
+    impl fmt::Display for ConnectError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.msg)
+        }
+    }
+
+    impl std::error::Error for ConnectError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.cause)
+        }
+    }
+
+    fn classify_connect(msg: &'static str, kind: io::ErrorKind) -> PollFailure {
+        let err = ConnectError {
+            msg,
+            cause: io::Error::from(kind),
+        };
+        classify_chain(Some(&err))
+    }
+
+    #[test]
+    fn router_side_errors_keep_polling() {
+        for kind in [
+            io::ErrorKind::ConnectionRefused,
+            io::ErrorKind::ConnectionReset,
+            io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::TimedOut,
+            io::ErrorKind::HostUnreachable,
+        ] {
+            assert_eq!(classify_io(kind), PollFailure::RouterDown, "{kind:?}");
+            assert_eq!(
+                classify_connect("tcp connect error", kind),
+                PollFailure::RouterDown,
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn local_network_errors_stop_polling() {
+        for kind in [
+            io::ErrorKind::NetworkUnreachable,
+            io::ErrorKind::NetworkDown,
+            io::ErrorKind::AddrNotAvailable,
+            io::ErrorKind::PermissionDenied,
+            io::ErrorKind::InvalidInput,
+        ] {
+            assert_eq!(classify_io(kind), PollFailure::Misconfigured, "{kind:?}");
+            assert_eq!(
+                classify_connect("tcp connect error", kind),
+                PollFailure::Misconfigured,
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn dns_errors_stop_polling() {
+        for kind in [io::ErrorKind::Other, io::ErrorKind::TimedOut] {
+            assert_eq!(
+                classify_connect("dns error", kind),
+                PollFailure::Misconfigured,
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_url_stops_polling() {
+        let err = Client::new().get("http://exa mple/").send().unwrap_err();
+        assert_eq!(classify(&err), PollFailure::Misconfigured);
+    }
+
+    #[test]
+    fn warmup_returns_once_the_router_starts_answering() {
+        let url = Url::parse(&closed_port_url()).unwrap();
+        let addr = format!("127.0.0.1:{}", url.port().unwrap());
+        // 首次轮询时还没有监听，约半秒后"路由器"的 Web 服务才起来。
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            let server = tiny_http::Server::http(addr.as_str()).unwrap();
+            started_tx.send(()).unwrap();
+            if let Ok(request) = server.recv() {
+                let _ = request.respond(tiny_http::Response::empty(503));
+            }
+        });
+        let started = Instant::now();
+        assert!(wait_until_reachable(
+            &Client::new(),
+            &url,
+            Duration::from_secs(10)
+        ));
+        assert!(started_rx.try_recv().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn warmup_gives_up_after_the_grace_period() {
+        let url = Url::parse(&closed_port_url()).unwrap();
+        let started = Instant::now();
+        assert!(!wait_until_reachable(
+            &Client::new(),
+            &url,
+            Duration::from_millis(300)
+        ));
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(300) && elapsed < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn refused_connection_keeps_polling() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let err = Client::new()
+            .get(format!("http://127.0.0.1:{port}/"))
+            .send()
+            .unwrap_err();
+        assert_eq!(classify(&err), PollFailure::RouterDown);
+    }
+
+    #[test]
+    fn router_that_never_answers_is_polled_until_the_deadline() {
+        let cfg = config(&closed_port_url(), &["--poll-interval-secs", "1"]);
+        let started = Instant::now();
+        let err = wait_for_online(&client(), &cfg, Duration::from_secs(1)).unwrap_err();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(err.to_string(), "router did not come back online within 1s");
+        assert!(!is_never_went_down(&err));
+    }
+
+    #[test]
+    fn router_that_stays_up_never_went_down() {
+        let stub = Stub::start(|_| Answer::ok("login"));
+        let cfg = config(&stub.base, &["--poll-interval-secs", "1"]);
+        let err = wait_for_online(&client(), &cfg, Duration::from_secs(1)).unwrap_err();
+        assert!(is_never_went_down(&err), "{err:#}");
+        assert!(stub.hits().len() >= 2);
+    }
+
+    #[test]
+    fn error_statuses_count_as_down_until_the_page_answers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let polls = AtomicUsize::new(0);
+        let stub = Stub::start(move |_| {
+            if polls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Answer::status(502, "")
+            } else {
+                Answer::ok("login")
+            }
+        });
+        let cfg = config(&stub.base, &["--poll-interval-secs", "1"]);
+        let downtime = wait_for_online(&client(), &cfg, Duration::from_secs(10)).unwrap();
+        // 掉线后又等了两个轮询间隔才恢复。
+        assert!(downtime >= Duration::from_secs(2), "{downtime:?}");
+        assert_eq!(stub.hits().len(), 3);
+    }
+}
@@ -0,0 +1,391 @@
+//! 不访问网络的子命令：直接运行编译好的二进制并检查输出与退出码。
+use std::process::{Command, Output};
+
+fn tianyi(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tianyi-auto"))
+        .args(args)
+        .env_remove("ROUTER_PASSWORD")
+        .output()
+        .expect("run tianyi-auto")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn next_prints_requested_number_of_runs() {
+    let output = tianyi(&["--cron", "0 4 * * Mon", "next", "3"]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines.len(), 3, "{out}");
+    assert!(
+        lines
+            .iter()
+            .all(|l| l.contains(" 04:00:00") && l.contains("(in "))
+    );
+}
+
+#[test]
+fn next_rejects_invalid_cron() {
+    let output = tianyi(&["--cron", "not a cron", "next"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid cron expression"));
+}
+
+#[test]
+fn empty_password_on_stdin_is_rejected() {
+    let output = tianyi(&["--password-stdin", "--show-config"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("password from stdin is empty"));
+}
+
+#[test]
+fn interval_and_cron_together_are_rejected() {
+    let output = tianyi(&["--interval", "12h", "--cron", "0 4 * * *", "next"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mutually exclusive"));
+}
+
+#[test]
+fn interval_conflicts_with_a_cron_from_the_config_file() {
+    let output = validate("interval", "cron = [\"0 4 * * *\"]\ninterval = \"12h\"\n");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mutually exclusive"));
+}
+
+#[test]
+fn once_never_parses_the_schedule() {
+    let output = tianyi(&[
+        "--password",
+        "x",
+        "--dry-run",
+        "--once",
+        "--cron",
+        "not a cron",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn once_exits_non_zero_when_the_router_is_down() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let output = tianyi(&[
+        "--password",
+        "x",
+        "--host",
+        &host,
+        "--max-retries",
+        "0",
+        "--once",
+    ]);
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn show_config_prints_resolved_urls_and_redacts_the_password() {
+    let output = tianyi(&[
+        "--password",
+        "S3cr3tPw",
+        "--host",
+        "http://10.0.0.1:8080/",
+        "--cron",
+        "0 4 * * Mon",
+        "--show-config",
+    ]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    assert!(!out.contains("S3cr3tPw"));
+    // 日志也写到 stdout，JSON 从单独一行的 `{` 开始。
+    let start = out.find("\n{").map_or(0, |i| i + 1);
+    let config: serde_json::Value = serde_json::from_str(&out[start..]).unwrap();
+    let router = &config["routers"][0];
+    assert_eq!(router["password"], "***");
+    assert_eq!(router["login_url"], "http://10.0.0.1:8080/");
+    assert_eq!(
+        router["reboot_url"],
+        "http://10.0.0.1:8080/common_page/gatewayManage.lua"
+    );
+    assert_eq!(config["schedule"]["cron"][0], "0 0 4 * * Mon");
+}
+
+/// 写入临时配置文件并运行 `validate`。
+fn validate(name: &str, toml: &str) -> Output {
+    let path = std::env::temp_dir().join(format!(
+        "tianyi-auto-cli-{}-{name}.toml",
+        std::process::id()
+    ));
+    std::fs::write(&path, toml).unwrap();
+    let output = tianyi(&["--config", path.to_str().unwrap(), "validate"]);
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+#[test]
+fn validate_accepts_a_good_config() {
+    let output = validate(
+        "good",
+        r#"
+password = "pw"
+host = "http://192.168.1.1"
+cron = ["0 4 * * Mon", "@daily"]
+webhook_url = "https://hooks.example.com/reboot"
+"#,
+    );
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("configuration OK"));
+}
+
+#[test]
+fn validate_lists_every_problem() {
+    let output = validate(
+        "bad",
+        r#"
+password = "pw"
+host = "192.168.1.1"
+cron = ["61 4 * * Mon"]
+webhook_url = "not a url"
+"#,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let out = stdout(&output);
+    let problems: Vec<_> = out.lines().filter(|l| l.starts_with("- ")).collect();
+    assert_eq!(problems.len(), 3, "{out}");
+    assert!(problems[0].starts_with("- router "));
+    assert!(problems[1].starts_with("- schedule: "));
+    assert!(problems[2].starts_with("- notifications: "));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("3 configuration problem(s) found"));
+}
+
+#[test]
+fn validate_reports_missing_password() {
+    let output = validate("no-password", "host = \"http://192.168.1.1\"\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).contains("- router "));
+}
+
+#[test]
+fn validate_rejects_unknown_keys() {
+    let output = validate("unknown-key", "pasword = \"pw\"\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pasword"));
+}
+
+#[test]
+fn env_var_overrides_config_file() {
+    // 本地桩路由器：把登录 POST 的请求体交回测试，其余请求一律应答成功。
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let (login_tx, login_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            let _ = std::io::Read::read_to_string(request.as_reader(), &mut body);
+            let response = match (request.method(), request.url()) {
+                (tiny_http::Method::Get, "/") => tiny_http::Response::from_string(
+                    r#"<form><input type="hidden" name="Frm_Logintoken" value="7"></form>"#,
+                ),
+                (tiny_http::Method::Post, "/") => {
+                    let _ = login_tx.send(body);
+                    tiny_http::Response::from_string("welcome").with_header(
+                        tiny_http::Header::from_bytes("Set-Cookie", "SID=abc; Path=/").unwrap(),
+                    )
+                }
+                _ => tiny_http::Response::from_string(r#"{"IF_ERRORID":0}"#),
+            };
+            let _ = request.respond(response);
+        }
+    });
+
+    let path = std::env::temp_dir().join(format!(
+        "tianyi-auto-cli-{}-env-override.toml",
+        std::process::id()
+    ));
+    std::fs::write(&path, "password = \"from-file\"\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_tianyi-auto"))
+        .args([
+            "--config",
+            path.to_str().unwrap(),
+            "--host",
+            &host,
+            "--once",
+        ])
+        .env("ROUTER_PASSWORD", "from-env")
+        .output()
+        .expect("run tianyi-auto");
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let login = login_rx.try_recv().expect("login request");
+    assert!(login.contains("Password=from-env"), "{login}");
+}
+
+#[test]
+fn simulate_lists_fire_times_in_window() {
+    let output = tianyi(&[
+        "--cron",
+        "0 4 * * Mon",
+        "--timezone",
+        "Asia/Shanghai",
+        "simulate",
+        "--from",
+        "2030-01-06T00:00:00+08:00",
+        "--until",
+        "2030-01-21T12:00:00+08:00",
+    ]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    let scheduled: Vec<_> = out.lines().filter(|l| l.ends_with("scheduled")).collect();
+    assert_eq!(
+        scheduled,
+        [
+            "2030-01-07 04:00:00 CST  scheduled",
+            "2030-01-14 04:00:00 CST  scheduled",
+            "2030-01-21 04:00:00 CST  scheduled",
+        ],
+        "{out}"
+    );
+    assert!(out.contains("(3 run(s) counted toward --max-runs in this window)"));
+}
+
+#[test]
+fn simulate_stops_at_max_runs_counting_run_now() {
+    let output = tianyi(&[
+        "--cron",
+        "0 4 * * *",
+        "--timezone",
+        "Asia/Shanghai",
+        "--run-now",
+        "--count-run-now",
+        "--max-runs",
+        "2",
+        "simulate",
+        "--from",
+        "2030-01-06T12:00:00+08:00",
+        "--until",
+        "2030-01-31T00:00:00+08:00",
+    ]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    assert!(
+        out.contains("2030-01-06 12:00:00 CST  immediate (--run-now)"),
+        "{out}"
+    );
+    let scheduled: Vec<_> = out.lines().filter(|l| l.ends_with("scheduled")).collect();
+    assert_eq!(scheduled, ["2030-01-07 04:00:00 CST  scheduled"], "{out}");
+    assert!(out.contains("(max runs reached after 2 run(s), scheduler would exit)"));
+}
+
+#[test]
+fn simulate_rejects_a_reversed_window() {
+    let output = tianyi(&[
+        "simulate",
+        "--from",
+        "2030-01-07 00:00",
+        "--until",
+        "2030-01-06 00:00",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--until must not be earlier"));
+}
+
+#[test]
+fn next_rejects_a_cron_pinned_to_a_past_year() {
+    let output = tianyi(&["--cron", "0 0 4 1 1 * 2020", "next"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has no future occurrences"));
+}
+
+#[test]
+fn set_password_requires_a_keyring_service() {
+    let output = tianyi(&["set-password"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires --keyring-service"));
+}
+
+#[test]
+fn print_request_works_with_dry_run() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let output = tianyi(&[
+        "--password",
+        "S3cr3tPw",
+        "--host",
+        &host,
+        "--max-retries",
+        "0",
+        "--dry-run",
+        "--print-request",
+        "--once",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let out = stdout(&output);
+    assert!(!out.contains("S3cr3tPw"));
+    let curl_after = |label: &str| {
+        let mut lines = out.lines().skip_while(|l| *l != label);
+        lines
+            .next()
+            .unwrap_or_else(|| panic!("no {label} in {out}"));
+        lines.next().unwrap().to_string()
+    };
+    let login = curl_after("# login");
+    assert!(
+        login.starts_with(&format!("curl -X POST '{host}/'")),
+        "{login}"
+    );
+    assert!(login.contains("-H 'user-agent: Mozilla/5.0"), "{login}");
+    assert!(login.contains("Password=***"), "{login}");
+    let reboot = curl_after("# reboot");
+    assert!(
+        reboot.starts_with(&format!(
+            "curl -X POST '{host}/common_page/gatewayManage.lua"
+        )),
+        "{reboot}"
+    );
+    assert!(
+        reboot.contains("-H 'x-requested-with: XMLHttpRequest'"),
+        "{reboot}"
+    );
+    assert!(reboot.contains("HG_COMMAND_REBOOT"), "{reboot}");
+}
+
+#[test]
+fn list_commands_shows_every_built_in_command() {
+    let output = tianyi(&["list-commands"]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    let names: Vec<_> = out
+        .lines()
+        .filter(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_lowercase() || c == '-'))
+        .collect();
+    assert_eq!(names, ["reboot", "custom"], "{out}");
+    assert!(out.contains("    jsonCfg={"), "{out}");
+    assert!(out.contains(r#""CmdType":"<--reboot-cmdtype>""#), "{out}");
+    assert!(!out.contains("HG_COMMAND_WAN_RECONNECT"), "{out}");
+    assert!(out.contains("jsonCfg=<--reboot-payload>"));
+    assert!(out.contains(r#"--reboot-cmdtype is currently "HG_COMMAND_REBOOT""#));
+}
+
+#[test]
+fn list_commands_follows_the_reboot_body_mode() {
+    let output = tianyi(&["--reboot-body-mode", "raw-json", "list-commands"]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    assert!(!out.contains("jsonCfg="), "{out}");
+    assert!(out.contains("    <--reboot-payload>"), "{out}");
+}